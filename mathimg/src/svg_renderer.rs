@@ -5,11 +5,12 @@ use svg;
 
 use std::path;
 
+use math_render::math_box::Vector as BoxVector;
 use math_render::math_box::*;
 use math_render::shaper::*;
 
 use self::svg::node::element::path::Data;
-use self::svg::node::element::{Group, Line, Path, Rectangle};
+use self::svg::node::element::{Ellipse, Group, Line, Path, Polygon, Rectangle};
 use self::svg::node::Node;
 use self::svg::Document;
 
@@ -58,8 +59,14 @@ pub fn render<'a, T: AsRef<path::Path>>(
         .set("stroke-dasharray", "140,70")
         .set("stroke-linecap", "round");
 
+    let mut background_group = Group::new().set("stroke", "none");
     let mut black_group = Group::new().set("fill", "black").set("stroke", "none");
 
+    // backgrounds are painted first, so that they lie behind everything else
+    generate_svg(&mut background_group, &math_box, &|group, math_box| {
+        draw_background(group, math_box)
+    });
+
     generate_svg(&mut italic_cor_group, &math_box, &|group, math_box| {
         draw_italic_correction(group, math_box)
     });
@@ -95,6 +102,7 @@ pub fn render<'a, T: AsRef<path::Path>>(
     }
 
     //    document.append(italic_cor_group);
+    document.append(background_group);
     document.append(black_group);
 
     if flags.show_top_accent_attachment {
@@ -129,21 +137,107 @@ where
     }
 }
 
-fn draw_filled<'a, T: Node>(doc: &mut T, math_box: &MathBox) {
-    if let MathBoxContent::Drawable(Drawable::Line { vector, thickness }) = *math_box.content() {
-        let line = Line::new()
-            .set("x1", math_box.origin.x)
-            .set("y1", math_box.origin.y - math_box.extents().ascent)
-            .set("x2", vector.x + math_box.origin.x)
-            .set(
-                "y2",
-                math_box.origin.y - math_box.extents().ascent + vector.y,
-            )
-            .set("stroke-width", thickness)
-            .set("stroke", "black")
-            .set("z-index", 1);
+/// Returns the colour of a box as SVG paint and opacity. Boxes without a colour are black.
+fn paint(math_box: &MathBox) -> (String, f32) {
+    match math_box.color() {
+        Some(color) => (
+            format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue),
+            f32::from(color.alpha) / 255.,
+        ),
+        None => ("black".to_string(), 1.),
+    }
+}
+
+fn draw_background<'a, T: Node>(doc: &mut T, math_box: &MathBox) {
+    if let MathBoxContent::Drawable(Drawable::FilledRect { size }) = *math_box.content() {
+        let (fill, opacity) = paint(math_box);
+        let rect = Rectangle::new()
+            .set("x", math_box.origin.x)
+            .set("y", math_box.origin.y)
+            .set("width", size.x)
+            .set("height", size.y)
+            .set("fill", fill)
+            .set("fill-opacity", opacity);
+        doc.append(rect);
+    }
+}
 
-        doc.append(line);
+fn draw_filled<'a, T: Node>(doc: &mut T, math_box: &MathBox) {
+    let origin = math_box.origin;
+    let (color, opacity) = paint(math_box);
+    match *math_box.content() {
+        MathBoxContent::Drawable(Drawable::Line { vector, thickness }) => {
+            doc.append(stroked_line(origin, vector, thickness, math_box));
+        }
+        MathBoxContent::Drawable(Drawable::Arrow {
+            vector,
+            thickness,
+            head_size,
+        }) => {
+            let length = ((vector.x as f32).powi(2) + (vector.y as f32).powi(2)).sqrt();
+            if length == 0. {
+                return;
+            }
+            // unit vectors along and perpendicular to the arrow
+            let (dx, dy) = (vector.x as f32 / length, vector.y as f32 / length);
+            let head_size = head_size as f32;
+            let tip = ((origin.x + vector.x) as f32, (origin.y + vector.y) as f32);
+            let base = (tip.0 - dx * head_size, tip.1 - dy * head_size);
+
+            // the line ends at the base of the arrowhead
+            let line_vector = BoxVector {
+                x: (base.0 - origin.x as f32) as i32,
+                y: (base.1 - origin.y as f32) as i32,
+            };
+            doc.append(stroked_line(origin, line_vector, thickness, math_box));
+
+            let points = format!(
+                "{},{} {},{} {},{}",
+                tip.0,
+                tip.1,
+                base.0 - dy * head_size / 2.,
+                base.1 + dx * head_size / 2.,
+                base.0 + dy * head_size / 2.,
+                base.1 - dx * head_size / 2.
+            );
+            let head = Polygon::new()
+                .set("points", points)
+                .set("fill", color)
+                .set("fill-opacity", opacity);
+            doc.append(head);
+        }
+        MathBoxContent::Drawable(Drawable::Rect {
+            size,
+            corner_radius,
+            thickness,
+        }) => {
+            // the outline is drawn inside of the rectangle
+            let inset = thickness as i32 / 2;
+            let rect = Rectangle::new()
+                .set("x", origin.x + inset)
+                .set("y", origin.y + inset)
+                .set("width", size.x - 2 * inset)
+                .set("height", size.y - 2 * inset)
+                .set("rx", corner_radius)
+                .set("stroke-width", thickness)
+                .set("stroke", color)
+                .set("stroke-opacity", opacity)
+                .set("fill", "none");
+            doc.append(rect);
+        }
+        MathBoxContent::Drawable(Drawable::Ellipse { size, thickness }) => {
+            let ellipse = Ellipse::new()
+                .set("cx", origin.x + size.x / 2)
+                .set("cy", origin.y + size.y / 2)
+                .set("rx", (size.x - thickness as i32) / 2)
+                .set("ry", (size.y - thickness as i32) / 2)
+                .set("stroke-width", thickness)
+                .set("stroke", color)
+                .set("stroke-opacity", opacity)
+                .set("fill", "none");
+            doc.append(ellipse);
+        }
+        _ => {}
     }
     if let MathBoxContent::Empty(_) = *math_box.content() {
         let _rect = Rectangle::new()
@@ -159,6 +253,24 @@ fn draw_filled<'a, T: Node>(doc: &mut T, math_box: &MathBox) {
     }
 }
 
+fn stroked_line(
+    origin: BoxVector<i32>,
+    vector: BoxVector<i32>,
+    thickness: u32,
+    math_box: &MathBox,
+) -> Line {
+    let (color, opacity) = paint(math_box);
+    Line::new()
+        .set("x1", origin.x)
+        .set("y1", origin.y)
+        .set("x2", origin.x + vector.x)
+        .set("y2", origin.y + vector.y)
+        .set("stroke-width", thickness)
+        .set("stroke", color)
+        .set("stroke-opacity", opacity)
+        .set("z-index", 1)
+}
+
 fn draw_ink_rect<'a, T: Node>(group: &mut T, math_box: &MathBox) {
     if let MathBoxContent::Drawable(Drawable::Glyphs { .. }) = *math_box.content() {
         let ink_rect = Rectangle::new()
@@ -253,6 +365,11 @@ fn draw_glyph<'a, T: Node>(doc: &mut T, math_box: &MathBox, face: &FT_Face<'_>)
         };
 
     let mut group = Group::new();
+    if math_box.color().is_some() {
+        let (fill, opacity) = paint(math_box);
+        group.assign("fill", fill);
+        group.assign("fill-opacity", opacity);
+    }
     {
         let origin = math_box.origin;
 
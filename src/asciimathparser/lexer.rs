@@ -0,0 +1,112 @@
+use super::symbols::{find_symbol, Kind, Symbol};
+use crate::mathmlparser::{ErrorType, ParsingError};
+
+/// The smallest unit of AsciiMath input. Every token is stored together with the byte offset in
+/// the source where it starts.
+#[derive(Debug, Clone)]
+pub enum Token {
+    Symbol(&'static Symbol),
+    /// A number with an optional decimal point.
+    Number(String),
+    /// Text in double quotes or the argument of `text`.
+    Text(String),
+    /// A letter or any other character that is not part of a symbol.
+    Character(char),
+}
+
+/// Splits AsciiMath input into tokens. Whitespace only separates tokens and is dropped.
+///
+/// At every position the longest matching symbol wins, so `sin` is a function and `int` an
+/// integral rather than `in` followed by `t`.
+pub fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParsingError> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    while let Some(chr) = source[position..].chars().next() {
+        let rest = &source[position..];
+        let (token, length) = if chr.is_whitespace() {
+            position += chr.len_utf8();
+            continue;
+        } else if chr == '"' {
+            let length = match rest[1..].find('"') {
+                Some(length) => length,
+                None => return Err(unterminated(source.len(), "\"")),
+            };
+            (Token::Text(rest[1..length + 1].to_owned()), length + 2)
+        } else if chr.is_ascii_digit() {
+            let mut length = rest
+                .find(|chr: char| !chr.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let fraction = &rest[length..];
+            if fraction.starts_with('.')
+                && fraction[1..].starts_with(|chr: char| chr.is_ascii_digit())
+            {
+                length += 1 + fraction[1..]
+                    .find(|chr: char| !chr.is_ascii_digit())
+                    .unwrap_or(fraction.len() - 1);
+            }
+            (Token::Number(rest[..length].to_owned()), length)
+        } else if let Some(symbol) = find_symbol(rest) {
+            if symbol.kind == Kind::Text {
+                // the argument of `text` is read verbatim
+                let argument = rest[symbol.input.len()..].trim_start();
+                let offset = rest.len() - argument.len();
+                if !argument.starts_with('(') {
+                    return Err(ParsingError {
+                        position: Some(position + offset),
+                        error_type: ErrorType::OtherError(format!(
+                            "\"{}\" has to be followed by text in parentheses.",
+                            symbol.input
+                        )),
+                    });
+                }
+                let length = match argument.find(')') {
+                    Some(length) => length,
+                    None => return Err(unterminated(source.len(), ")")),
+                };
+                (
+                    Token::Text(argument[1..length].to_owned()),
+                    offset + length + 1,
+                )
+            } else {
+                (Token::Symbol(symbol), symbol.input.len())
+            }
+        } else {
+            (Token::Character(chr), chr.len_utf8())
+        };
+        tokens.push((token, position));
+        position += length;
+    }
+    Ok(tokens)
+}
+
+fn unterminated(position: usize, delimiter: &str) -> ParsingError {
+    ParsingError {
+        position: Some(position),
+        error_type: ErrorType::OtherError(format!("Missing closing \"{}\".", delimiter)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("sin x_12.5 >= \"a b\" text( c )int").unwrap();
+        let description = tokens
+            .iter()
+            .map(|&(ref token, position)| match *token {
+                Token::Symbol(symbol) => format!("{}@{}", symbol.input, position),
+                Token::Number(ref number) => format!("n{}@{}", number, position),
+                Token::Text(ref text) => format!("t{}@{}", text, position),
+                Token::Character(chr) => format!("c{}@{}", chr, position),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            description,
+            vec!["sin@0", "cx@4", "_@5", "n12.5@6", ">=@11", "ta b@14", "t c @20", "int@29"]
+        );
+        assert!(tokenize("\"text").is_err());
+        assert!(tokenize("text x").is_err());
+    }
+}
@@ -0,0 +1,655 @@
+//! A parser for AsciiMath, the plain text notation in which `sum_(i=1)^n i^3` is a sum with
+//! limits.
+//!
+//! Like the TeX parser this builds the tree that the MathML parser builds for the equivalent
+//! presentation markup, so operators get their spacing and stretchiness from the operator
+//! dictionary and `user_data` values are handed out by a `ParseContext`.
+
+mod lexer;
+mod symbols;
+
+use std;
+
+use self::lexer::Token;
+use self::symbols::{Binary, Kind, Symbol, Unary};
+use crate::mathmlparser::{
+    Attributes, ErrorType, Flags, Form, OperatorAttributes, ParseContext, ParsingError,
+    SchemaAttributes, TokenAttributes,
+};
+use crate::types::{
+    HorizontalAlign, Length, MathExpression, MathItem, Notation, Table, TableCell, TableRow,
+};
+use crate::unicode_math::Family;
+
+pub type Result<T> = std::result::Result<T, ParsingError>;
+
+/// Parses a formula written in AsciiMath.
+///
+/// Errors report the byte offset into `source` at which they occurred.
+///
+/// # Examples
+/// ```
+/// # use math_render::asciimathparser;
+/// let expr = asciimathparser::parse("sum_(i=1)^n i^3=((n(n+1))/2)^2").unwrap();
+/// assert_eq!(asciimathparser::parse("x^").unwrap_err().position, Some(2));
+/// ```
+pub fn parse(source: &str) -> Result<MathExpression> {
+    let mut parser = Parser {
+        tokens: lexer::tokenize(source)?,
+        index: 0,
+        context: ParseContext::default(),
+        end_position: source.len(),
+    };
+    let list = parser.parse_expression(Terminator::EndOfInput)?;
+    parser.context.schema("math", Attributes::default(), list)
+}
+
+/// The token that ends an expression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Terminator {
+    EndOfInput,
+    RightBracket,
+    /// A comma or a right bracket inside of a matrix row.
+    Cell,
+}
+
+/// A parsed simple expression.
+///
+/// Brackets around the arguments of fractions, scripts and commands are not displayed, e.g. the
+/// numerator of `(a+b)/2` is just `a+b`. Groups in brackets are therefore kept apart until it is
+/// known how they are used.
+enum Node {
+    Expression {
+        expr: MathExpression,
+        /// True if scripts are placed above and below the expression.
+        under_over: bool,
+    },
+    Group {
+        open: &'static Symbol,
+        content: Vec<MathExpression>,
+        /// The closing bracket, which is missing if the input ended before it.
+        close: Option<&'static Symbol>,
+    },
+}
+
+impl Node {
+    fn into_expression(self, context: &mut ParseContext) -> Result<MathExpression> {
+        match self {
+            Node::Expression { expr, .. } => Ok(expr),
+            Node::Group {
+                open,
+                content,
+                close,
+            } => {
+                let mut list = Vec::with_capacity(content.len() + 2);
+                list.extend(fence(context, open, Form::Prefix)?);
+                list.extend(content);
+                if let Some(close) = close {
+                    list.extend(fence(context, close, Form::Postfix)?);
+                }
+                context.schema("mrow", Attributes::default(), list)
+            }
+        }
+    }
+
+    fn into_argument(self, context: &mut ParseContext) -> Result<MathExpression> {
+        match self {
+            Node::Group {
+                content,
+                close: Some(_),
+                ..
+            } => context.schema("mrow", Attributes::default(), content),
+            node => node.into_expression(context),
+        }
+    }
+
+    fn is_under_over(&self) -> bool {
+        match *self {
+            Node::Expression { under_over, .. } => under_over,
+            Node::Group { .. } => false,
+        }
+    }
+}
+
+/// Creates the operator for a bracket. The invisible brackets `{:` and `:}` yield `None`.
+fn fence(
+    context: &mut ParseContext,
+    bracket: &Symbol,
+    form: Form,
+) -> Result<Option<MathExpression>> {
+    if bracket.output.is_empty() {
+        return Ok(None);
+    }
+    let attributes = OperatorAttributes {
+        form: Some(form),
+        ..Default::default()
+    };
+    context.operator(bracket.output, attributes).map(Some)
+}
+
+fn is_comma(token: &Token) -> bool {
+    match *token {
+        Token::Symbol(symbol) => symbol.input == ",",
+        _ => false,
+    }
+}
+
+fn kind(token: &Token) -> Option<Kind> {
+    match *token {
+        Token::Symbol(symbol) => Some(symbol.kind),
+        _ => None,
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    context: ParseContext,
+    end_position: usize,
+}
+
+impl Parser {
+    fn error<T, S: ToString>(&self, position: usize, message: S) -> Result<T> {
+        Err(ParsingError {
+            position: Some(position),
+            error_type: ErrorType::OtherError(message.to_string()),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn peek_kind(&self) -> Option<Kind> {
+        self.peek().and_then(kind)
+    }
+
+    fn next(&mut self) -> Result<(Token, usize)> {
+        match self.tokens.get(self.index) {
+            Some(token) => {
+                self.index += 1;
+                Ok(token.clone())
+            }
+            None => Err(ParsingError {
+                position: Some(self.end_position),
+                error_type: ErrorType::UnexpectedEndOfInput,
+            }),
+        }
+    }
+
+    fn parse_expression(&mut self, terminator: Terminator) -> Result<Vec<MathExpression>> {
+        let mut list = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Ok(list),
+                Some(&Token::Symbol(symbol)) if symbol.kind == Kind::RightBracket => {
+                    if terminator != Terminator::EndOfInput {
+                        return Ok(list);
+                    }
+                    // a closing bracket without an opening one is displayed as it is
+                    self.index += 1;
+                    let bracket = self.context.operator(symbol.output, Default::default())?;
+                    list.push(bracket);
+                    continue;
+                }
+                Some(token) if terminator == Terminator::Cell && is_comma(token) => {
+                    return Ok(list)
+                }
+                _ => {}
+            }
+
+            let node = self.parse_intermediate()?;
+            if self.peek_kind() == Some(Kind::Fraction) {
+                self.index += 1;
+                self.check_argument()?;
+                let numerator = node.into_argument(&mut self.context)?;
+                let denominator = self
+                    .parse_intermediate()?
+                    .into_argument(&mut self.context)?;
+                let fraction = self.context.schema(
+                    "mfrac",
+                    Attributes::default(),
+                    vec![numerator, denominator],
+                )?;
+                list.push(fraction);
+            } else {
+                list.push(node.into_expression(&mut self.context)?);
+            }
+        }
+    }
+
+    /// Parses a simple expression together with its sub- and superscripts.
+    fn parse_intermediate(&mut self) -> Result<Node> {
+        let nucleus = match self.peek_kind() {
+            Some(Kind::Subscript) | Some(Kind::Superscript) => Node::Expression {
+                expr: self
+                    .context
+                    .schema("mrow", Attributes::default(), Vec::new())?,
+                under_over: false,
+            },
+            _ => self.parse_simple()?,
+        };
+
+        let mut subscript = None;
+        let mut superscript = None;
+        loop {
+            let (script, message) = match self.peek_kind() {
+                Some(Kind::Subscript) => (&mut subscript, "Double subscript."),
+                Some(Kind::Superscript) => (&mut superscript, "Double superscript."),
+                _ => break,
+            };
+            let (_, position) = self.tokens[self.index];
+            if script.is_some() {
+                return self.error(position, message);
+            }
+            self.index += 1;
+            *script = Some(self.parse_argument()?);
+        }
+
+        if subscript.is_none() && superscript.is_none() {
+            return Ok(nucleus);
+        }
+        let under_over = nucleus.is_under_over();
+        let nucleus = nucleus.into_expression(&mut self.context)?;
+        let (identifier, arguments) = match (subscript, superscript) {
+            (Some(sub), None) => (
+                if under_over { "munder" } else { "msub" },
+                vec![nucleus, sub],
+            ),
+            (None, Some(sup)) => (
+                if under_over { "mover" } else { "msup" },
+                vec![nucleus, sup],
+            ),
+            (Some(sub), Some(sup)) => (
+                if under_over { "munderover" } else { "msubsup" },
+                vec![nucleus, sub, sup],
+            ),
+            (None, None) => unreachable!(),
+        };
+        Ok(Node::Expression {
+            expr: self
+                .context
+                .schema(identifier, Attributes::default(), arguments)?,
+            under_over: false,
+        })
+    }
+
+    /// Returns an error if the next token cannot start the argument of a command, a script or a
+    /// fraction.
+    fn check_argument(&self) -> Result<()> {
+        match self.tokens.get(self.index) {
+            None => Err(ParsingError {
+                position: Some(self.end_position),
+                error_type: ErrorType::UnexpectedEndOfInput,
+            }),
+            Some(&(ref token, position)) => match kind(token) {
+                Some(Kind::RightBracket)
+                | Some(Kind::Subscript)
+                | Some(Kind::Superscript)
+                | Some(Kind::Fraction) => self.error(position, "Missing argument."),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    fn parse_argument(&mut self) -> Result<MathExpression> {
+        self.check_argument()?;
+        let node = self.parse_simple()?;
+        node.into_argument(&mut self.context)
+    }
+
+    fn parse_simple(&mut self) -> Result<Node> {
+        let (token, _) = self.next()?;
+        let symbol = match token {
+            Token::Number(number) => return self.expression(|context| context.number(&number)),
+            Token::Text(text) => {
+                return self
+                    .expression(|context| context.token("mtext", text, TokenAttributes::default()))
+            }
+            Token::Character(chr) if chr.is_alphabetic() => {
+                return self.expression(|context| context.identifier(&chr.to_string(), None))
+            }
+            Token::Character(chr) => {
+                let text = chr.to_string();
+                return self.expression(|context| context.operator(&text, Default::default()));
+            }
+            Token::Symbol(symbol) => symbol,
+        };
+
+        let expr = match symbol.kind {
+            Kind::Identifier => self.context.identifier(symbol.output, None)?,
+            Kind::UprightIdentifier => self
+                .context
+                .identifier(symbol.output, Some(Family::Normal))?,
+            Kind::Operator | Kind::RightBracket | Kind::Subscript | Kind::Superscript => {
+                self.context.operator(symbol.output, Default::default())?
+            }
+            Kind::Fraction => self.context.operator("/", Default::default())?,
+            Kind::UnderOver => {
+                return Ok(Node::Expression {
+                    expr: self.context.operator(symbol.output, Default::default())?,
+                    under_over: true,
+                })
+            }
+            Kind::Function { limits } => {
+                return Ok(Node::Expression {
+                    expr: self.context.function(symbol.output, limits)?,
+                    under_over: limits,
+                })
+            }
+            Kind::Space(width) => self.context.space(Length::em(width))?,
+            Kind::LeftBracket => return self.parse_group(symbol),
+            Kind::Unary(unary) => return self.parse_unary(symbol, unary),
+            Kind::Binary(binary) => self.parse_binary(binary)?,
+            Kind::Text => unreachable!("text is turned into a text token by the lexer"),
+        };
+        Ok(Node::Expression {
+            expr,
+            under_over: false,
+        })
+    }
+
+    fn expression<F>(&mut self, build: F) -> Result<Node>
+    where
+        F: FnOnce(&mut ParseContext) -> Result<MathExpression>,
+    {
+        Ok(Node::Expression {
+            expr: build(&mut self.context)?,
+            under_over: false,
+        })
+    }
+
+    fn parse_group(&mut self, open: &'static Symbol) -> Result<Node> {
+        let content = if self.is_matrix() {
+            let mut table = self.parse_matrix()?;
+            if open.input == "{" {
+                // a matrix in a single brace is a case distinction
+                table.column_align = vec![HorizontalAlign::Left];
+            }
+            let user_data = self.context.new_user_data();
+            vec![MathExpression::new(MathItem::Table(table), user_data)]
+        } else {
+            self.parse_expression(Terminator::RightBracket)?
+        };
+        let close = match self.tokens.get(self.index) {
+            Some(&(Token::Symbol(symbol), _)) if symbol.kind == Kind::RightBracket => {
+                self.index += 1;
+                Some(symbol)
+            }
+            _ => None,
+        };
+        Ok(Node::Group {
+            open,
+            content,
+            close,
+        })
+    }
+
+    /// Returns true if the tokens after a left bracket form a matrix like `[(a,b),(c,d)]`: at
+    /// least two rows in brackets that all have the same number of columns.
+    fn is_matrix(&self) -> bool {
+        let mut index = self.index;
+        let mut columns = None;
+        let mut rows = 0;
+        loop {
+            match self.tokens.get(index).and_then(|(token, _)| kind(token)) {
+                Some(Kind::LeftBracket) => {}
+                _ => return false,
+            }
+            let mut depth = 0;
+            let mut commas = 0;
+            loop {
+                index += 1;
+                let token = match self.tokens.get(index) {
+                    Some((token, _)) => token,
+                    None => return false,
+                };
+                match kind(token) {
+                    Some(Kind::LeftBracket) => depth += 1,
+                    Some(Kind::RightBracket) if depth == 0 => break,
+                    Some(Kind::RightBracket) => depth -= 1,
+                    _ if depth == 0 && is_comma(token) => commas += 1,
+                    _ => {}
+                }
+            }
+            if columns.unwrap_or(commas) != commas {
+                return false;
+            }
+            columns = Some(commas);
+            rows += 1;
+
+            index += 1;
+            match self.tokens.get(index) {
+                Some((token, _)) if is_comma(token) => index += 1,
+                Some((token, _)) if kind(token) == Some(Kind::RightBracket) => return rows > 1,
+                _ => return false,
+            }
+        }
+    }
+
+    fn parse_matrix(&mut self) -> Result<Table> {
+        let mut table = Table::default();
+        loop {
+            // the bracket that starts the row
+            self.next()?;
+            let mut cells = Vec::new();
+            loop {
+                let content = self.parse_expression(Terminator::Cell)?;
+                cells.push(TableCell {
+                    content: Some(
+                        self.context
+                            .schema("mrow", Attributes::default(), content)?,
+                    ),
+                    ..Default::default()
+                });
+                let (token, _) = self.next()?;
+                if !is_comma(&token) {
+                    break;
+                }
+            }
+            table.rows.push(TableRow {
+                cells,
+                ..Default::default()
+            });
+            match self.peek() {
+                Some(token) if is_comma(token) => self.index += 1,
+                _ => return Ok(table),
+            }
+        }
+    }
+
+    fn parse_unary(&mut self, symbol: &Symbol, unary: Unary) -> Result<Node> {
+        if let Unary::Font(family) = unary {
+            let inherited_math_variant = self.context.math_variant;
+            self.context.math_variant = Some(family);
+            let result = self.parse_argument();
+            self.context.math_variant = inherited_math_variant;
+            return Ok(Node::Expression {
+                expr: result?,
+                under_over: false,
+            });
+        }
+
+        let argument = self.parse_argument()?;
+        let (expr, under_over) = match unary {
+            Unary::Sqrt => (
+                self.context
+                    .schema("msqrt", Attributes::default(), vec![argument])?,
+                false,
+            ),
+            Unary::Over {
+                stretchy,
+                under_over,
+            }
+            | Unary::Under {
+                stretchy,
+                under_over,
+            } => {
+                let mut attributes = OperatorAttributes::default();
+                attributes.set_user_override(Flags::STRETCHY, stretchy);
+                let accent = self.context.operator(symbol.output, attributes)?;
+                let is_over = matches!(unary, Unary::Over { .. });
+                let schema = SchemaAttributes {
+                    accent: is_over,
+                    accentunder: !is_over,
+                    ..Default::default()
+                };
+                let attributes = Attributes {
+                    schema,
+                    ..Default::default()
+                };
+                let identifier = if is_over { "mover" } else { "munder" };
+                let expr = self
+                    .context
+                    .schema(identifier, attributes, vec![argument, accent])?;
+                (expr, under_over)
+            }
+            Unary::Fences(open, close) => {
+                let mut list = Vec::with_capacity(3);
+                for &(chr, form) in &[(open, Form::Prefix), (close, Form::Postfix)] {
+                    let attributes = OperatorAttributes {
+                        form: Some(form),
+                        ..Default::default()
+                    };
+                    list.push(self.context.operator(&chr.to_string(), attributes)?);
+                }
+                list.insert(1, argument);
+                let expr = self.context.schema("mrow", Attributes::default(), list)?;
+                (expr, false)
+            }
+            Unary::Cancel => {
+                let schema = SchemaAttributes {
+                    notation: Some(vec![Notation::UpDiagonalStrike]),
+                    ..Default::default()
+                };
+                let attributes = Attributes {
+                    schema,
+                    ..Default::default()
+                };
+                let expr = self
+                    .context
+                    .schema("menclose", attributes, vec![argument])?;
+                (expr, false)
+            }
+            Unary::Font(_) => unreachable!(),
+        };
+        Ok(Node::Expression { expr, under_over })
+    }
+
+    fn parse_binary(&mut self, binary: Binary) -> Result<MathExpression> {
+        let first = self.parse_argument()?;
+        let second = self.parse_argument()?;
+        let (identifier, arguments) = match binary {
+            Binary::Frac => ("mfrac", vec![first, second]),
+            Binary::Root => ("mroot", vec![second, first]),
+            Binary::Over => ("mover", vec![second, first]),
+            Binary::Under => ("munder", vec![second, first]),
+        };
+        self.context
+            .schema(identifier, Attributes::default(), arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    fn list(expr: &MathExpression) -> &[MathExpression] {
+        match *expr.item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        }
+    }
+
+    fn text(expr: &MathExpression) -> &str {
+        match *expr.item {
+            MathItem::Field(Field::Unicode(ref text)) => text,
+            ref other_item => panic!("Expected Field. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_brackets_of_arguments() {
+        let expr = parse("sum_(i=1)^n i^3=((n(n+1))/2)^2").unwrap();
+        let items = list(&expr);
+        assert_eq!(items.len(), 4);
+        match *items[0].item {
+            MathItem::OverUnder(ref over_under) => {
+                assert!(over_under.is_limits);
+                // the brackets around the limit are dropped
+                assert_eq!(list(over_under.under.as_ref().unwrap()).len(), 3);
+            }
+            ref other_item => panic!("Expected OverUnder. Found {:?}", other_item),
+        }
+        let fenced = match *items[3].item {
+            MathItem::Atom(Atom {
+                nucleus: Some(ref nucleus),
+                ..
+            }) => list(nucleus),
+            ref other_item => panic!("Expected Atom. Found {:?}", other_item),
+        };
+        // the outer parentheses are kept, the ones around the numerator are dropped
+        assert_eq!(fenced.len(), 3);
+        match *fenced[1].item {
+            MathItem::GeneralizedFraction(GeneralizedFraction {
+                numerator: Some(ref numerator),
+                ..
+            }) => assert_eq!(list(numerator).len(), 2),
+            ref other_item => panic!("Expected fraction. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_matrix() {
+        let expr = parse("[(a,b),(c,d)]").unwrap();
+        match *list(&expr)[1].item {
+            MathItem::Table(ref table) => {
+                assert_eq!(table.rows.len(), 2);
+                assert!(table.rows.iter().all(|row| row.cells.len() == 2));
+            }
+            ref other_item => panic!("Expected Table. Found {:?}", other_item),
+        }
+        let expr = parse("{(x, x >= 0),(-x, \"else\"):}").unwrap();
+        match *list(&expr)[1].item {
+            MathItem::Table(ref table) => {
+                assert_eq!(table.column_align, vec![HorizontalAlign::Left])
+            }
+            ref other_item => panic!("Expected Table. Found {:?}", other_item),
+        }
+        // rows with different numbers of columns are not a matrix
+        let expr = parse("((a,b),(c))").unwrap();
+        assert!(!list(&expr)
+            .iter()
+            .any(|expr| matches!(*expr.item, MathItem::Table(_))));
+    }
+
+    #[test]
+    fn test_commands() {
+        let expr = parse("root(3)(x)").unwrap();
+        match *expr.item {
+            MathItem::Root(Root {
+                degree: Some(ref degree),
+                radicand: Some(ref radicand),
+            }) => {
+                assert_eq!(text(degree), "3");
+                assert_eq!(text(radicand), "𝑥");
+            }
+            ref other_item => panic!("Expected Root. Found {:?}", other_item),
+        }
+        let expr = parse("bbb(R) text(for all)").unwrap();
+        let list = list(&expr);
+        assert_eq!(text(&list[0]), "ℝ");
+        assert_eq!(text(&list[1]), "for all");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("x^").unwrap_err().position, Some(2));
+        assert_eq!(parse("frac(a)").unwrap_err().position, Some(7));
+        assert_eq!(parse("(a_)").unwrap_err().position, Some(3));
+        assert_eq!(parse("x_1_2").unwrap_err().position, Some(3));
+        assert_eq!(parse("a/").unwrap_err().position, Some(2));
+        assert!(parse("\"abc").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! The table of AsciiMath symbols.
+
+use crate::unicode_math::Family;
+
+/// What a symbol stands for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    /// An identifier that is set in italics unless another math alphabet is selected.
+    Identifier,
+    /// An identifier that is set upright, like the capital Greek letters.
+    UprightIdentifier,
+    /// An operator. Its spacing and stretchiness are taken from the operator dictionary.
+    Operator,
+    /// A large operator like `sum` whose scripts are placed above and below it.
+    UnderOver,
+    /// A named function like `sin`. If `limits` is true, scripts are placed above and below the
+    /// name in display style.
+    Function {
+        limits: bool,
+    },
+    LeftBracket,
+    RightBracket,
+    /// Horizontal space, measured in em.
+    Space(f32),
+    Subscript,
+    Superscript,
+    /// The `/` that turns the expressions next to it into a fraction.
+    Fraction,
+    /// A command that takes one argument.
+    Unary(Unary),
+    /// A command that takes two arguments.
+    Binary(Binary),
+    /// `text` and `mbox`, whose argument in parentheses is read verbatim.
+    Text,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Unary {
+    Sqrt,
+    /// An accent placed above the argument. Its scripts are placed above and below it if
+    /// `under_over` is true.
+    Over {
+        stretchy: bool,
+        under_over: bool,
+    },
+    /// An accent placed below the argument.
+    Under {
+        stretchy: bool,
+        under_over: bool,
+    },
+    /// Encloses the argument in the given fences, like `abs`.
+    Fences(char, char),
+    Font(Family),
+    Cancel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Binary {
+    Frac,
+    Root,
+    /// Places the first argument above the second one.
+    Over,
+    /// Places the first argument below the second one.
+    Under,
+}
+
+#[derive(Debug)]
+pub struct Symbol {
+    pub input: &'static str,
+    pub output: &'static str,
+    pub kind: Kind,
+}
+
+const fn symbol(input: &'static str, output: &'static str, kind: Kind) -> Symbol {
+    Symbol {
+        input,
+        output,
+        kind,
+    }
+}
+
+use self::Kind::*;
+
+const FUNCTION: Kind = Function { limits: false };
+const LIMITS: Kind = Function { limits: true };
+
+#[rustfmt::skip]
+static SYMBOLS: [Symbol; 232] = [
+    // Greek letters
+    symbol("alpha", "α", Identifier), symbol("beta", "β", Identifier),
+    symbol("gamma", "γ", Identifier), symbol("Gamma", "Γ", UprightIdentifier),
+    symbol("delta", "δ", Identifier), symbol("Delta", "Δ", UprightIdentifier),
+    symbol("epsilon", "ε", Identifier), symbol("varepsilon", "ɛ", Identifier),
+    symbol("zeta", "ζ", Identifier), symbol("eta", "η", Identifier),
+    symbol("theta", "θ", Identifier), symbol("Theta", "Θ", UprightIdentifier),
+    symbol("vartheta", "ϑ", Identifier), symbol("iota", "ι", Identifier),
+    symbol("kappa", "κ", Identifier), symbol("lambda", "λ", Identifier),
+    symbol("Lambda", "Λ", UprightIdentifier), symbol("mu", "μ", Identifier),
+    symbol("nu", "ν", Identifier), symbol("xi", "ξ", Identifier),
+    symbol("Xi", "Ξ", UprightIdentifier), symbol("pi", "π", Identifier),
+    symbol("Pi", "Π", UprightIdentifier), symbol("rho", "ρ", Identifier),
+    symbol("sigma", "σ", Identifier), symbol("Sigma", "Σ", UprightIdentifier),
+    symbol("tau", "τ", Identifier), symbol("upsilon", "υ", Identifier),
+    symbol("phi", "ϕ", Identifier), symbol("varphi", "φ", Identifier),
+    symbol("Phi", "Φ", UprightIdentifier), symbol("chi", "χ", Identifier),
+    symbol("psi", "ψ", Identifier), symbol("Psi", "Ψ", UprightIdentifier),
+    symbol("omega", "ω", Identifier), symbol("Omega", "Ω", UprightIdentifier),
+    // binary operators
+    symbol("+", "+", Operator), symbol("-", "−", Operator), symbol("*", "⋅", Operator),
+    symbol("**", "∗", Operator), symbol("***", "⋆", Operator), symbol("//", "/", Operator),
+    symbol("\\\\", "∖", Operator), symbol("setminus", "∖", Operator),
+    symbol("xx", "×", Operator), symbol("|><", "⋉", Operator), symbol("><|", "⋊", Operator),
+    symbol("|><|", "⋈", Operator), symbol("-:", "÷", Operator), symbol("divide", "÷", Operator),
+    symbol("@", "∘", Operator), symbol("o+", "⊕", Operator), symbol("ox", "⊗", Operator),
+    symbol("o.", "⊙", Operator), symbol("^^", "∧", Operator), symbol("vv", "∨", Operator),
+    symbol("nn", "∩", Operator), symbol("uu", "∪", Operator), symbol("+-", "±", Operator),
+    symbol("-+", "∓", Operator),
+    // large operators
+    symbol("sum", "∑", UnderOver), symbol("prod", "∏", UnderOver),
+    symbol("^^^", "⋀", UnderOver), symbol("vvv", "⋁", UnderOver),
+    symbol("nnn", "⋂", UnderOver), symbol("uuu", "⋃", UnderOver),
+    symbol("int", "∫", Operator), symbol("oint", "∮", Operator),
+    // relations
+    symbol("=", "=", Operator), symbol("!=", "≠", Operator), symbol("<", "<", Operator),
+    symbol(">", ">", Operator), symbol("lt", "<", Operator), symbol("gt", ">", Operator),
+    symbol("<=", "≤", Operator), symbol(">=", "≥", Operator), symbol("le", "≤", Operator),
+    symbol("ge", "≥", Operator), symbol("mlt", "≪", Operator), symbol("mgt", "≫", Operator),
+    symbol("-<", "≺", Operator), symbol(">-", "≻", Operator), symbol("-<=", "⪯", Operator),
+    symbol(">-=", "⪰", Operator), symbol("in", "∈", Operator), symbol("!in", "∉", Operator),
+    symbol("sub", "⊂", Operator), symbol("sup", "⊃", Operator), symbol("sube", "⊆", Operator),
+    symbol("supe", "⊇", Operator), symbol("-=", "≡", Operator), symbol("~=", "≅", Operator),
+    symbol("~~", "≈", Operator), symbol("~", "∼", Operator), symbol("prop", "∝", Operator),
+    // logic
+    symbol("and", "and", Operator), symbol("or", "or", Operator), symbol("not", "¬", Operator),
+    symbol("=>", "⇒", Operator), symbol("if", "if", Operator), symbol("<=>", "⇔", Operator),
+    symbol("AA", "∀", Operator), symbol("EE", "∃", Operator), symbol("_|_", "⊥", Operator),
+    symbol("TT", "⊤", Operator), symbol("|--", "⊢", Operator), symbol("|==", "⊨", Operator),
+    // brackets
+    symbol("(", "(", LeftBracket), symbol(")", ")", RightBracket),
+    symbol("[", "[", LeftBracket), symbol("]", "]", RightBracket),
+    symbol("{", "{", LeftBracket), symbol("}", "}", RightBracket),
+    symbol("(:", "⟨", LeftBracket), symbol(":)", "⟩", RightBracket),
+    symbol("<<", "⟨", LeftBracket), symbol(">>", "⟩", RightBracket),
+    symbol("{:", "", LeftBracket), symbol(":}", "", RightBracket),
+    symbol("|__", "⌊", LeftBracket), symbol("__|", "⌋", RightBracket),
+    symbol("|~", "⌈", LeftBracket), symbol("~|", "⌉", RightBracket),
+    symbol("|", "|", Operator), symbol("||", "‖", Operator),
+    // miscellaneous
+    symbol(",", ",", Operator), symbol(":", ":", Operator), symbol("'", "′", Operator),
+    symbol("del", "∂", Operator), symbol("partial", "∂", Operator),
+    symbol("grad", "∇", Operator), symbol("nabla", "∇", Operator),
+    symbol("O/", "∅", UprightIdentifier), symbol("oo", "∞", UprightIdentifier),
+    symbol("infty", "∞", UprightIdentifier), symbol("aleph", "ℵ", UprightIdentifier),
+    symbol(":.", "∴", Operator), symbol(":'", "∵", Operator), symbol("/_", "∠", Operator),
+    symbol("/_\\", "△", Operator),
+    symbol("...", "…", Operator), symbol("cdots", "⋯", Operator),
+    symbol("vdots", "⋮", Operator), symbol("ddots", "⋱", Operator),
+    symbol("diamond", "⋄", Operator), symbol("square", "□", Operator),
+    symbol("CC", "ℂ", UprightIdentifier), symbol("NN", "ℕ", UprightIdentifier),
+    symbol("QQ", "ℚ", UprightIdentifier), symbol("RR", "ℝ", UprightIdentifier),
+    symbol("ZZ", "ℤ", UprightIdentifier),
+    // spaces
+    symbol("\\ ", "", Space(1.0 / 3.0)), symbol("quad", "", Space(1.0)),
+    symbol("qquad", "", Space(2.0)),
+    // functions
+    symbol("sin", "sin", FUNCTION), symbol("cos", "cos", FUNCTION), symbol("tan", "tan", FUNCTION),
+    symbol("sec", "sec", FUNCTION), symbol("csc", "csc", FUNCTION), symbol("cot", "cot", FUNCTION),
+    symbol("arcsin", "arcsin", FUNCTION), symbol("arccos", "arccos", FUNCTION),
+    symbol("arctan", "arctan", FUNCTION), symbol("sinh", "sinh", FUNCTION),
+    symbol("cosh", "cosh", FUNCTION), symbol("tanh", "tanh", FUNCTION),
+    symbol("sech", "sech", FUNCTION), symbol("csch", "csch", FUNCTION),
+    symbol("coth", "coth", FUNCTION), symbol("exp", "exp", FUNCTION),
+    symbol("log", "log", FUNCTION), symbol("ln", "ln", FUNCTION), symbol("det", "det", LIMITS),
+    symbol("dim", "dim", FUNCTION), symbol("mod", "mod", FUNCTION), symbol("gcd", "gcd", LIMITS),
+    symbol("lcm", "lcm", LIMITS), symbol("lub", "lub", LIMITS), symbol("glb", "glb", LIMITS),
+    symbol("min", "min", LIMITS), symbol("max", "max", LIMITS), symbol("lim", "lim", LIMITS),
+    symbol("Lim", "Lim", LIMITS),
+    // arrows
+    symbol("uarr", "↑", Operator), symbol("darr", "↓", Operator), symbol("rarr", "→", Operator),
+    symbol("->", "→", Operator), symbol(">->", "↣", Operator), symbol("->>", "↠", Operator),
+    symbol(">->>", "⤖", Operator), symbol("|->", "↦", Operator), symbol("larr", "←", Operator),
+    symbol("harr", "↔", Operator), symbol("rArr", "⇒", Operator), symbol("lArr", "⇐", Operator),
+    symbol("hArr", "⇔", Operator), symbol("iff", "⇔", Operator),
+    // commands
+    symbol("sqrt", "", Unary(Unary::Sqrt)),
+    symbol("hat", "^", Unary(Unary::Over { stretchy: false, under_over: false })),
+    symbol("bar", "¯", Unary(Unary::Over { stretchy: true, under_over: false })),
+    symbol("overline", "¯", Unary(Unary::Over { stretchy: true, under_over: false })),
+    symbol("vec", "→", Unary(Unary::Over { stretchy: false, under_over: false })),
+    symbol("tilde", "~", Unary(Unary::Over { stretchy: false, under_over: false })),
+    symbol("dot", "˙", Unary(Unary::Over { stretchy: false, under_over: false })),
+    symbol("ddot", "¨", Unary(Unary::Over { stretchy: false, under_over: false })),
+    symbol("obrace", "⏞", Unary(Unary::Over { stretchy: true, under_over: true })),
+    symbol("overbrace", "⏞", Unary(Unary::Over { stretchy: true, under_over: true })),
+    symbol("ul", "_", Unary(Unary::Under { stretchy: true, under_over: false })),
+    symbol("underline", "_", Unary(Unary::Under { stretchy: true, under_over: false })),
+    symbol("ubrace", "⏟", Unary(Unary::Under { stretchy: true, under_over: true })),
+    symbol("underbrace", "⏟", Unary(Unary::Under { stretchy: true, under_over: true })),
+    symbol("abs", "", Unary(Unary::Fences('|', '|'))),
+    symbol("norm", "", Unary(Unary::Fences('‖', '‖'))),
+    symbol("floor", "", Unary(Unary::Fences('⌊', '⌋'))),
+    symbol("ceil", "", Unary(Unary::Fences('⌈', '⌉'))),
+    symbol("cancel", "", Unary(Unary::Cancel)),
+    symbol("bb", "", Unary(Unary::Font(Family::Bold))),
+    symbol("bbb", "", Unary(Unary::Font(Family::DoubleStruck))),
+    symbol("cc", "", Unary(Unary::Font(Family::Script))),
+    symbol("tt", "", Unary(Unary::Font(Family::Monospace))),
+    symbol("fr", "", Unary(Unary::Font(Family::Fraktur))),
+    symbol("sf", "", Unary(Unary::Font(Family::SansSerif))),
+    symbol("frac", "", Binary(Binary::Frac)), symbol("root", "", Binary(Binary::Root)),
+    symbol("stackrel", "", Binary(Binary::Over)), symbol("overset", "", Binary(Binary::Over)),
+    symbol("underset", "", Binary(Binary::Under)),
+    symbol("text", "", Text), symbol("mbox", "", Text),
+    // scripts and fractions
+    symbol("_", "", Subscript), symbol("^", "", Superscript), symbol("/", "", Fraction),
+];
+
+/// Returns the longest symbol that `input` starts with.
+pub fn find_symbol(input: &str) -> Option<&'static Symbol> {
+    SYMBOLS
+        .iter()
+        .filter(|symbol| input.starts_with(symbol.input))
+        .max_by_key(|symbol| symbol.input.len())
+}
@@ -11,6 +11,13 @@ mod typesetting;
 extern crate quick_xml;
 
 pub mod mathmlparser;
+pub mod mathmlwriter;
+#[cfg(feature = "tex_parser")]
+pub mod texparser;
+#[cfg(feature = "asciimath_parser")]
+pub mod asciimathparser;
 
-pub use crate::typesetting::{math_box, unicode_math, shaper, layout, layout_with_style};
+pub use crate::typesetting::{
+    math_box, unicode_math, shaper, layout, layout_with_style, layout_with_width,
+};
 pub use crate::types::*;
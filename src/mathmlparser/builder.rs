@@ -0,0 +1,123 @@
+//! Shortcuts for the parsers of other input formats, which build the same trees as the MathML
+//! parser without going through XML.
+
+use std::iter;
+
+use super::error::Result;
+use super::{
+    build_element, match_math_element, Attributes, Child, ErrorType, Flags, OperatorAttributes,
+    ParseContext, ParsingError, StringExtMathml, TokenAttributes,
+};
+use crate::types::{Field, Length, MathExpression, MathSpace};
+use crate::unicode_math::Family;
+
+impl ParseContext {
+    /// Builds the MathML element called `identifier`. Elements that cannot be built, e.g. because
+    /// of a wrong number of children, are an error, unless the context recovers from errors and
+    /// replaces them by a `merror` element.
+    fn build(
+        &mut self,
+        identifier: &str,
+        attributes: Attributes,
+        children: impl Iterator<Item = Child>,
+    ) -> Result<MathExpression> {
+        let user_data = self.new_user_data();
+        let result = match match_math_element(identifier.as_bytes()) {
+            Some(elem) => build_element(elem, attributes, children, self, user_data),
+            None => Err(ParsingError {
+                position: None,
+                error_type: ErrorType::UnknownElement(identifier.to_owned()),
+            }),
+        };
+        result.or_else(|error| self.recover(error))
+    }
+
+    /// Builds the MathML element called `identifier` from already parsed children.
+    pub(crate) fn schema(
+        &mut self,
+        identifier: &str,
+        attributes: Attributes,
+        children: Vec<MathExpression>,
+    ) -> Result<MathExpression> {
+        self.build(
+            identifier,
+            attributes,
+            children.into_iter().map(Child::Expression),
+        )
+    }
+
+    /// Builds a token element like `mi` that contains `text`.
+    pub(crate) fn token(
+        &mut self,
+        identifier: &str,
+        text: String,
+        attributes: TokenAttributes,
+    ) -> Result<MathExpression> {
+        let field = (Field::Unicode(text), self.new_user_data());
+        let attributes = Attributes {
+            token: attributes,
+            ..Default::default()
+        };
+        self.build(identifier, attributes, iter::once(Child::Field(field)))
+    }
+
+    /// Builds an identifier. The inherited `math_variant` takes precedence over `family`; without
+    /// either a single letter is set in italics.
+    pub(crate) fn identifier(
+        &mut self,
+        text: &str,
+        family: Option<Family>,
+    ) -> Result<MathExpression> {
+        let family = self.math_variant.or(family);
+        let text = text.adapt_to_family(family).into_owned();
+        self.token("mi", text, TokenAttributes::default())
+    }
+
+    pub(crate) fn number(&mut self, number: &str) -> Result<MathExpression> {
+        let family = self.math_variant.unwrap_or(Family::Normal);
+        let text = number.adapt_to_family(Some(family)).into_owned();
+        self.token("mn", text, TokenAttributes::default())
+    }
+
+    pub(crate) fn operator(
+        &mut self,
+        text: &str,
+        attributes: OperatorAttributes,
+    ) -> Result<MathExpression> {
+        let family = self.math_variant.unwrap_or(Family::Normal);
+        let text = text.adapt_to_family(Some(family)).into_owned();
+        let attributes = TokenAttributes {
+            operator_attributes: attributes,
+            ..Default::default()
+        };
+        self.token("mo", text, attributes)
+    }
+
+    /// Builds a named function like "sin", which is set upright with thin spaces on both sides.
+    /// If `limits` is true its scripts are placed above and below the name in display style.
+    pub(crate) fn function(&mut self, name: &str, limits: bool) -> Result<MathExpression> {
+        let mut attributes = OperatorAttributes {
+            lspace: Some(Length::em(3.0 / 18.0)),
+            rspace: Some(Length::em(3.0 / 18.0)),
+            ..Default::default()
+        };
+        attributes.set_user_override(Flags::MOVABLE_LIMITS, limits);
+        let attributes = TokenAttributes {
+            operator_attributes: attributes,
+            ..Default::default()
+        };
+        self.token("mo", name.to_owned(), attributes)
+    }
+
+    /// Builds a `mspace` element of the given width.
+    pub(crate) fn space(&mut self, width: Length) -> Result<MathExpression> {
+        let attributes = Attributes {
+            token: TokenAttributes {
+                space: Some(MathSpace::horizontal_space(width)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        self.build("mspace", attributes, iter::empty())
+    }
+}
@@ -0,0 +1,435 @@
+//! Finds the formulas in HTML and XHTML documents that embed MathML.
+
+use super::error::Result;
+use super::source::{Namespaces, QuickXmlSource, MATHML_NAMESPACE};
+use super::{xml_reader, AttributeParse, ParseContext};
+use crate::{MathExpression, MathStyle};
+use std::ops::Range;
+
+const XHTML_NAMESPACE: &[u8] = b"http://www.w3.org/1999/xhtml";
+
+/// Returns an iterator over the `math` elements in an HTML or XHTML document.
+///
+/// A `math` element is found if its namespace prefix, like the `m` of `<m:math>`, is declared for
+/// the MathML namespace, or if it has no prefix and the default namespace is MathML, XHTML or not
+/// declared at all. The elements inside of a `math` element are MathML elements if they have no
+/// prefix or a prefix that is declared for the MathML namespace, here or in the document around
+/// the `math` element. The names of HTML elements are compared without regard to case.
+///
+/// # Examples
+/// ```
+/// # use math_render::{mathmlparser, MathStyle};
+/// let document = r#"<p xmlns:m="http://www.w3.org/1998/Math/MathML">Since
+///     <m:math><m:mi>x</m:mi><m:mo>=</m:mo><m:mn>1</m:mn></m:math>, we get
+///     <m:math display="block"><m:msup><m:mi>x</m:mi><m:mn>2</m:mn></m:msup></m:math></p>"#;
+/// let islands: Vec<_> = mathmlparser::math_islands(document.as_bytes()).collect();
+/// assert_eq!(islands.len(), 2);
+/// assert_eq!(islands[1].display, MathStyle::Display);
+/// assert!(document[islands[0].span.clone()].starts_with("<m:math>"));
+/// assert!(islands[1].parse().is_ok());
+/// ```
+pub fn math_islands(document: &[u8]) -> MathIslands<'_> {
+    MathIslands {
+        document,
+        position: 0,
+        scopes: Vec::new(),
+    }
+}
+
+/// A `math` element in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MathIsland<'a> {
+    /// The byte range of the element in the document, from its start tag to its end tag.
+    pub span: Range<usize>,
+    /// The style given by the `display` attribute, which is `Inline` if the attribute is missing.
+    pub display: MathStyle,
+    /// The source of the element, i.e. the part of the document in `span`.
+    pub source: &'a [u8],
+    // the prefixes that are declared for MathML by the elements around this one
+    mathml_prefixes: Vec<&'a [u8]>,
+}
+
+impl<'a> MathIsland<'a> {
+    /// Parses the element. The position of an error is a position in the whole document.
+    pub fn parse(&self) -> Result<MathExpression> {
+        self.parse_with_context(&mut ParseContext::default())
+    }
+
+    /// Parses the element with the given context like [`xml_reader::parse_with_context`]. The
+    /// spans of the elements and the positions of errors refer to the whole document.
+    pub fn parse_with_context(&self, context: &mut ParseContext) -> Result<MathExpression> {
+        let first_user_data = context.next_user_data;
+        let first_error = context.errors.len();
+        let prefixes = self
+            .mathml_prefixes
+            .iter()
+            .map(|prefix| String::from_utf8_lossy(prefix).into_owned())
+            .collect();
+        let source = Namespaces::new(QuickXmlSource::new(self.source), prefixes);
+        let result = xml_reader::parse_namespaced_with_context(source, context);
+
+        let offset = self.span.start;
+        for info in context.mathml_info.range_mut(first_user_data..) {
+            if let Some(ref mut span) = info.1.span {
+                *span = span.start + offset..span.end + offset;
+            }
+        }
+        for error in &mut context.errors[first_error..] {
+            error.position = error.position.map(|position| position + offset);
+        }
+        result.map_err(|mut error| {
+            error.position = error.position.map(|position| position + offset);
+            error
+        })
+    }
+}
+
+/// The iterator returned by [`math_islands`].
+#[derive(Debug, Clone)]
+pub struct MathIslands<'a> {
+    document: &'a [u8],
+    position: usize,
+    // the elements that enclose `position`
+    scopes: Vec<Scope<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct Scope<'a> {
+    name: &'a [u8],
+    // pairs of prefixes and namespaces, the default namespace has an empty prefix
+    namespaces: Vec<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a> Iterator for MathIslands<'a> {
+    type Item = MathIsland<'a>;
+
+    fn next(&mut self) -> Option<MathIsland<'a>> {
+        while let Some(start) = find(self.document, b"<", self.position) {
+            let (tag, tag_end) = read_tag(self.document, start);
+            self.position = tag_end;
+            match tag {
+                Tag::Start {
+                    name,
+                    attributes,
+                    empty,
+                } => {
+                    let namespaces = namespace_declarations(&attributes);
+                    if self.is_math(name, &namespaces) {
+                        if !empty {
+                            self.position = end_of_element(self.document, name, tag_end);
+                        }
+                        let display = attributes
+                            .iter()
+                            .find(|(attribute, _)| *attribute == b"display")
+                            .and_then(|(_, value)| std::str::from_utf8(value).ok())
+                            .and_then(|value| value.parse_xml().ok())
+                            .unwrap_or(MathStyle::Inline);
+                        return Some(MathIsland {
+                            span: start..self.position,
+                            display,
+                            source: &self.document[start..self.position],
+                            mathml_prefixes: self.mathml_prefixes(),
+                        });
+                    }
+                    if empty {
+                        continue;
+                    }
+                    if name.eq_ignore_ascii_case(b"script") || name.eq_ignore_ascii_case(b"style") {
+                        // the text of scripts and style sheets is not markup
+                        let end_tag = [b"</", name].concat();
+                        self.position = find_ignore_case(self.document, &end_tag, tag_end)
+                            .unwrap_or(self.document.len());
+                    } else {
+                        self.scopes.push(Scope { name, namespaces });
+                    }
+                }
+                // elements without end tag, like `<br>` in HTML, are closed by their parent
+                Tag::End(name) => {
+                    let scope = self.scopes.iter().rposition(|scope| {
+                        // HTML names are case-insensitive
+                        scope.name.eq_ignore_ascii_case(name)
+                    });
+                    if let Some(index) = scope {
+                        self.scopes.truncate(index);
+                    }
+                }
+                Tag::Other => {}
+            }
+        }
+        self.position = self.document.len();
+        None
+    }
+}
+
+impl<'a> MathIslands<'a> {
+    // Checks whether an element with the given name and namespace declarations is a MathML
+    // `math` element.
+    fn is_math(&self, name: &[u8], namespaces: &[(&[u8], &[u8])]) -> bool {
+        let (prefix, local_name) = match name.iter().position(|&byte| byte == b':') {
+            Some(colon) => (&name[..colon], &name[colon + 1..]),
+            None => (&b""[..], name),
+        };
+        if local_name != b"math" {
+            return false;
+        }
+        let namespace = namespaces
+            .iter()
+            .chain(self.scopes.iter().rev().flat_map(|scope| &scope.namespaces))
+            .find(|(declared_prefix, _)| *declared_prefix == prefix)
+            .map(|&(_, namespace)| namespace);
+        match namespace {
+            Some(namespace) => {
+                namespace == MATHML_NAMESPACE.as_bytes()
+                    || (prefix.is_empty() && namespace == XHTML_NAMESPACE)
+            }
+            // HTML puts `math` elements into the MathML namespace without a declaration
+            None => prefix.is_empty(),
+        }
+    }
+
+    // Returns the prefixes that the open elements declare for the MathML namespace, leaving out
+    // those that an inner element declares for another namespace.
+    fn mathml_prefixes(&self) -> Vec<&'a [u8]> {
+        let mut prefixes: Vec<(&[u8], bool)> = Vec::new();
+        for &(prefix, namespace) in self.scopes.iter().rev().flat_map(|scope| &scope.namespaces) {
+            if !prefix.is_empty() && prefixes.iter().all(|&(declared, _)| declared != prefix) {
+                prefixes.push((prefix, namespace == MATHML_NAMESPACE.as_bytes()));
+            }
+        }
+        prefixes
+            .into_iter()
+            .filter(|&(_, is_mathml)| is_mathml)
+            .map(|(prefix, _)| prefix)
+            .collect()
+    }
+}
+
+enum Tag<'a> {
+    Start {
+        name: &'a [u8],
+        attributes: Vec<(&'a [u8], &'a [u8])>,
+        empty: bool,
+    },
+    End(&'a [u8]),
+    /// Comments, CDATA sections, processing instructions, doctypes and a `<` in text.
+    Other,
+}
+
+// Reads the markup that starts with the `<` at `start`. Returns the markup and the position after
+// it.
+fn read_tag(document: &[u8], start: usize) -> (Tag<'_>, usize) {
+    let rest = &document[start..];
+    let skip_to = |end: &[u8]| find(document, end, start).map_or(document.len(), |i| i + end.len());
+    if rest.starts_with(b"<!--") {
+        return (Tag::Other, skip_to(b"-->"));
+    }
+    if rest.starts_with(b"<![CDATA[") {
+        return (Tag::Other, skip_to(b"]]>"));
+    }
+    if rest.starts_with(b"<!") || rest.starts_with(b"<?") {
+        return (Tag::Other, skip_to(b">"));
+    }
+    if rest.starts_with(b"</") {
+        return (Tag::End(read_name(document, start + 2)), skip_to(b">"));
+    }
+
+    let name = read_name(document, start + 1);
+    match name.first() {
+        Some(byte) if byte.is_ascii_alphabetic() || *byte == b'_' || !byte.is_ascii() => {}
+        _ => return (Tag::Other, start + 1),
+    }
+    let mut attributes = Vec::new();
+    let mut position = start + 1 + name.len();
+    loop {
+        position = skip_whitespace(document, position);
+        let empty = match document.get(position) {
+            None => false,
+            Some(b'>') => {
+                position += 1;
+                false
+            }
+            Some(b'/') if document.get(position + 1) == Some(&b'>') => {
+                position += 2;
+                true
+            }
+            Some(_) => {
+                let attribute = read_name(document, position);
+                if attribute.is_empty() {
+                    // skip a stray character
+                    position += 1;
+                    continue;
+                }
+                position = skip_whitespace(document, position + attribute.len());
+                let mut value = &b""[..];
+                if document.get(position) == Some(&b'=') {
+                    position = skip_whitespace(document, position + 1);
+                    let value_end = match document.get(position) {
+                        Some(&quote) if quote == b'"' || quote == b'\'' => {
+                            position += 1;
+                            find(document, &[quote], position).unwrap_or(document.len())
+                        }
+                        _ => document[position..]
+                            .iter()
+                            .position(|&byte| byte.is_ascii_whitespace() || byte == b'>')
+                            .map_or(document.len(), |i| position + i),
+                    };
+                    value = &document[position..value_end];
+                    position = value_end;
+                    if document.get(position) != Some(&b'>') {
+                        position = (position + 1).min(document.len());
+                    }
+                }
+                attributes.push((attribute, value));
+                continue;
+            }
+        };
+        let tag = Tag::Start {
+            name,
+            attributes,
+            empty,
+        };
+        return (tag, position);
+    }
+}
+
+// Returns the position after the end tag of the element with the given name whose start tag ends
+// at `position`.
+fn end_of_element(document: &[u8], name: &[u8], mut position: usize) -> usize {
+    let mut depth = 0;
+    while let Some(start) = find(document, b"<", position) {
+        let (tag, tag_end) = read_tag(document, start);
+        position = tag_end;
+        match tag {
+            Tag::Start {
+                name: start_name,
+                empty: false,
+                ..
+            } if start_name.eq_ignore_ascii_case(name) => depth += 1,
+            Tag::End(end_name) if end_name.eq_ignore_ascii_case(name) => {
+                if depth == 0 {
+                    return position;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    document.len()
+}
+
+fn namespace_declarations<'a>(attributes: &[(&'a [u8], &'a [u8])]) -> Vec<(&'a [u8], &'a [u8])> {
+    attributes
+        .iter()
+        .filter_map(|&(attribute, namespace)| match attribute {
+            b"xmlns" => Some((&b""[..], namespace)),
+            _ if attribute.starts_with(b"xmlns:") => Some((&attribute[6..], namespace)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn read_name(document: &[u8], start: usize) -> &[u8] {
+    let length = document[start..]
+        .iter()
+        .take_while(|&&byte| !byte.is_ascii_whitespace() && !b"<>/=".contains(&byte))
+        .count();
+    &document[start..start + length]
+}
+
+fn skip_whitespace(document: &[u8], position: usize) -> usize {
+    position
+        + document[position..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count()
+}
+
+fn find(document: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    document[from..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|i| from + i)
+}
+
+fn find_ignore_case(document: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    document[from..]
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern))
+        .map(|i| from + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    #[test]
+    fn test_math_islands() {
+        let document = "<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" \
+                        xmlns:m=\"http://www.w3.org/1998/Math/MathML\">\n\
+                        <body><p>a <math><mi>x</mi></math> b<br>\
+                        <m:math display=\"block\"><m:mrow><m:mi>y</m:mi></m:mrow></m:math></p>\
+                        <!-- <math><mi>z</mi></math> -->\
+                        <script>if (a <math.b) {}</script>\
+                        <SCRIPT>var m = '<math></math>';</script>\
+                        <div><p>c</P><math><mn>1</mn></math></div>\
+                        <svg xmlns=\"http://www.w3.org/2000/svg\"><math/></svg>\
+                        <div xmlns:m=\"urn:other\"><m:math/></div>\
+                        <mml:math xmlns:mml='http://www.w3.org/1998/Math/MathML'/></body></html>";
+        let islands: Vec<_> = math_islands(document.as_bytes()).collect();
+        let sources: Vec<_> = islands
+            .iter()
+            .map(|island| &document[island.span.clone()])
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "<math><mi>x</mi></math>",
+                "<m:math display=\"block\"><m:mrow><m:mi>y</m:mi></m:mrow></m:math>",
+                "<math><mn>1</mn></math>",
+                "<mml:math xmlns:mml='http://www.w3.org/1998/Math/MathML'/>",
+            ]
+        );
+        let styles: Vec<_> = islands.iter().map(|island| island.display).collect();
+        assert_eq!(
+            styles,
+            [
+                MathStyle::Inline,
+                MathStyle::Display,
+                MathStyle::Inline,
+                MathStyle::Inline
+            ]
+        );
+
+        let expr = islands[1].parse().unwrap();
+        match *expr.item {
+            MathItem::Styled(ref styled) => {
+                assert_eq!(styled.math_style, Some(MathStyle::Display));
+            }
+            ref other => panic!("expected a styled element, found {:?}", other),
+        }
+        // the prefix of the MathML namespace is declared by the `html` element
+        assert_eq!(islands[1].mathml_prefixes, [b"m"]);
+        assert!(islands[3].parse().is_ok());
+
+        let document = "<p xmlns:m=\"http://www.w3.org/1998/Math/MathML\" xmlns:svg=\"urn:svg\">\
+                        <m:math><svg:mi>x</svg:mi></m:math></p>";
+        let island = math_islands(document.as_bytes()).next().unwrap();
+        assert!(island.parse().is_err());
+    }
+
+    #[test]
+    fn test_island_positions() {
+        let document = "<p>\n<math><mi>x</mi></math>\n<math><mfrac><mn>1</mn></mfrac></math></p>";
+        let mut islands = math_islands(document.as_bytes());
+        let first = islands.next().unwrap();
+        let mut context = ParseContext::default();
+        let expr = first.parse_with_context(&mut context).unwrap();
+        // the formula consists of the `mi` element only
+        assert_eq!(context.span(expr.get_user_data()), Some(10..20));
+
+        let error = islands.next().unwrap().parse().unwrap_err();
+        assert_eq!(error.line_column(document.as_bytes()), Some((3, 7)));
+        assert!(islands.next().is_none());
+    }
+}
@@ -1,9 +1,9 @@
 use std;
 use std::fmt;
-use std::io::prelude::*;
 
+use super::source::XmlSource;
 #[cfg(feature = "mathml_parser")]
-use quick_xml::{self, XmlReader};
+use quick_xml;
 
 pub type Result<T> = std::result::Result<T, ParsingError>;
 
@@ -13,28 +13,61 @@ pub struct ParsingError {
     pub error_type: ErrorType,
 }
 impl ParsingError {
-    #[cfg(feature = "mathml_parser")]
-    pub fn from_string<B: BufRead, S: ToString>(parser: &XmlReader<B>, string: S) -> ParsingError {
+    /// Returns the line and the column (both starting at 1) in `source` at which this error
+    /// occurred, if its position is known.
+    pub fn line_column(&self, source: &[u8]) -> Option<(usize, usize)> {
+        self.position.map(|position| line_column(source, position))
+    }
+
+    pub fn from_string<X: XmlSource, S: ToString>(source: &X, string: S) -> ParsingError {
         ParsingError {
-            position: Some(parser.buffer_position()),
+            position: source.span().map(|span| span.end),
             error_type: ErrorType::OtherError(string.to_string()),
         }
     }
 
-    #[cfg(feature = "mathml_parser")]
-    pub fn of_type<B: BufRead>(parser: &XmlReader<B>, err_type: ErrorType) -> ParsingError {
+    pub fn of_type<X: XmlSource>(source: &X, err_type: ErrorType) -> ParsingError {
         ParsingError {
-            position: Some(parser.buffer_position()),
+            position: source.span().map(|span| span.end),
             error_type: err_type,
         }
     }
 }
 
+/// Converts a byte offset into `source` to a line and a column, both starting at 1. Columns are
+/// counted in characters.
+pub fn line_column(source: &[u8], position: usize) -> (usize, usize) {
+    let before = &source[..position.min(source.len())];
+    let line_start = before
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+    let line_text = String::from_utf8_lossy(&before[line_start..]);
+    let column = line_text.chars().count() + 1;
+    (line, column)
+}
+
 #[derive(Debug)]
 pub enum ErrorType {
     UnknownElement(String),
+    UnknownCommand(String),
     UnexpectedEndOfInput,
     WrongEndElement(String),
+    /// An element does not have the number of children that its layout requires.
+    WrongArgumentCount {
+        element: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The value of an attribute could not be parsed.
+    InvalidAttribute {
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    /// A known element that cannot be used where it appears.
+    UnsupportedElement(String),
     OtherError(String),
     Utf8Error(std::str::Utf8Error),
     #[cfg(feature = "mathml_parser")]
@@ -46,18 +79,97 @@ impl fmt::Display for ParsingError {
         match self.error_type {
             ErrorType::Utf8Error(err) => write!(f, "{}", err),
             ErrorType::UnknownElement(ref name) => write!(f, "Unknown Element: \"{}\"", name),
+            ErrorType::UnknownCommand(ref name) => write!(f, "Unknown Command: \"\\{}\"", name),
             ErrorType::UnexpectedEndOfInput => write!(f, "Unexpected end of input."),
             ErrorType::WrongEndElement(ref name) => write!(
                 f,
                 "Unexpected end element \"<{}>\" without corresponding start element.",
                 name
             ),
+            ErrorType::WrongArgumentCount {
+                ref element,
+                expected,
+                found,
+            } => write!(
+                f,
+                "\"{}\" element requires {} arguments. Found {} arguments.",
+                element, expected, found
+            ),
+            ErrorType::InvalidAttribute {
+                ref element,
+                ref attribute,
+                ref value,
+            } => write!(
+                f,
+                "Invalid value \"{}\" of the attribute \"{}\" of a \"{}\" element.",
+                value, attribute, element
+            ),
+            ErrorType::UnsupportedElement(ref name) => {
+                write!(f, "Unsupported element: \"{}\"", name)
+            }
             ErrorType::OtherError(ref string) => write!(f, "Error: {}", string),
             #[cfg(feature = "mathml_parser")]
             ErrorType::XmlError(ref error) => write!(f, "XML error: {}", error),
         }
     }
 }
+
+impl ErrorType {
+    /// Returns `true` if the parser can go on after this error by replacing the element in which
+    /// it occurred. Errors in the XML structure itself are fatal.
+    pub(crate) fn is_recoverable(&self) -> bool {
+        match *self {
+            ErrorType::UnknownElement(_)
+            | ErrorType::UnknownCommand(_)
+            | ErrorType::WrongArgumentCount { .. }
+            | ErrorType::InvalidAttribute { .. }
+            | ErrorType::UnsupportedElement(_)
+            | ErrorType::OtherError(_) => true,
+            ErrorType::UnexpectedEndOfInput
+            | ErrorType::WrongEndElement(_)
+            | ErrorType::Utf8Error(_) => false,
+            #[cfg(feature = "mathml_parser")]
+            ErrorType::XmlError(_) => false,
+        }
+    }
+}
+
+impl ParsingError {
+    /// Returns a copy of this error if it is recoverable. The other errors may wrap errors of the
+    /// XML reader that cannot be copied, but they are never collected by a recovering parser.
+    pub(crate) fn clone_recoverable(&self) -> Option<ParsingError> {
+        let error_type = match self.error_type {
+            ErrorType::UnknownElement(ref name) => ErrorType::UnknownElement(name.clone()),
+            ErrorType::UnknownCommand(ref name) => ErrorType::UnknownCommand(name.clone()),
+            ErrorType::WrongArgumentCount {
+                ref element,
+                expected,
+                found,
+            } => ErrorType::WrongArgumentCount {
+                element: element.clone(),
+                expected,
+                found,
+            },
+            ErrorType::InvalidAttribute {
+                ref element,
+                ref attribute,
+                ref value,
+            } => ErrorType::InvalidAttribute {
+                element: element.clone(),
+                attribute: attribute.clone(),
+                value: value.clone(),
+            },
+            ErrorType::UnsupportedElement(ref name) => ErrorType::UnsupportedElement(name.clone()),
+            ErrorType::OtherError(ref message) => ErrorType::OtherError(message.clone()),
+            _ => return None,
+        };
+        Some(ParsingError {
+            position: self.position,
+            error_type,
+        })
+    }
+}
+
 impl std::error::Error for ParsingError {
     fn cause(&self) -> Option<&dyn std::error::Error> {
         match self.error_type {
@@ -1,27 +1,41 @@
+mod builder;
+#[cfg(feature = "mathml_parser")]
+mod document;
 mod escape;
 mod operator;
-mod operator_dict;
+pub(crate) mod operator_dict;
 mod token;
 
 mod error;
-#[cfg(feature = "mathml_parser")]
+mod source;
 mod xml_reader;
 #[cfg(feature = "mathml_parser")]
-pub use xml_reader::parse;
+pub use document::{math_islands, MathIsland, MathIslands};
+pub use source::{XmlEvent, XmlSource};
+#[cfg(feature = "mathml_parser")]
+pub use xml_reader::{parse, parse_with_context, parse_with_recovery};
+pub use xml_reader::{parse_source, parse_source_with_context};
 
+pub use self::error::{line_column, ErrorType, ParsingError};
 pub use operator::{Attributes as OperatorAttributes, Flags, Form};
 pub use token::{Attributes as TokenAttributes, StringExtMathml};
 
 use std;
 use std::collections::BTreeMap;
+use std::ops::Range;
 
 use crate::{
     types::{
-        Atom, GeneralizedFraction, Length, LengthUnit, MathExpression, MathItem, OverUnder, Root,
+        Atom, Color, Enclosure, GeneralizedFraction, HorizontalAlign, Length, LengthUnit,
+        LineBreak, LineBreakStyle, LineStyle, MathExpression, MathItem, MathStyle, Notation,
+        OverUnder, Padded, PaddedLength, PaddedValue, Phantom, PseudoUnit, Root, ScriptLevel,
+        Styled, TextDirection, VerticalAlign,
     },
+    unicode_math::Family,
     Field,
 };
 
+use self::error::Result;
 use self::operator::{guess_if_operator_with_form};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -78,7 +92,7 @@ impl AttributeParse for str {
 }
 
 // a static list of all mathml elements known to this parser
-static MATHML_ELEMENTS: [MathmlElement; 16] = [
+static MATHML_ELEMENTS: [MathmlElement; 33] = [
     MathmlElement {
         identifier: "mi",
         elem_type: ElementType::TokenElement,
@@ -99,6 +113,14 @@ static MATHML_ELEMENTS: [MathmlElement; 16] = [
         identifier: "mspace",
         elem_type: ElementType::TokenElement,
     },
+    MathmlElement {
+        identifier: "malignmark",
+        elem_type: ElementType::TokenElement,
+    },
+    MathmlElement {
+        identifier: "maligngroup",
+        elem_type: ElementType::TokenElement,
+    },
     MathmlElement {
         identifier: "mrow",
         elem_type: ElementType::LayoutSchema {
@@ -165,6 +187,96 @@ static MATHML_ELEMENTS: [MathmlElement; 16] = [
             args: ArgumentRequirements::RequiredArguments(2),
         },
     },
+    MathmlElement {
+        identifier: "mmultiscripts",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "mprescripts",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "none",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "mstyle",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::ArgumentList,
+        },
+    },
+    MathmlElement {
+        identifier: "mpadded",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::ArgumentList,
+        },
+    },
+    MathmlElement {
+        identifier: "mphantom",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::ArgumentList,
+        },
+    },
+    MathmlElement {
+        identifier: "menclose",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::ArgumentList,
+        },
+    },
+    MathmlElement {
+        identifier: "merror",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::ArgumentList,
+        },
+    },
+    MathmlElement {
+        identifier: "mtable",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "mtr",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "mlabeledtr",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "mtd",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "semantics",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "annotation",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
+    MathmlElement {
+        identifier: "annotation-xml",
+        elem_type: ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        },
+    },
 ];
 
 pub fn match_math_element(identifier: &[u8]) -> Option<MathmlElement> {
@@ -174,12 +286,108 @@ pub fn match_math_element(identifier: &[u8]) -> Option<MathmlElement> {
         .cloned()
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Debug, Default)]
 pub struct ParseContext {
     pub mathml_info: BTreeMap<u64, MathmlInfo>,
+    /// The `mathvariant` that token elements inherit from enclosing `mstyle` or `math` elements.
+    pub math_variant: Option<Family>,
+    next_user_data: u64,
+    /// If `true`, recoverable errors are collected in `errors` instead of ending the parse.
+    recover: bool,
+    errors: Vec<ParsingError>,
+}
+
+impl Clone for ParseContext {
+    fn clone(&self) -> Self {
+        ParseContext {
+            mathml_info: self.mathml_info.clone(),
+            math_variant: self.math_variant,
+            next_user_data: self.next_user_data,
+            recover: self.recover,
+            errors: self
+                .errors
+                .iter()
+                .filter_map(ParsingError::clone_recoverable)
+                .collect(),
+        }
+    }
 }
 
 impl ParseContext {
+    /// Returns a context that replaces elements with errors by `merror` placeholders.
+    pub fn with_recovery() -> Self {
+        ParseContext {
+            recover: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the errors that have been recovered from.
+    pub fn take_errors(&mut self) -> Vec<ParsingError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Handles an error in an element that has been read completely. When recovering, the
+    /// element is replaced by a placeholder that shows the error.
+    pub(crate) fn recover(&mut self, error: ParsingError) -> Result<MathExpression> {
+        if self.recover && error.error_type.is_recoverable() {
+            let placeholder = self.error_placeholder(error.to_string());
+            self.errors.push(error);
+            Ok(placeholder)
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Handles an error that does not affect the structure of the formula, e.g. an invalid
+    /// attribute value. When recovering, the error is recorded and the parser carries on.
+    pub(crate) fn report(&mut self, error: ParsingError) -> Result<()> {
+        if self.recover && error.error_type.is_recoverable() {
+            self.errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Builds a `merror` element that contains `message`.
+    pub(crate) fn error_placeholder(&mut self, message: String) -> MathExpression {
+        let text = MathExpression::new(
+            MathItem::Field(Field::Unicode(message)),
+            self.new_user_data(),
+        );
+        let user_data = self.new_user_data();
+        MathExpression::new(merror(text, self), user_data)
+    }
+
+    /// Returns a `user_data` value that has not been handed out by this context before.
+    pub fn new_user_data(&mut self) -> u64 {
+        let user_data = self.next_user_data;
+        self.next_user_data += 1;
+        user_data
+    }
+
+    /// Returns the byte range of the MathML input from which the expression with the given
+    /// `user_data` has been built, e.g. to find the source of a `MathBox`.
+    pub fn span(&self, user_data: u64) -> Option<Range<usize>> {
+        self.mathml_info
+            .get(&user_data)
+            .and_then(|info| info.span.clone())
+    }
+
+    /// Records that `expr` has been built from `span`. The parts of `expr` that have no span yet
+    /// have been built for the same element and get the same span.
+    pub(crate) fn set_span(&mut self, expr: &MathExpression, span: Range<usize>) {
+        let info = self.mathml_info.entry(expr.get_user_data()).or_default();
+        if info.span.is_some() {
+            return;
+        }
+        info.span = Some(span.clone());
+        for sub_expr in expr.item.sub_expressions() {
+            self.set_span(sub_expr, span.clone());
+        }
+    }
+
     fn info_for_expr<'a, T: Into<Option<&'a MathExpression>>>(
         &self,
         expr: T,
@@ -202,19 +410,29 @@ impl ParseContext {
         }
     }
 
-    fn operator_attrs<'a, 'b: 'a, T: Into<Option<&'a MathExpression>>>(
+    pub(crate) fn operator_attrs<'a, 'b: 'a, T: Into<Option<&'a MathExpression>>>(
         &'b self,
         expr: T,
     ) -> Option<&'b operator::Attributes> {
         self.info_for_expr(expr)
             .and_then(|info| info.operator_attrs.as_ref())
     }
+
+    pub(crate) fn operator_attrs_mut<'a, 'b: 'a, T: Into<Option<&'a MathExpression>>>(
+        &'b mut self,
+        expr: T,
+    ) -> Option<&'b mut operator::Attributes> {
+        self.info_for_expr_mut(expr)
+            .and_then(|info| info.operator_attrs.as_mut())
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct MathmlInfo {
     operator_attrs: Option<operator::Attributes>,
     pub is_space: bool,
+    /// The byte range of the element in the MathML input.
+    pub span: Option<Range<usize>>,
 }
 
 impl MathmlInfo {
@@ -236,8 +454,26 @@ pub struct Attributes {
 
 #[derive(Debug, Default, Clone)]
 pub struct SchemaAttributes {
-    accent: bool,
-    accentunder: bool,
+    pub(crate) accent: bool,
+    pub(crate) accentunder: bool,
+    pub(crate) notation: Option<Vec<Notation>>,
+    pub(crate) display: Option<MathStyle>,
+    pub(crate) display_style: Option<bool>,
+    pub(crate) script_level: Option<ScriptLevel>,
+    pub(crate) math_variant: Option<Family>,
+    pub(crate) math_size: Option<Length>,
+    pub(crate) math_color: Option<Color>,
+    pub(crate) math_background: Option<Color>,
+    pub(crate) direction: Option<TextDirection>,
+    pub(crate) width: Option<PaddedLength>,
+    pub(crate) height: Option<PaddedLength>,
+    pub(crate) depth: Option<PaddedLength>,
+    pub(crate) lspace: Option<PaddedLength>,
+    pub(crate) voffset: Option<PaddedLength>,
+    pub(crate) line_thickness: Option<Length>,
+    pub(crate) numerator_align: Option<HorizontalAlign>,
+    pub(crate) denominator_align: Option<HorizontalAlign>,
+    pub(crate) bevelled: bool,
 }
 
 pub fn build_element<'a>(
@@ -247,7 +483,7 @@ pub fn build_element<'a>(
     context: &mut ParseContext,
     // You have to ensure that this key is unique
     user_data: u64,
-) -> MathExpression {
+) -> Result<MathExpression> {
     match elem.elem_type {
         ElementType::LayoutSchema {
             args: ArgumentRequirements::RequiredArguments(_),
@@ -268,35 +504,71 @@ pub fn build_element<'a>(
             });
             let mut list = expressions.collect();
             operator::process_operators(&mut list, context);
-            parse_list_schema(list, elem, user_data)
+            Ok(parse_list_schema(
+                list,
+                elem,
+                attributes.schema,
+                context,
+                user_data,
+            ))
         }
         ElementType::TokenElement => {
-            let fields = children.filter_map(|child| match child {
-                Child::Field(field) => Some(field),
-                _ => None,
-            });
-            token::build_token(fields, elem, attributes.token, context, user_data).unwrap()
+            token::build_token(children, elem, attributes.token, context, user_data)
         }
-        _ => todo!(),
+        // tables and scripts have a structure that is not given by a list of children
+        ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        } => Err(ParsingError {
+            position: None,
+            error_type: ErrorType::UnsupportedElement(elem.identifier.to_owned()),
+        }),
     }
 }
 
 fn parse_list_schema<'a>(
     mut content: Vec<MathExpression>,
     elem: MathmlElement,
+    attributes: SchemaAttributes,
+    context: &mut ParseContext,
     user_data: u64,
 ) -> MathExpression {
+    let math_style = attributes
+        .display_style
+        .map(|display_style| {
+            if display_style {
+                MathStyle::Display
+            } else {
+                MathStyle::Inline
+            }
+        })
+        .or(attributes.display);
+    let is_wrapped = match elem.identifier {
+        _ if elem.elem_type == ElementType::MathmlRoot => false,
+        "msqrt" | "menclose" | "merror" | "mpadded" | "mphantom" => true,
+        "math" | "mstyle" => {
+            math_style.is_some()
+                || attributes.script_level.is_some()
+                || attributes.math_size.is_some()
+                || attributes.math_color.is_some()
+                || attributes.math_background.is_some()
+                || attributes.direction.is_some()
+        }
+        "mrow" => attributes.direction.is_some(),
+        _ => false,
+    };
+
     // a mrow with a single element is strictly equivalent to the element
     let content = if content.len() == 1 {
         content.remove(0)
+    } else if is_wrapped {
+        MathExpression::new(MathItem::List(content), context.new_user_data())
     } else {
         MathExpression::new(MathItem::List(content), user_data)
     };
-    if elem.elem_type == ElementType::MathmlRoot {
+    if !is_wrapped {
         return content;
     }
     match elem.identifier {
-        "mrow" | "math" => content,
         "msqrt" => {
             let item = Root {
                 radicand: Some(content),
@@ -304,10 +576,73 @@ fn parse_list_schema<'a>(
             };
             MathExpression::new(MathItem::Root(item), user_data)
         }
-        _ => content,
+        "menclose" => {
+            let item = Enclosure {
+                content: Some(content),
+                notations: attributes
+                    .notation
+                    .unwrap_or_else(|| vec![Notation::LongDiv]),
+            };
+            MathExpression::new(MathItem::Enclosure(item), user_data)
+        }
+        "merror" => MathExpression::new(merror(content, context), user_data),
+        _ => {
+            // these elements are embellished operators if their content is an embellished operator
+            let info = MathmlInfo {
+                operator_attrs: context.operator_attrs(&content).cloned(),
+                ..Default::default()
+            };
+            context.mathml_info.insert(user_data, info);
+            let item = match elem.identifier {
+                "math" | "mstyle" => MathItem::Styled(Styled {
+                    content: Some(content),
+                    math_style,
+                    script_level: attributes.script_level,
+                    math_size: attributes.math_size,
+                    math_color: attributes.math_color,
+                    math_background: attributes.math_background,
+                    direction: attributes.direction,
+                }),
+                "mrow" => MathItem::Styled(Styled {
+                    content: Some(content),
+                    direction: attributes.direction,
+                    ..Default::default()
+                }),
+                "mpadded" => MathItem::Padded(Padded {
+                    content: Some(content),
+                    width: attributes.width,
+                    height: attributes.height,
+                    depth: attributes.depth,
+                    lspace: attributes.lspace,
+                    voffset: attributes.voffset,
+                }),
+                "mphantom" => MathItem::Phantom(Phantom {
+                    content: Some(content),
+                }),
+                _ => unreachable!(),
+            };
+            MathExpression::new(item, user_data)
+        }
     }
 }
 
+// Error messages are framed in red on a light yellow background.
+fn merror(content: MathExpression, context: &mut ParseContext) -> MathItem {
+    let enclosure = Enclosure {
+        content: Some(content),
+        notations: vec![Notation::Box],
+    };
+    MathItem::Styled(Styled {
+        content: Some(MathExpression::new(
+            MathItem::Enclosure(enclosure),
+            context.new_user_data(),
+        )),
+        math_color: Some(Color::rgb(255, 0, 0)),
+        math_background: Some(Color::rgb(255, 255, 224)),
+        ..Default::default()
+    })
+}
+
 fn construct_under_over<'a>(
     nucleus: Option<MathExpression>,
     under: Option<MathExpression>,
@@ -341,19 +676,39 @@ fn construct_under_over<'a>(
 }
 
 fn parse_fixed_schema<'a>(
-    mut content: impl Iterator<Item = MathExpression>,
+    content: impl Iterator<Item = MathExpression>,
     elem: MathmlElement,
     attributes: SchemaAttributes,
     context: &mut ParseContext,
     user_data: u64,
-) -> MathExpression {
-    let mut next = || Some(content.next().unwrap());
+) -> Result<MathExpression> {
+    let content: Vec<_> = content.collect();
+    if let ElementType::LayoutSchema {
+        args: ArgumentRequirements::RequiredArguments(num_args),
+    } = elem.elem_type
+    {
+        if content.len() != num_args as usize {
+            return Err(ParsingError {
+                position: None,
+                error_type: ErrorType::WrongArgumentCount {
+                    element: elem.identifier.to_owned(),
+                    expected: num_args as usize,
+                    found: content.len(),
+                },
+            });
+        }
+    }
+    let mut content = content.into_iter();
+    let mut next = || content.next();
     let result = match elem.identifier {
         "mfrac" => {
             let frac = GeneralizedFraction {
                 numerator: next(),
                 denominator: next(),
-                thickness: None,
+                thickness: attributes.line_thickness,
+                numerator_align: attributes.numerator_align.unwrap_or_default(),
+                denominator_align: attributes.denominator_align.unwrap_or_default(),
+                is_bevelled: attributes.bevelled,
             };
             MathItem::GeneralizedFraction(frac)
         }
@@ -367,11 +722,8 @@ fn parse_fixed_schema<'a>(
         "msub" => {
             let atom = Atom {
                 nucleus: next(),
-                bottom_right: Some(guess_if_operator_with_form(
-                    next().unwrap(),
-                    Form::Postfix,
-                    context,
-                )),
+                bottom_right: next()
+                    .map(|script| guess_if_operator_with_form(script, Form::Postfix, context)),
                 ..Default::default()
             };
             MathItem::Atom(atom)
@@ -379,11 +731,8 @@ fn parse_fixed_schema<'a>(
         "msup" => {
             let atom = Atom {
                 nucleus: next(),
-                top_right: Some(guess_if_operator_with_form(
-                    next().unwrap(),
-                    Form::Postfix,
-                    context,
-                )),
+                top_right: next()
+                    .map(|script| guess_if_operator_with_form(script, Form::Postfix, context)),
                 ..Default::default()
             };
             MathItem::Atom(atom)
@@ -391,16 +740,10 @@ fn parse_fixed_schema<'a>(
         "msubsup" => {
             let atom = Atom {
                 nucleus: next(),
-                bottom_right: Some(guess_if_operator_with_form(
-                    next().unwrap(),
-                    Form::Postfix,
-                    context,
-                )),
-                top_right: Some(guess_if_operator_with_form(
-                    next().unwrap(),
-                    Form::Postfix,
-                    context,
-                )),
+                bottom_right: next()
+                    .map(|script| guess_if_operator_with_form(script, Form::Postfix, context)),
+                top_right: next()
+                    .map(|script| guess_if_operator_with_form(script, Form::Postfix, context)),
                 ..Default::default()
             };
             MathItem::Atom(atom)
@@ -421,7 +764,12 @@ fn parse_fixed_schema<'a>(
             let over = next();
             construct_under_over(nuc, under, over, attributes, context)
         }
-        _ => unreachable!(),
+        _ => {
+            return Err(ParsingError {
+                position: None,
+                error_type: ErrorType::UnsupportedElement(elem.identifier.to_owned()),
+            })
+        }
     };
     let info = MathmlInfo {
         operator_attrs: match result {
@@ -439,14 +787,16 @@ fn parse_fixed_schema<'a>(
         ..Default::default()
     };
     context.mathml_info.insert(user_data, info);
-    let expr = MathExpression::new(result, user_data);
-    expr
+    Ok(MathExpression::new(result, user_data))
 }
 
 impl FromXmlAttribute for Length {
     type Err = &'static str;
     fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
         let string = attr.trim().to_ascii_lowercase();
+        if let Some(length) = named_space(&string) {
+            return Ok(length);
+        }
         let first_non_digit = string.find(|chr| match chr {
             '0'..='9' | '.' | '+' | '-' => false,
             _ => true,
@@ -455,20 +805,352 @@ impl FromXmlAttribute for Length {
             Some(x) => x,
             None => string.len(),
         };
-        if let Ok(num) = string[0..first_non_digit].parse() {
-            let unit = match string[first_non_digit..].trim() {
-                "em" => LengthUnit::Em,
-                "pt" => LengthUnit::Point,
-                // fallback to points
-                _ => LengthUnit::Point,
-            };
-            Ok(Length::new(num, unit))
+        let num = string[0..first_non_digit]
+            .parse()
+            .map_err(|_| "invalid number")?;
+        let unit = match string[first_non_digit..].trim() {
+            "em" => LengthUnit::Em,
+            "ex" => LengthUnit::Ex,
+            "pt" => LengthUnit::Point,
+            "px" => LengthUnit::Pixel,
+            "in" => LengthUnit::Inch,
+            "cm" => LengthUnit::Centimeter,
+            "mm" => LengthUnit::Millimeter,
+            "pc" => return Ok(Length::new(num * 12.0, LengthUnit::Point)),
+            "mu" => LengthUnit::MathUnit,
+            "%" => LengthUnit::Percent,
+            "" if num == 0.0 => LengthUnit::Point,
+            "" => Err("missing length unit")?,
+            _ => Err("unknown length unit")?,
+        };
+        Ok(Length::new(num, unit))
+    }
+}
+
+/// Returns the length of one of the named spaces of MathML, which are multiples of 1/18 em.
+fn named_space(name: &str) -> Option<Length> {
+    let (sign, name) = match name.strip_prefix("negative") {
+        Some(name) => (-1.0, name),
+        None => (1.0, name),
+    };
+    let math_units = match name {
+        "veryverythinmathspace" => 1.0,
+        "verythinmathspace" => 2.0,
+        "thinmathspace" => 3.0,
+        "mediummathspace" => 4.0,
+        "thickmathspace" => 5.0,
+        "verythickmathspace" => 6.0,
+        "veryverythickmathspace" => 7.0,
+        _ => return None,
+    };
+    Some(Length::new(sign * math_units, LengthUnit::MathUnit))
+}
+
+impl FromXmlAttribute for Color {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        let attr = attr.trim().to_ascii_lowercase();
+        let hex = match attr.strip_prefix('#') {
+            Some(hex) => hex,
+            None => return named_color(&attr).ok_or("unknown color name"),
+        };
+        let digits = hex
+            .chars()
+            .map(|chr| chr.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or("invalid hexadecimal color")?;
+        // the short forms repeat every digit
+        let components = match digits.len() {
+            3 | 4 => digits.iter().map(|&digit| digit * 17).collect::<Vec<_>>(),
+            6 | 8 => digits
+                .chunks(2)
+                .map(|pair| pair[0] * 16 + pair[1])
+                .collect(),
+            _ => Err("invalid hexadecimal color")?,
+        };
+        Ok(Color {
+            red: components[0],
+            green: components[1],
+            blue: components[2],
+            alpha: components.get(3).cloned().unwrap_or(255),
+        })
+    }
+}
+
+/// Returns one of the colours that MathML names like HTML 4.
+fn named_color(name: &str) -> Option<Color> {
+    let (red, green, blue) = match name {
+        "black" => (0x00, 0x00, 0x00),
+        "silver" => (0xc0, 0xc0, 0xc0),
+        "gray" => (0x80, 0x80, 0x80),
+        "white" => (0xff, 0xff, 0xff),
+        "maroon" => (0x80, 0x00, 0x00),
+        "red" => (0xff, 0x00, 0x00),
+        "purple" => (0x80, 0x00, 0x80),
+        "fuchsia" => (0xff, 0x00, 0xff),
+        "green" => (0x00, 0x80, 0x00),
+        "lime" => (0x00, 0xff, 0x00),
+        "olive" => (0x80, 0x80, 0x00),
+        "yellow" => (0xff, 0xff, 0x00),
+        "navy" => (0x00, 0x00, 0x80),
+        "blue" => (0x00, 0x00, 0xff),
+        "teal" => (0x00, 0x80, 0x80),
+        "aqua" => (0x00, 0xff, 0xff),
+        "transparent" => {
+            return Some(Color {
+                alpha: 0,
+                ..Color::rgb(0, 0, 0)
+            })
+        }
+        _ => return None,
+    };
+    Some(Color::rgb(red, green, blue))
+}
+
+impl FromXmlAttribute for HorizontalAlign {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "left" => Ok(HorizontalAlign::Left),
+            "center" => Ok(HorizontalAlign::Center),
+            "right" => Ok(HorizontalAlign::Right),
+            _ => Err("unrecognized horizontal alignment"),
+        }
+    }
+}
+
+impl FromXmlAttribute for VerticalAlign {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "top" => Ok(VerticalAlign::Top),
+            "bottom" => Ok(VerticalAlign::Bottom),
+            "center" => Ok(VerticalAlign::Center),
+            "baseline" => Ok(VerticalAlign::Baseline),
+            "axis" => Ok(VerticalAlign::Axis),
+            _ => Err("unrecognized vertical alignment"),
+        }
+    }
+}
+
+impl FromXmlAttribute for LineStyle {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "none" => Ok(LineStyle::None),
+            "solid" => Ok(LineStyle::Solid),
+            "dashed" => Ok(LineStyle::Dashed),
+            _ => Err("unrecognized line style"),
+        }
+    }
+}
+
+impl FromXmlAttribute for LineBreak {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "auto" => Ok(LineBreak::Auto),
+            "newline" => Ok(LineBreak::NewLine),
+            "nobreak" => Ok(LineBreak::NoBreak),
+            "goodbreak" => Ok(LineBreak::GoodBreak),
+            "badbreak" => Ok(LineBreak::BadBreak),
+            _ => Err("unrecognized line break"),
+        }
+    }
+}
+
+impl FromXmlAttribute for LineBreakStyle {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "before" | "infixlinebreakstyle" => Ok(LineBreakStyle::Before),
+            "after" => Ok(LineBreakStyle::After),
+            "duplicate" => Ok(LineBreakStyle::Duplicate),
+            _ => Err("unrecognized line break style"),
+        }
+    }
+}
+
+/// Parses the `groupalign` attribute of a table or a table row, which holds a list of alignments
+/// in braces for every column, e.g. `{left right} {center}`.
+fn parse_group_align_list(
+    attr: &str,
+) -> std::result::Result<Vec<Vec<HorizontalAlign>>, &'static str> {
+    attr.split('}')
+        .map(str::trim)
+        .filter(|column| !column.is_empty())
+        .map(|column| match column.strip_prefix('{') {
+            Some(column) => column.parse_xml(),
+            None => Err("expected a list of alignments in braces"),
+        })
+        .collect()
+}
+
+/// Parses the `linethickness` attribute of a `mfrac` element.
+///
+/// Named thicknesses, percentages and numbers without a unit are relative to the default
+/// thickness of the fraction rule.
+fn parse_line_thickness(attr: &str) -> std::result::Result<Length, &'static str> {
+    let attr = attr.trim();
+    let relative = |value| Length::new(value, LengthUnit::FractionRuleThickness);
+    match attr {
+        "thin" => Ok(relative(0.5)),
+        "medium" => Ok(relative(1.0)),
+        "thick" => Ok(relative(2.0)),
+        _ if attr.ends_with('%') => attr[..attr.len() - 1]
+            .trim()
+            .parse::<f32>()
+            .map(|percent| relative(percent / 100.0))
+            .map_err(|_| "invalid percentage"),
+        _ => match attr.parse::<f32>() {
+            Ok(value) => Ok(relative(value)),
+            Err(_) => attr.parse_xml(),
+        },
+    }
+}
+
+/// Parses the `minsize` or `maxsize` attribute of a `mo` element.
+///
+/// Percentages and numbers without a unit are multiples of the size that the operator has without
+/// stretching.
+fn parse_operator_size(attr: &str) -> std::result::Result<Length, &'static str> {
+    let attr = attr.trim();
+    let relative = |value| Length::new(value, LengthUnit::NormalSize);
+    if let Some(percent) = attr.strip_suffix('%') {
+        return percent
+            .trim()
+            .parse::<f32>()
+            .map(|percent| relative(percent / 100.0))
+            .map_err(|_| "invalid percentage");
+    }
+    match attr.parse::<f32>() {
+        Ok(value) => Ok(relative(value)),
+        Err(_) => attr.parse_xml(),
+    }
+}
+
+/// Parses the `mathsize` attribute.
+///
+/// The named sizes `small` and `big` are a fixed percentage of the surrounding size.
+fn parse_math_size(attr: &str) -> std::result::Result<Length, &'static str> {
+    match attr.trim() {
+        "small" => Ok(Length::new(80.0, LengthUnit::Percent)),
+        "normal" => Ok(Length::new(100.0, LengthUnit::Percent)),
+        "big" => Ok(Length::new(120.0, LengthUnit::Percent)),
+        attr => attr.parse_xml(),
+    }
+}
+
+/// Parses the value of an attribute of a `mpadded` element.
+///
+/// A percentage without a pseudo-unit refers to the dimension given by `default_unit`.
+fn parse_padded_length(
+    attr: &str,
+    default_unit: PseudoUnit,
+) -> std::result::Result<PaddedLength, &'static str> {
+    let attr = attr.trim();
+    let (is_increment, sign, attr) = if let Some(attr) = attr.strip_prefix('+') {
+        (true, 1.0, attr)
+    } else if let Some(attr) = attr.strip_prefix('-') {
+        (true, -1.0, attr)
+    } else {
+        (false, 1.0, attr)
+    };
+    if let Some(length) = named_space(attr) {
+        return Ok(PaddedLength {
+            is_increment,
+            value: PaddedValue::Length(Length::new(sign * length.value, length.unit)),
+        });
+    }
+    let number_end = attr
+        .find(|chr: char| !chr.is_ascii_digit() && chr != '.')
+        .unwrap_or(attr.len());
+    let number: f32 = attr[..number_end].parse().map_err(|_| "invalid number")?;
+    let unit = attr[number_end..].trim();
+    let (factor, is_percentage, unit) = if let Some(unit) = unit.strip_prefix('%') {
+        (sign * number / 100.0, true, unit.trim())
+    } else {
+        (sign * number, false, unit)
+    };
+    let value = match unit {
+        "" => PaddedValue::Dimension(factor, default_unit),
+        "width" => PaddedValue::Dimension(factor, PseudoUnit::Width),
+        "height" => PaddedValue::Dimension(factor, PseudoUnit::Height),
+        "depth" => PaddedValue::Dimension(factor, PseudoUnit::Depth),
+        _ if is_percentage => Err("a percentage needs a pseudo-unit")?,
+        unit => {
+            let length: Length = format!("{}{}", factor, unit).parse_xml()?;
+            PaddedValue::Length(length)
+        }
+    };
+    Ok(PaddedLength {
+        is_increment,
+        value,
+    })
+}
+
+impl FromXmlAttribute for MathStyle {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "block" => Ok(MathStyle::Display),
+            "inline" => Ok(MathStyle::Inline),
+            _ => Err("unrecognized display value"),
+        }
+    }
+}
+
+impl FromXmlAttribute for ScriptLevel {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        let attr = attr.trim();
+        let result = if attr.starts_with('+') || attr.starts_with('-') {
+            attr.parse().map(ScriptLevel::Relative)
         } else {
-            Err("invalid number")?
+            attr.parse().map(ScriptLevel::Absolute)
+        };
+        result.map_err(|_| "invalid script level")
+    }
+}
+
+impl FromXmlAttribute for Notation {
+    type Err = &'static str;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        match attr {
+            "longdiv" => Ok(Notation::LongDiv),
+            "actuarial" => Ok(Notation::Actuarial),
+            "radical" => Ok(Notation::Radical),
+            "box" => Ok(Notation::Box),
+            "roundedbox" => Ok(Notation::RoundedBox),
+            "circle" => Ok(Notation::Circle),
+            "left" => Ok(Notation::Left),
+            "right" => Ok(Notation::Right),
+            "top" => Ok(Notation::Top),
+            "bottom" => Ok(Notation::Bottom),
+            "updiagonalstrike" => Ok(Notation::UpDiagonalStrike),
+            "downdiagonalstrike" => Ok(Notation::DownDiagonalStrike),
+            "verticalstrike" => Ok(Notation::VerticalStrike),
+            "horizontalstrike" => Ok(Notation::HorizontalStrike),
+            "updiagonalarrow" | "northeastarrow" => Ok(Notation::UpDiagonalArrow),
+            "southeastarrow" => Ok(Notation::DownDiagonalArrow),
+            "uparrow" => Ok(Notation::UpArrow),
+            "downarrow" => Ok(Notation::DownArrow),
+            "leftarrow" => Ok(Notation::LeftArrow),
+            "rightarrow" => Ok(Notation::RightArrow),
+            "madruwb" => Ok(Notation::MAdruwb),
+            _ => Err("unrecognized notation"),
         }
     }
 }
 
+/// A whitespace separated list of values as used e.g. by the `columnalign` attribute.
+impl<T: FromXmlAttribute> FromXmlAttribute for Vec<T> {
+    type Err = T::Err;
+    fn from_xml_attr(attr: &str) -> std::result::Result<Self, Self::Err> {
+        attr.split_whitespace().map(T::from_xml_attr).collect()
+    }
+}
+
 impl FromXmlAttribute for bool {
     type Err = &'static str;
     fn from_xml_attr(bytes: &str) -> std::result::Result<Self, Self::Err> {
@@ -562,4 +1244,842 @@ mod tests {
             ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
         }
     }
+
+    #[test]
+    fn test_atom_classes() {
+        // the spaces of an operator do not change its class
+        let xml = "<mo>(</mo><mi>a</mi><mo>+</mo><mi>b</mi><mo>)</mo>\
+                   <mo lspace=\"0\" rspace=\"0\">=</mo><mo>&#x2211;</mo><mi>c</mi><mo>,</mo>\
+                   <mo>!</mo>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        let classes: Vec<_> = match *expr.item {
+            MathItem::List(ref list) => list
+                .iter()
+                .filter_map(|expr| match *expr.item {
+                    MathItem::Operator(ref operator) => Some(operator.atom_class),
+                    _ => None,
+                })
+                .collect(),
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        assert_eq!(
+            classes,
+            [
+                AtomClass::Open,
+                AtomClass::Bin,
+                AtomClass::Close,
+                AtomClass::Rel,
+                AtomClass::Op,
+                AtomClass::Punct,
+                AtomClass::Ord,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_break_attributes() {
+        let xml = "<mi>x</mi><mo linebreak=\"newline\" linebreakstyle=\"after\" \
+                   indentalign=\"right\">=</mo><mi>y</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *find_operator(&expr).item {
+            MathItem::Operator(ref operator) => {
+                assert_eq!(operator.line_break, LineBreak::NewLine);
+                assert_eq!(operator.line_break_style, LineBreakStyle::After);
+                assert_eq!(operator.indent_align, Some(HorizontalAlign::Right));
+            }
+            ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_stretch_size_attributes() {
+        let xml = "<mo minsize=\"2em\" maxsize=\"24pt\">(</mo><mi>x</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *find_operator(&expr).item {
+            MathItem::Operator(ref operator) => {
+                let constraints = operator.stretch_constraints.expect("fence is not stretchy");
+                assert_eq!(constraints.min_size, Some(Length::em(2.0)));
+                assert_eq!(
+                    constraints.max_size,
+                    Some(Length::new(24.0, LengthUnit::Point))
+                );
+                assert!(constraints.symmetric);
+            }
+            ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
+        }
+
+        // sizes without a unit are relative to the unstretched operator
+        let relative = |value| Ok(Length::new(value, LengthUnit::NormalSize));
+        assert_eq!(parse_operator_size("2"), relative(2.0));
+        assert_eq!(parse_operator_size(" 150% "), relative(1.5));
+        assert_eq!(parse_operator_size("1.5em"), Ok(Length::em(1.5)));
+        assert!(parse_operator_size("%").is_err());
+
+        let xml = "<mo maxsize=\"infinity\" minsize=\"0.5\">(</mo><mi>x</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *find_operator(&expr).item {
+            MathItem::Operator(ref operator) => {
+                let constraints = operator.stretch_constraints.expect("fence is not stretchy");
+                assert_eq!(
+                    constraints.min_size,
+                    Some(Length::new(0.5, LengthUnit::NormalSize))
+                );
+                assert_eq!(constraints.max_size, None);
+            }
+            ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_alignment_elements() {
+        let xml = "<mtable groupalign=\"{left right} {center}\">\
+                   <mtr groupalign=\"{right}\"><mtd groupalign=\"center left\">\
+                   <maligngroup groupalign=\"right\"/><mi>x</mi><malignmark/><mo>=</mo>\
+                   <mn>3<malignmark/>.14</mn></mtd></mtr>\
+                   </mtable>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        let table = match *expr.item {
+            MathItem::Table(ref table) => table,
+            ref other_item => panic!("Expected MathItem::Table. Found {:?}.", other_item),
+        };
+        use HorizontalAlign::*;
+        assert_eq!(table.group_align, vec![vec![Left, Right], vec![Center]]);
+        let row = &table.rows[0];
+        assert_eq!(row.group_align, vec![vec![Right]]);
+        assert_eq!(row.cells[0].group_align, vec![Center, Left]);
+
+        let list = match *row.cells[0].content.as_ref().unwrap().item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        match *list[0].item {
+            MathItem::AlignGroup(group) => assert_eq!(group.group_align, Some(Right)),
+            ref other_item => panic!("Expected MathItem::AlignGroup. Found {:?}.", other_item),
+        }
+        assert!(matches!(*list[2].item, MathItem::AlignMark));
+        // the mark does not keep the equals sign from being an infix operator
+        match *list[3].item {
+            MathItem::Operator(ref operator) => {
+                assert_eq!(operator.leading_space, Length::em(5.0 / 18.0))
+            }
+            ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
+        }
+        // a mark inside of a token splits its text
+        match *list[4].item {
+            MathItem::List(ref fields) => {
+                assert_eq!(fields.len(), 3);
+                assert!(matches!(*fields[1].item, MathItem::AlignMark));
+            }
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        let xml = "<mtable columnalign=\"left right\" rowlines=\"solid\">\
+                   <mtr><mtd><mi>a</mi></mtd><mtd columnalign=\"center\"><mi>b</mi></mtd></mtr>\
+                   <mlabeledtr><mtd><mtext>(1)</mtext></mtd><mtd><mi>c</mi></mtd></mlabeledtr>\
+                   </mtable>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Table(ref table) => {
+                assert_eq!(
+                    table.column_align,
+                    vec![HorizontalAlign::Left, HorizontalAlign::Right]
+                );
+                assert_eq!(table.row_lines, vec![LineStyle::Solid]);
+                assert_eq!(table.rows.len(), 2);
+                assert_eq!(table.rows[0].cells.len(), 2);
+                assert_eq!(
+                    table.rows[0].cells[1].column_align,
+                    Some(HorizontalAlign::Center)
+                );
+                assert_eq!(table.rows[1].cells.len(), 1);
+                assert!(table.rows[1].label.is_some());
+            }
+            ref other_item => panic!("Expected MathItem::Table. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_enclosure_notations() {
+        let xml = "<menclose notation=\"box  updiagonalstrike\"><mi>x</mi></menclose>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Enclosure(ref enclosure) => assert_eq!(
+                enclosure.notations,
+                vec![Notation::Box, Notation::UpDiagonalStrike]
+            ),
+            ref other_item => panic!("Expected MathItem::Enclosure. Found {:?}.", other_item),
+        }
+
+        // unknown notations are skipped
+        let xml = "<menclose notation=\"box phasorangle\"><mi>x</mi></menclose>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Enclosure(ref enclosure) => {
+                assert_eq!(enclosure.notations, vec![Notation::Box])
+            }
+            ref other_item => panic!("Expected MathItem::Enclosure. Found {:?}.", other_item),
+        }
+
+        // longdiv is the default notation
+        let xml = "<menclose><mi>x</mi></menclose>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Enclosure(ref enclosure) => {
+                assert_eq!(enclosure.notations, vec![Notation::LongDiv])
+            }
+            ref other_item => panic!("Expected MathItem::Enclosure. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_style() {
+        let xml = "<mstyle displaystyle=\"false\" scriptlevel=\"+1\"><mi>x</mi><mi>y</mi></mstyle>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Styled(ref styled) => {
+                assert_eq!(styled.math_style, Some(MathStyle::Inline));
+                assert_eq!(styled.script_level, Some(ScriptLevel::Relative(1)));
+                assert_ne!(
+                    styled.content.as_ref().unwrap().get_user_data(),
+                    expr.get_user_data()
+                );
+            }
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+
+        // `displaystyle` takes precedence over `display`
+        let xml = "<math display=\"block\" displaystyle=\"false\"><mi>x</mi></math>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Styled(ref styled) => assert_eq!(styled.math_style, Some(MathStyle::Inline)),
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_math_size() {
+        let math_size = |xml: &str| match *parse(xml.as_bytes()).unwrap().item {
+            MathItem::Styled(ref styled) => styled.math_size,
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        };
+        assert_eq!(
+            math_size("<mstyle mathsize=\"200%\"><mi>x</mi></mstyle>"),
+            Some(Length::new(200.0, LengthUnit::Percent))
+        );
+        assert_eq!(
+            math_size("<mi mathsize=\"big\">x</mi>"),
+            Some(Length::new(120.0, LengthUnit::Percent))
+        );
+        assert_eq!(
+            math_size("<mn mathsize=\"24pt\">2</mn>"),
+            Some(Length::new(24.0, LengthUnit::Point))
+        );
+
+        // an operator with a size is still an embellished operator and gets the infix spacing
+        let xml = "<mrow><mi>x</mi><mo mathsize=\"small\">+</mo><mi>y</mi></mrow>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        let styled = match *expr.item {
+            MathItem::List(ref list) => &list[1],
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        match *styled.item {
+            MathItem::Styled(Styled {
+                content: Some(ref content),
+                ..
+            }) => match *content.item {
+                MathItem::Operator(ref operator) => assert!(!operator.leading_space.is_null()),
+                ref other_item => panic!("Expected MathItem::Operator. Found {:?}.", other_item),
+            },
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_colors() {
+        let parse_color = |attr: &str| attr.parse_xml::<Color>();
+        assert_eq!(parse_color("#FF8000"), Ok(Color::rgb(255, 128, 0)));
+        assert_eq!(parse_color("#f80"), Ok(Color::rgb(255, 136, 0)));
+        assert_eq!(
+            parse_color("#ff800080"),
+            Ok(Color {
+                alpha: 128,
+                ..Color::rgb(255, 128, 0)
+            })
+        );
+        assert_eq!(parse_color(" Teal "), Ok(Color::rgb(0, 128, 128)));
+        assert_eq!(parse_color("transparent").map(|color| color.alpha), Ok(0));
+        assert!(parse_color("#ff80").is_ok());
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("#gg0000").is_err());
+        assert!(parse_color("reddish").is_err());
+
+        let xml = "<mstyle mathcolor=\"red\" mathbackground=\"#ffff00\"><mi>x</mi></mstyle>";
+        match *parse(xml.as_bytes()).unwrap().item {
+            MathItem::Styled(ref styled) => {
+                assert_eq!(styled.math_color, Some(Color::rgb(255, 0, 0)));
+                assert_eq!(styled.math_background, Some(Color::rgb(255, 255, 0)));
+            }
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+        let expr = parse("<mn mathcolor=\"blue\">1</mn>".as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Styled(ref styled) => {
+                assert_eq!(styled.math_color, Some(Color::rgb(0, 0, 255)));
+                assert_eq!(styled.math_background, None);
+            }
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_direction() {
+        let direction_of = |xml: &str| match *parse(xml.as_bytes()).unwrap().item {
+            MathItem::Styled(ref styled) => styled.direction,
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        };
+        let rtl = Some(TextDirection::Rtl);
+        assert_eq!(
+            direction_of("<mrow dir=\"rtl\"><mi>a</mi><mi>b</mi></mrow>"),
+            rtl
+        );
+        assert_eq!(direction_of("<mstyle dir=\"rtl\"><mi>a</mi></mstyle>"), rtl);
+        assert_eq!(direction_of("<mi dir=\"rtl\">a</mi>"), rtl);
+        assert_eq!(
+            direction_of("<mstyle dir=\"ltr\"><mi>a</mi></mstyle>"),
+            Some(TextDirection::Ltr)
+        );
+
+        // an invalid direction is an error like any other invalid attribute, the recovering
+        // parser ignores it
+        let xml = "<mrow dir=\"up\"><mi>a</mi><mi>b</mi></mrow>";
+        let error = parse(xml.as_bytes()).unwrap_err();
+        match error.error_type {
+            ErrorType::InvalidAttribute {
+                ref element,
+                ref attribute,
+                ref value,
+            } => assert_eq!((&**element, &**attribute, &**value), ("mrow", "dir", "up")),
+            ref other => panic!("Expected ErrorType::InvalidAttribute. Found {:?}.", other),
+        }
+        let (expr, errors) = parse_with_recovery(xml.as_bytes()).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(*expr.item, MathItem::List(_)));
+    }
+
+    #[test]
+    fn test_invalid_attributes() {
+        let documents = [
+            "<mi mathcolor=\"nocolor\">x</mi>",
+            "<mstyle mathbackground=\"blu\"><mi>x</mi></mstyle>",
+            "<mi mathsize=\"huge\">x</mi>",
+            "<mi mathvariant=\"bolder\">x</mi>",
+            "<mstyle scriptlevel=\"up\"><mi>x</mi></mstyle>",
+            "<mspace width=\"wide\"/>",
+            "<mpadded height=\"+x\"><mi>x</mi></mpadded>",
+            "<mfrac linethickness=\"heavy\"><mi>a</mi><mi>b</mi></mfrac>",
+            "<mfrac bevelled=\"yes\"><mi>a</mi><mi>b</mi></mfrac>",
+            "<mo form=\"middle\">+</mo>",
+            "<mo lspace=\"wide\">+</mo>",
+            "<mo stretchy=\"yes\">(</mo>",
+            "<mo maxsize=\"big\">(</mo>",
+            "<mtable frame=\"dotted\"><mtr><mtd/></mtr></mtable>",
+            "<mtable><mtr rowalign=\"middle\"><mtd/></mtr></mtable>",
+            "<mtable><mtr><mtd columnalign=\"centre\"/></mtr></mtable>",
+        ];
+        for xml in documents.iter() {
+            match parse(xml.as_bytes()).unwrap_err().error_type {
+                ErrorType::InvalidAttribute {
+                    ref element,
+                    ref attribute,
+                    ref value,
+                } => {
+                    // the error names the element and its only attribute
+                    assert!(xml.contains(&format!("<{} {}=\"{}\"", element, attribute, value)));
+                }
+                ref other => panic!("Expected ErrorType::InvalidAttribute. Found {:?}.", other),
+            }
+            let (_, errors) = parse_with_recovery(xml.as_bytes()).unwrap();
+            assert_eq!(errors.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error_type = |xml: &str| parse(xml.as_bytes()).unwrap_err().error_type;
+        match error_type("<mfrac><mn>1</mn></mfrac>") {
+            ErrorType::WrongArgumentCount {
+                ref element,
+                expected: 2,
+                found: 1,
+            } => assert_eq!(element, "mfrac"),
+            other => panic!("Expected ErrorType::WrongArgumentCount. Found {:?}.", other),
+        }
+        match error_type("<mover accent=\"yes\"><mi>x</mi><mo>^</mo></mover>") {
+            ErrorType::InvalidAttribute { ref attribute, .. } => assert_eq!(attribute, "accent"),
+            other => panic!("Expected ErrorType::InvalidAttribute. Found {:?}.", other),
+        }
+        match error_type("<mi>a<mrow/></mi>") {
+            ErrorType::UnsupportedElement(ref name) => assert_eq!(name, "mrow"),
+            other => panic!("Expected ErrorType::UnsupportedElement. Found {:?}.", other),
+        }
+        match error_type("<mi>x</mi><mfoo/>") {
+            ErrorType::UnknownElement(ref name) => assert_eq!(name, "mfoo"),
+            other => panic!("Expected ErrorType::UnknownElement. Found {:?}.", other),
+        }
+
+        // the shortcuts for the other parsers fail in the same way
+        let mut context = ParseContext::default();
+        let one = context.number("1").unwrap();
+        match context.schema("mfrac", Attributes::default(), vec![one]) {
+            Err(ParsingError {
+                error_type: ErrorType::WrongArgumentCount { .. },
+                ..
+            }) => {}
+            other => panic!("Expected ErrorType::WrongArgumentCount. Found {:?}.", other),
+        }
+        match context.schema("mtable", Attributes::default(), Vec::new()) {
+            Err(ParsingError {
+                error_type: ErrorType::UnsupportedElement(ref name),
+                ..
+            }) => assert_eq!(name, "mtable"),
+            other => panic!("Expected ErrorType::UnsupportedElement. Found {:?}.", other),
+        }
+
+        // unless they recover
+        let mut context = ParseContext::with_recovery();
+        let one = context.number("1").unwrap();
+        let fraction = context
+            .schema("mfrac", Attributes::default(), vec![one])
+            .unwrap();
+        assert!(matches!(*fraction.item, MathItem::Styled(_)));
+        let table = context
+            .schema("mtable", Attributes::default(), Vec::new())
+            .unwrap();
+        assert!(matches!(*table.item, MathItem::Styled(_)));
+        assert_eq!(context.take_errors().len(), 2);
+    }
+
+    #[test]
+    fn test_recovery() {
+        let xml = "<mi>a</mi><mfrac><mn>1</mn></mfrac><mi dir=\"up\">b</mi><mfoo><mi>c</mi></mfoo>\
+                   <mtd/><mi>d</mi>";
+        let (expr, errors) = parse_with_recovery(xml.as_bytes()).unwrap();
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(
+            errors[0].error_type,
+            ErrorType::WrongArgumentCount { .. }
+        ));
+        assert!(matches!(
+            errors[1].error_type,
+            ErrorType::InvalidAttribute { .. }
+        ));
+        assert!(matches!(errors[2].error_type, ErrorType::UnknownElement(_)));
+        let list = match *expr.item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        // the invalid attribute is ignored, the other elements are replaced by `merror`
+        assert_eq!(list.len(), 6);
+        for &index in &[1, 3, 4] {
+            match *list[index].item {
+                MathItem::Styled(ref styled) => {
+                    assert_eq!(styled.math_color, Some(Color::rgb(255, 0, 0)))
+                }
+                ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+            }
+        }
+        assert!(matches!(*list[5].item, MathItem::Field(_)));
+
+        // errors in the XML structure cannot be recovered from
+        assert!(parse_with_recovery("<mrow><mi>a</mi></mi>".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_spans() {
+        let xml = "<mrow>\n  <msup><mi>x</mi><mn>2</mn></msup>\n  <mspace width=\"1em\"/>\n</mrow>";
+        let mut context = ParseContext::default();
+        let expr = parse_with_context(xml.as_bytes(), &mut context).unwrap();
+        let source = |expr: &MathExpression| {
+            let span = context.span(expr.get_user_data()).unwrap();
+            &xml[span]
+        };
+        let list = match *expr.item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        assert_eq!(source(&expr), xml);
+        assert_eq!(source(&list[0]), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(source(&list[1]), "<mspace width=\"1em\"/>");
+        match *list[0].item {
+            MathItem::Atom(ref atom) => {
+                assert_eq!(source(atom.nucleus.as_ref().unwrap()), "<mi>x</mi>");
+                assert_eq!(source(atom.top_right.as_ref().unwrap()), "<mn>2</mn>");
+            }
+            ref other_item => panic!("Expected MathItem::Atom. Found {:?}.", other_item),
+        }
+
+        let xml = "<mrow>\n  <mfrac><mi>x</mi></mfrac>\n</mrow>";
+        let error = parse(xml.as_bytes()).unwrap_err();
+        assert_eq!(error.line_column(xml.as_bytes()), Some((2, 3)));
+        let error = parse("<mi>x</mo>".as_bytes()).unwrap_err();
+        assert!(error.position.is_some());
+    }
+
+    #[test]
+    fn test_inherited_math_variant() {
+        let xml = "<mstyle mathvariant=\"bold\"><mrow><mi>x</mi></mrow></mstyle><mi>x</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        let fields = match *expr.item {
+            MathItem::List(ref list) => list
+                .iter()
+                .map(|expr| match *expr.item {
+                    MathItem::Field(Field::Unicode(ref text)) => text.clone(),
+                    ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+                })
+                .collect::<Vec<_>>(),
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        assert_eq!(fields, vec!["\u{1D431}", "\u{1D465}"]); // bold x, italic x
+
+        // other attributes of a token don't replace the inherited variant
+        let xml = "<mstyle mathvariant=\"bold\"><mn mathcolor=\"red\">1</mn></mstyle>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Styled(Styled {
+                content: Some(ref content),
+                ..
+            }) => match *content.item {
+                MathItem::Field(Field::Unicode(ref text)) => assert_eq!(text, "\u{1D7CF}"),
+                ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+            },
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_styled_embellished_operator() {
+        let xml = "<mi>x</mi><mstyle scriptlevel=\"1\"><mo>=</mo></mstyle><mi>y</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        let styled = match *expr.item {
+            MathItem::List(ref list) => &list[1],
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        };
+        match *styled.item {
+            MathItem::Styled(Styled {
+                content: Some(ref content),
+                ..
+            }) => assert!(find_operator(content).get_user_data() == content.get_user_data()),
+            ref other_item => panic!("Expected MathItem::Styled. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_multiscripts() {
+        let xml = "<mmultiscripts><mi>R</mi><mi>i</mi><none/><none/><mi>j</mi>\
+                   <mprescripts/><mn>6</mn><mn>14</mn></mmultiscripts>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::MultiScripts(ref multiscripts) => {
+                assert!(multiscripts.nucleus.is_some());
+                assert_eq!(multiscripts.post_scripts.len(), 2);
+                assert!(multiscripts.post_scripts[0].subscript.is_some());
+                assert!(multiscripts.post_scripts[0].superscript.is_none());
+                assert!(multiscripts.post_scripts[1].subscript.is_none());
+                assert!(multiscripts.post_scripts[1].superscript.is_some());
+                assert_eq!(multiscripts.pre_scripts.len(), 1);
+            }
+            ref other_item => panic!("Expected MathItem::MultiScripts. Found {:?}.", other_item),
+        }
+
+        // scripts have to come in pairs
+        let xml = "<mmultiscripts><mi>R</mi><mi>i</mi></mmultiscripts>";
+        assert!(parse(xml.as_bytes()).is_err());
+        let xml = "<mmultiscripts><mi>R</mi><mprescripts/><mi>i</mi></mmultiscripts>";
+        assert!(parse(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_padded_length() {
+        let parse = |attr| parse_padded_length(attr, PseudoUnit::Width).unwrap();
+        assert_eq!(
+            parse("+10%"),
+            PaddedLength {
+                is_increment: true,
+                value: PaddedValue::Dimension(0.1, PseudoUnit::Width),
+            }
+        );
+        assert_eq!(
+            parse("2 height"),
+            PaddedLength {
+                is_increment: false,
+                value: PaddedValue::Dimension(2.0, PseudoUnit::Height),
+            }
+        );
+        assert_eq!(
+            parse("-50% depth"),
+            PaddedLength {
+                is_increment: true,
+                value: PaddedValue::Dimension(-0.5, PseudoUnit::Depth),
+            }
+        );
+        assert_eq!(
+            parse("-1em"),
+            PaddedLength {
+                is_increment: true,
+                value: PaddedValue::Length(Length::em(-1.0)),
+            }
+        );
+        assert_eq!(
+            parse("-thinmathspace"),
+            PaddedLength {
+                is_increment: true,
+                value: PaddedValue::Length(Length::new(-3.0, LengthUnit::MathUnit)),
+            }
+        );
+        assert!(parse_padded_length("10%em", PseudoUnit::Width).is_err());
+        assert!(parse_padded_length("height", PseudoUnit::Width).is_err());
+    }
+
+    #[test]
+    fn test_length() {
+        let parse = |attr: &str| attr.parse_xml::<Length>();
+        assert_eq!(parse("1ex"), Ok(Length::new(1.0, LengthUnit::Ex)));
+        assert_eq!(parse(" 3px "), Ok(Length::new(3.0, LengthUnit::Pixel)));
+        assert_eq!(parse("0.5cm"), Ok(Length::new(0.5, LengthUnit::Centimeter)));
+        assert_eq!(parse("2in"), Ok(Length::new(2.0, LengthUnit::Inch)));
+        assert_eq!(parse("1pc"), Ok(Length::new(12.0, LengthUnit::Point)));
+        assert_eq!(parse("50%"), Ok(Length::new(50.0, LengthUnit::Percent)));
+        assert_eq!(parse("-3mu"), Ok(Length::new(-3.0, LengthUnit::MathUnit)));
+        assert_eq!(parse("0"), Ok(Length::default()));
+        assert_eq!(
+            parse("thickmathspace"),
+            Ok(Length::new(5.0, LengthUnit::MathUnit))
+        );
+        assert_eq!(
+            parse("negativeverythinmathspace"),
+            Ok(Length::new(-2.0, LengthUnit::MathUnit))
+        );
+        assert!(parse("2").is_err());
+        assert!(parse("2furlongs").is_err());
+        assert!(parse("em").is_err());
+    }
+
+    #[test]
+    fn test_space() {
+        let xml = "<mspace height=\"1em\" depth=\"0.5em\"/>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Space(ref space) => {
+                assert_eq!(space.width, Length::default());
+                assert_eq!(space.ascent, Length::em(1.0));
+                assert_eq!(space.descent, Length::em(0.5));
+            }
+            ref other_item => panic!("Expected MathItem::Space. Found {:?}.", other_item),
+        }
+    }
+
+    #[test]
+    fn test_fraction_attributes() {
+        let relative = |value| Length::new(value, LengthUnit::FractionRuleThickness);
+        assert_eq!(parse_line_thickness("thick"), Ok(relative(2.0)));
+        assert_eq!(parse_line_thickness("50%"), Ok(relative(0.5)));
+        assert_eq!(parse_line_thickness("0"), Ok(relative(0.0)));
+        assert_eq!(
+            parse_line_thickness("2pt"),
+            Ok(Length::new(2.0, LengthUnit::Point))
+        );
+
+        let xml = "<mfrac linethickness=\"0\" numalign=\"left\" denomalign=\"right\" \
+                   bevelled=\"true\"><mi>n</mi><mi>k</mi></mfrac>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::GeneralizedFraction(ref frac) => {
+                assert!(frac.thickness.unwrap().is_null());
+                assert_eq!(frac.numerator_align, HorizontalAlign::Left);
+                assert_eq!(frac.denominator_align, HorizontalAlign::Right);
+                assert!(frac.is_bevelled);
+            }
+            ref other_item => panic!(
+                "Expected MathItem::GeneralizedFraction. Found {:?}.",
+                other_item
+            ),
+        }
+    }
+
+    #[test]
+    fn test_glyph() {
+        let xml = "<mi><mglyph fontfamily=\"MyFont\" index=\"42\"/></mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Field(Field::Glyph(ref glyph)) => {
+                assert_eq!(glyph.glyph_code, 42);
+                assert_eq!(glyph.scale, ScaleFactor::new(100));
+            }
+            ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+        }
+
+        let xml = "<mi><mglyph fontfamily=\"MyFont\"/></mi>";
+        assert!(parse(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_token_whitespace() {
+        let tokens = [
+            ("<mtext>&#xA0;</mtext>", "\u{A0}"),
+            ("<mi>&#x2009;</mi>", "\u{2009}"),
+            ("<mtext> &#xA0;a&#x2009;\n</mtext>", "\u{A0}a\u{2009}"),
+        ];
+        for &(xml, expected) in tokens.iter() {
+            let expr = parse(xml.as_bytes()).unwrap();
+            match *expr.item {
+                MathItem::Field(Field::Unicode(ref text)) => assert_eq!(text, expected),
+                ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+            }
+        }
+    }
+
+    #[test]
+    fn test_attribute_entities() {
+        let expr = parse("<mi mathvariant=\"b&#x6F;ld\">x</mi>".as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Field(Field::Unicode(ref text)) => assert_eq!(text, "\u{1D431}"),
+            ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+        }
+
+        // values with unknown entities are kept as they are
+        let values = [
+            ("<mi mathvariant=\"&quot;bold&quot;\">x</mi>", "\"bold\""),
+            ("<mi mathvariant=\"&bold;\">x</mi>", "&bold;"),
+        ];
+        for &(xml, expected) in values.iter() {
+            match parse(xml.as_bytes()).unwrap_err().error_type {
+                ErrorType::InvalidAttribute { ref value, .. } => assert_eq!(value, expected),
+                other => panic!("Expected ErrorType::InvalidAttribute. Found {:?}.", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_semantics() {
+        let xml = "<semantics><mi>x</mi><annotation encoding=\"application/x-tex\">\\alpha &lt; x\
+                   </annotation><annotation-xml encoding=\"MathML-Content\"><ci>x</ci>\
+                   </annotation-xml></semantics>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::Semantics(ref semantics) => match semantics.content {
+                Some(ref content) => match *content.item {
+                    MathItem::Field(Field::Unicode(ref text)) => assert_eq!(text, "\u{1D465}"),
+                    ref other_item => panic!("Expected MathItem::Field. Found {:?}.", other_item),
+                },
+                None => panic!("Expected content."),
+            },
+            ref other_item => panic!("Expected MathItem::Semantics. Found {:?}.", other_item),
+        }
+        let tex = expr.annotation("application/x-tex").unwrap();
+        assert_eq!(tex.content, "\\alpha < x");
+        assert!(!tex.is_xml);
+        assert_eq!(expr.annotations()[1].content, "<ci>x</ci>");
+
+        // unknown elements like content MathML are errors
+        let xml = "<mrow><mn>1</mn><semantics><apply><plus/><cn>1</cn></apply>\
+                   <annotation-xml encoding=\"MathML-Presentation\"><mo>+</mo><mn>1</mn>\
+                   </annotation-xml></semantics></mrow>";
+        match parse(xml.as_bytes()).unwrap_err().error_type {
+            ErrorType::UnknownElement(ref name) => assert_eq!(name, "apply"),
+            other => panic!("Expected ErrorType::UnknownElement. Found {:?}.", other),
+        }
+
+        // when recovering they are kept as annotations and replaced by presentation MathML
+        let (expr, errors) = parse_with_recovery(xml.as_bytes()).unwrap();
+        assert_eq!(errors.len(), 1);
+        let annotations = expr.annotations();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].encoding.as_deref(), Some("MathML-Content"));
+        assert_eq!(
+            annotations[0].content,
+            "<apply><plus></plus><cn>1</cn></apply>"
+        );
+        match *expr.item {
+            MathItem::List(ref list) => match *list[1].item {
+                MathItem::Semantics(Semantics {
+                    content: Some(ref content),
+                    ..
+                }) => assert!(matches!(*content.item, MathItem::List(_))),
+                ref other_item => panic!("Expected MathItem::Semantics. Found {:?}.", other_item),
+            },
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        }
+
+        // a semantics element around an operator is an embellished operator
+        let xml = "<mi>a</mi><semantics><mo>+</mo><annotation>plus</annotation></semantics>\
+                   <mi>b</mi>";
+        let expr = parse(xml.as_bytes()).unwrap();
+        match *expr.item {
+            MathItem::List(ref list) => match *list[1].item {
+                MathItem::Semantics(Semantics {
+                    content: Some(ref content),
+                    ..
+                }) => assert!(matches!(*content.item, MathItem::Operator(_))),
+                ref other_item => panic!("Expected MathItem::Semantics. Found {:?}.", other_item),
+            },
+            ref other_item => panic!("Expected MathItem::List. Found {:?}.", other_item),
+        }
+
+        let xml = "<mi>a</mi><annotation>a</annotation>";
+        assert!(parse(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parse_source() {
+        let documents = [
+            "<mrow><mi>x</mi><mo>=</mo><mfrac><mn>1</mn><mn>2</mn></mfrac></mrow>",
+            "<mtable><mtr><mtd><mi>a</mi></mtd><mi>b</mi></mtr><mn>1</mn></mtable>",
+            "<mmultiscripts><mi>X</mi><none/><mi>j</mi><mprescripts/><mi>k</mi><none/>\
+             </mmultiscripts>",
+            "<mstyle mathvariant=\"bold\"><mi> x </mi><mo>&#x2211;</mo><malignmark/></mstyle>",
+        ];
+        for xml in documents.iter() {
+            // the events without positions, as they would come from a document tree
+            let mut source = source::QuickXmlSource::new(xml.as_bytes());
+            let events: Vec<_> = std::iter::from_fn(|| source.next_event())
+                .map(|event| event.unwrap())
+                .collect();
+            let mut context = ParseContext::default();
+            let expr = parse_source_with_context(events.into_iter(), &mut context).unwrap();
+            let parsed = parse(xml.as_bytes()).unwrap();
+            assert_eq!(format!("{:?}", expr), format!("{:?}", parsed));
+            assert_eq!(context.span(expr.get_user_data()), None);
+        }
+
+        let events = vec![
+            XmlEvent::Start {
+                name: "m:mi".to_owned(),
+                attributes: vec![
+                    ("xmlns:m".to_owned(), source::MATHML_NAMESPACE.to_owned()),
+                    ("dir".to_owned(), "up".to_owned()),
+                ],
+            },
+            XmlEvent::Text("x".to_owned()),
+            XmlEvent::End("m:mi".to_owned()),
+        ];
+        let error = parse_source(events.into_iter()).unwrap_err();
+        assert_eq!(error.position, None);
+        match error.error_type {
+            ErrorType::InvalidAttribute { ref attribute, .. } => assert_eq!(attribute, "dir"),
+            ref other => panic!("Expected an invalid attribute. Found {:?}.", other),
+        }
+
+        // only prefixes that are bound to the MathML namespace are removed
+        let xml = "<m:math xmlns:m=\"http://www.w3.org/1998/Math/MathML\" xmlns:svg=\"urn:svg\">\
+                   <m:mi>x</m:mi><svg:mi>y</svg:mi></m:math>";
+        match parse(xml.as_bytes()).unwrap_err().error_type {
+            ErrorType::UnknownElement(ref name) => assert_eq!(name, "svg:mi"),
+            ref other => panic!("Expected an unknown element. Found {:?}.", other),
+        }
+        let xml = "<m:mrow><m:mi>x</m:mi></m:mrow>";
+        assert!(parse(xml.as_bytes()).is_err());
+    }
 }
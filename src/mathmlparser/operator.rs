@@ -1,6 +1,7 @@
 use crate::types::{
-    Atom, GeneralizedFraction, Length, MathExpression, MathItem, Operator, OverUnder,
-    StretchConstraints,
+    Atom, AtomClass, GeneralizedFraction, HorizontalAlign, Length, LengthUnit, LineBreak,
+    LineBreakStyle, MathExpression, MathItem, MultiScripts, Operator, OverUnder, Padded, Phantom,
+    Semantics, StretchConstraints, Styled,
 };
 
 use super::operator_dict;
@@ -61,8 +62,13 @@ pub struct Attributes {
     pub form: Option<Form>,
     pub lspace: Option<Length>,
     pub rspace: Option<Length>,
+    pub min_size: Option<Length>,
+    pub max_size: Option<Length>,
     pub flags: Flags,
     pub user_overrides: Flags,
+    pub line_break: LineBreak,
+    pub line_break_style: LineBreakStyle,
+    pub indent_align: Option<HorizontalAlign>,
 }
 
 impl Attributes {
@@ -162,12 +168,19 @@ fn guess_operator_attributes(expr: &MathExpression, context: &mut ParseContext)
         .and_then(|chr| operator_dict::find_entry(chr, form))
         .unwrap_or_default();
 
-    if operator_attrs.lspace.is_none() {
-        operator_attrs.lspace = Some(Length::em(entry.lspace as f32 / 18.0f32));
-    }
-    if operator_attrs.rspace.is_none() {
-        operator_attrs.rspace = Some(Length::em(entry.rspace as f32 / 18.0f32));
-    }
+    // a percentage is relative to the space in the dictionary
+    let space = |length: Option<Length>, default: u8| {
+        let default = default as f32 / 18.0f32;
+        match length {
+            Some(length) if length.unit == LengthUnit::Percent => {
+                Length::em(default * length.value / 100.0)
+            }
+            Some(length) => length,
+            None => Length::em(default),
+        }
+    };
+    operator_attrs.lspace = Some(space(operator_attrs.lspace, entry.lspace));
+    operator_attrs.rspace = Some(space(operator_attrs.rspace, entry.rspace));
 
     // apply user overrides
     operator_attrs.flags = (operator_attrs.user_overrides & operator_attrs.flags)
@@ -196,6 +209,25 @@ fn find_core_operator<'a>(
             numerator: Some(ref mut numerator),
             ..
         }) => numerator,
+        &mut MathItem::Styled(Styled {
+            content: Some(ref mut content),
+            ..
+        }) => content,
+        &mut MathItem::MultiScripts(MultiScripts {
+            nucleus: Some(ref mut nucleus),
+            ..
+        }) => nucleus,
+        &mut MathItem::Padded(Padded {
+            content: Some(ref mut content),
+            ..
+        }) => content,
+        &mut MathItem::Phantom(Phantom {
+            content: Some(ref mut content),
+        }) => content,
+        &mut MathItem::Semantics(Semantics {
+            content: Some(ref mut content),
+            ..
+        }) => content,
         _ => return None,
     };
     find_core_operator(core, context)
@@ -218,6 +250,25 @@ fn set_movable_limits(embellished_op: &mut MathExpression, context: &mut ParseCo
             numerator: Some(ref mut numerator),
             ..
         }) => numerator,
+        MathItem::Styled(Styled {
+            content: Some(ref mut content),
+            ..
+        }) => content,
+        MathItem::MultiScripts(MultiScripts {
+            nucleus: Some(ref mut nucleus),
+            ..
+        }) => nucleus,
+        MathItem::Padded(Padded {
+            content: Some(ref mut content),
+            ..
+        }) => content,
+        MathItem::Phantom(Phantom {
+            content: Some(ref mut content),
+        }) => content,
+        MathItem::Semantics(Semantics {
+            content: Some(ref mut content),
+            ..
+        }) => content,
         _ => return,
     };
     set_movable_limits(&mut core_expr, context)
@@ -242,8 +293,9 @@ fn make_operator(expr: &mut MathExpression, context: &mut ParseContext) {
     if let Some(core_expr) = find_core_operator(expr, context) {
         let stretch_constraints = if flags.contains(Flags::STRETCHY) {
             Some(StretchConstraints {
+                min_size: operator_attrs.min_size,
+                max_size: operator_attrs.max_size,
                 symmetric: flags.contains(Flags::SYMMETRIC),
-                ..Default::default()
             })
         } else {
             None
@@ -256,10 +308,44 @@ fn make_operator(expr: &mut MathExpression, context: &mut ParseContext) {
             stretch_constraints,
             field,
             is_large_op: flags.contains(Flags::LARGEOP),
+            atom_class: atom_class(&operator_attrs),
             leading_space: operator_attrs.lspace.expect("operator has no lspace"),
             trailing_space: operator_attrs.rspace.expect("operator has no rspace"),
-            ..Default::default()
+            line_break: operator_attrs.line_break,
+            line_break_style: operator_attrs.line_break_style,
+            indent_align: operator_attrs.indent_align,
         };
         core_expr.item = Box::new(MathItem::Operator(new_elem));
     }
 }
+
+/// Returns the class that TeX would assign to an operator, judging from its entry in the operator
+/// dictionary. Fences are opening or closing depending on the form of the entry, the other
+/// operators are told apart by its spaces: relations have thick spaces on both sides and binary
+/// operators have medium spaces.
+fn atom_class(operator_attrs: &Attributes) -> AtomClass {
+    let flags = operator_attrs.flags;
+    let form = operator_attrs.form.unwrap_or_default();
+    let entry = operator_attrs
+        .character
+        .and_then(|chr| operator_dict::find_entry(chr, form));
+    if flags.contains(Flags::LARGEOP) {
+        return AtomClass::Op;
+    }
+    if flags.contains(Flags::FENCE) {
+        // a closing parenthesis in the middle of a row has the infix form but no infix entry
+        return match entry.map_or(form, |entry| entry.form) {
+            Form::Prefix => AtomClass::Open,
+            Form::Postfix => AtomClass::Close,
+            Form::Infix => AtomClass::Ord,
+        };
+    }
+    if flags.contains(Flags::SEPARATOR) {
+        return AtomClass::Punct;
+    }
+    match entry {
+        Some(entry) if entry.lspace >= 5 => AtomClass::Rel,
+        Some(entry) if entry.lspace == 4 => AtomClass::Bin,
+        _ => AtomClass::Ord,
+    }
+}
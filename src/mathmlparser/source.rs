@@ -0,0 +1,235 @@
+//! The input of the MathML parser. The parser reads elements, attributes and text from an
+//! [`XmlSource`], which can be an XML reader or a walk through a document tree that has already
+//! been parsed by other means.
+
+use std::ops::Range;
+
+use super::error::Result;
+
+#[cfg(feature = "mathml_parser")]
+use super::escape::StringExtUnescape;
+#[cfg(feature = "mathml_parser")]
+use quick_xml::{Event, ResultPos, XmlReader};
+#[cfg(feature = "mathml_parser")]
+use std::io::BufRead;
+
+/// A piece of the MathML input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlEvent {
+    /// The start of an element. A namespace prefix of the name, like the `m` of `m:mi`, is
+    /// removed if it is bound to the MathML namespace by an `xmlns:m` attribute of this or an
+    /// enclosing element.
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    /// The end of the element with the given name.
+    End(String),
+    /// Text in which all entity and character references have been resolved.
+    Text(String),
+}
+
+/// A source of MathML input for [`parse_source`](super::parse_source).
+///
+/// Every `Start` event has to be followed by the matching `End` event, after the events of the
+/// content of the element. The trait is implemented for all iterators over [`XmlEvent`]s, so a
+/// document tree that has been parsed by another XML or HTML parser can be turned into a source
+/// by collecting the events of its elements.
+pub trait XmlSource {
+    /// Returns the next event, or `None` at the end of the input.
+    fn next_event(&mut self) -> Option<Result<XmlEvent>>;
+
+    /// Returns the byte range in the input of the event that has been returned last, if it is
+    /// known. These ranges are used for the spans of the elements and the positions of errors.
+    fn span(&self) -> Option<Range<usize>> {
+        None
+    }
+}
+
+impl<I: Iterator<Item = XmlEvent>> XmlSource for I {
+    fn next_event(&mut self) -> Option<Result<XmlEvent>> {
+        self.next().map(Ok)
+    }
+}
+
+/// Reads MathML with quick-xml.
+#[cfg(feature = "mathml_parser")]
+pub(crate) struct QuickXmlSource<R: BufRead> {
+    reader: XmlReader<R>,
+    // the event after the one that has been returned last together with the position after it,
+    // which is read in advance to recognize empty elements
+    lookahead: Option<(Option<ResultPos<Event>>, usize)>,
+    // `true` if the event that has been returned last is the start of an empty element
+    empty_element: bool,
+    span: Range<usize>,
+}
+
+#[cfg(feature = "mathml_parser")]
+impl<R: BufRead> QuickXmlSource<R> {
+    pub(crate) fn new(file: R) -> Self {
+        QuickXmlSource {
+            reader: XmlReader::from_reader(file).trim_text(true),
+            lookahead: None,
+            empty_element: false,
+            span: 0..0,
+        }
+    }
+
+    fn read(&mut self) -> (Option<ResultPos<Event>>, usize) {
+        self.lookahead.take().unwrap_or_else(|| {
+            let event = self.reader.next();
+            (event, self.reader.buffer_position())
+        })
+    }
+}
+
+#[cfg(feature = "mathml_parser")]
+impl<R: BufRead> XmlSource for QuickXmlSource<R> {
+    fn next_event(&mut self) -> Option<Result<XmlEvent>> {
+        loop {
+            let (event, end) = self.read();
+            let event = match event {
+                Some(Ok(event)) => event,
+                Some(Err(error)) => return Some(Err(error.into())),
+                None => {
+                    self.span = end..end;
+                    return None;
+                }
+            };
+            let after_empty_element = std::mem::replace(&mut self.empty_element, false);
+            match event {
+                Event::Start(ref elem) => {
+                    // an empty element like `<none/>` is followed by its end element without
+                    // moving on, its start tag contains an additional "/"
+                    let next = self.read();
+                    self.empty_element = next.1 == end && matches!(next.0, Some(Ok(Event::End(_))));
+                    self.lookahead = Some(next);
+                    let tag_length = elem.content().len() + if self.empty_element { 3 } else { 2 };
+                    self.span = end.saturating_sub(tag_length)..end;
+                    let attributes = elem
+                        .attributes()
+                        .filter_map(|attribute| {
+                            let (name, value) = attribute.ok()?;
+                            let name = std::str::from_utf8(name).ok()?;
+                            let value = std::str::from_utf8(value).ok()?;
+                            // values with unknown entities are kept as they are
+                            let value = value.unescape().unwrap_or(value.into());
+                            Some((name.to_owned(), value.into_owned()))
+                        })
+                        .collect();
+                    return Some(Ok(XmlEvent::Start {
+                        name: String::from_utf8_lossy(elem.name()).into_owned(),
+                        attributes,
+                    }));
+                }
+                Event::End(ref elem) => {
+                    let tag_length = if after_empty_element {
+                        0
+                    } else {
+                        elem.name().len() + 3
+                    };
+                    self.span = end.saturating_sub(tag_length)..end;
+                    let name = String::from_utf8_lossy(elem.name()).into_owned();
+                    return Some(Ok(XmlEvent::End(name)));
+                }
+                Event::Text(ref text) => {
+                    self.span = end.saturating_sub(text.content().len())..end;
+                    let text = std::str::from_utf8(text.content())
+                        .map_err(Into::into)
+                        .and_then(|text| text.unescape())
+                        .map(|text| XmlEvent::Text(text.into_owned()));
+                    return Some(text);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        Some(self.span.clone())
+    }
+}
+
+/// The MathML namespace, to which a prefix has to be bound to be removed by [`Namespaces`].
+pub(crate) const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// Resolves the namespace prefixes of the element names of another source. A prefix that is bound
+/// to the MathML namespace, like the `m` of `m:mi` after `xmlns:m="…"`, is removed, so that the
+/// name matches the MathML element. Names with other prefixes are kept and don't match any MathML
+/// element.
+pub(crate) struct Namespaces<S> {
+    source: S,
+    // the prefixes declared by each open element together with whether they are bound to MathML,
+    // the first scope holds the prefixes that are bound outside of the source
+    scopes: Vec<Vec<(String, bool)>>,
+}
+
+impl<S: XmlSource> Namespaces<S> {
+    /// Reads from `source`, in which the given prefixes are bound to MathML from the start.
+    pub(crate) fn new(source: S, mathml_prefixes: Vec<String>) -> Self {
+        let outer = mathml_prefixes
+            .into_iter()
+            .map(|prefix| (prefix, true))
+            .collect();
+        Namespaces {
+            source,
+            scopes: vec![outer],
+        }
+    }
+
+    // Strips the namespace prefix from an element name if it is bound to MathML.
+    fn local_name(&self, name: String) -> String {
+        let colon = match name.find(':') {
+            Some(colon) => colon,
+            None => return name,
+        };
+        let is_mathml = self
+            .scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|(prefix, _)| *prefix == name[..colon])
+            .map(|&(_, is_mathml)| is_mathml)
+            == Some(true);
+        if is_mathml {
+            name[colon + 1..].to_owned()
+        } else {
+            name
+        }
+    }
+}
+
+impl<S: XmlSource> XmlSource for Namespaces<S> {
+    fn next_event(&mut self) -> Option<Result<XmlEvent>> {
+        let event = match self.source.next_event()? {
+            Ok(XmlEvent::Start { name, attributes }) => {
+                let declarations = attributes
+                    .iter()
+                    .filter_map(|(attribute, namespace)| {
+                        let prefix = attribute.strip_prefix("xmlns:")?;
+                        Some((prefix.to_owned(), namespace == MATHML_NAMESPACE))
+                    })
+                    .collect();
+                // the declarations of an element apply to its own name
+                self.scopes.push(declarations);
+                XmlEvent::Start {
+                    name: self.local_name(name),
+                    attributes,
+                }
+            }
+            Ok(XmlEvent::End(name)) => {
+                let name = self.local_name(name);
+                if self.scopes.len() > 1 {
+                    self.scopes.pop();
+                }
+                XmlEvent::End(name)
+            }
+            other => return Some(other),
+        };
+        Some(Ok(event))
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        self.source.span()
+    }
+}
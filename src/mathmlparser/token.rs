@@ -3,37 +3,27 @@ use std::borrow::Cow;
 
 use super::operator;
 use super::{
-    error::ParsingError, FromXmlAttribute, MathmlElement, MathmlInfo,
-    ParseContext,
+    error::ParsingError, Child, FromXmlAttribute, MathmlElement, MathmlInfo, ParseContext,
 };
 
 
-use crate::types::{Field, Length, MathExpression, MathItem, MathSpace};
+use crate::types::{
+    AlignGroup, Color, Field, HorizontalAlign, Length, MathExpression, MathItem, MathSpace, Styled,
+    TextDirection,
+};
 use crate::unicode_math::{convert_character_to_family, Family};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum TextDirection {
-    Ltr,
-    Rtl,
-}
-
 impl FromXmlAttribute for TextDirection {
     type Err = ();
     fn from_xml_attr(bytes: &str) -> std::result::Result<Self, Self::Err> {
-        if bytes == "rtl" {
-            Ok(TextDirection::Rtl)
-        } else {
-            Ok(TextDirection::Ltr)
+        match bytes {
+            "ltr" => Ok(TextDirection::Ltr),
+            "rtl" => Ok(TextDirection::Rtl),
+            _ => Err(()),
         }
     }
 }
 
-impl std::default::Default for TextDirection {
-    fn default() -> TextDirection {
-        TextDirection::Ltr
-    }
-}
-
 impl FromXmlAttribute for Family {
     type Err = ();
     fn from_xml_attr(bytes: &str) -> std::result::Result<Self, Self::Err> {
@@ -52,6 +42,10 @@ impl FromXmlAttribute for Family {
             "sans-serif-italic" => Ok(Family::SansSerifItalics),
             "sans-serif-bold-italic" => Ok(Family::SansSerifBoldItalics),
             "monospace" => Ok(Family::Monospace),
+            "initial" => Ok(Family::Initial),
+            "tailed" => Ok(Family::Tailed),
+            "looped" => Ok(Family::Looped),
+            "stretched" => Ok(Family::Stretched),
             _ => Err(()),
         }
     }
@@ -63,8 +57,10 @@ pub struct TokenStyle {
     // a single glyph or multiple glyphs. A single glyph is laid out in italic style. Multiple
     // glyphs would be layed out in normal style.
     pub math_variant: Option<Family>,
-    // TODO: missing math_size
-    pub direction: TextDirection,
+    pub math_size: Option<Length>,
+    pub math_color: Option<Color>,
+    pub math_background: Option<Color>,
+    pub direction: Option<TextDirection>,
 }
 
 pub trait StringExtMathml {
@@ -124,25 +120,36 @@ fn try_extract_char(field: &Field) -> Option<char> {
 pub struct Attributes {
     pub operator_attributes: operator::Attributes,
     pub token_style: TokenStyle,
-    pub horizontal_space: Option<Length>,
+    /// The dimensions of a `mspace` element.
+    pub space: Option<MathSpace>,
+    /// The `groupalign` attribute of a `maligngroup` element.
+    pub group_align: Option<HorizontalAlign>,
 }
 
 pub fn build_token<'a>(
-    fields: impl Iterator<Item = (Field, u64)>,
+    children: impl Iterator<Item = Child>,
     elem: MathmlElement,
     mut attributes: Attributes,
     context: &mut ParseContext,
     user_data: u64,
 ) -> Result<MathExpression, ParsingError> {
-    if let Some(width) = attributes.horizontal_space {
-        let item = MathExpression::new(
-            MathItem::Space(MathSpace::horizontal_space(width)),
-            user_data,
-        );
+    let space_like = if let Some(space) = attributes.space {
+        Some(MathItem::Space(space))
+    } else if elem.is("maligngroup") {
+        Some(MathItem::AlignGroup(AlignGroup {
+            group_align: attributes.group_align,
+        }))
+    } else if elem.is("malignmark") {
+        Some(MathItem::AlignMark)
+    } else {
+        None
+    };
+    if let Some(item) = space_like {
+        let item = MathExpression::new(item, user_data);
         context.mathml_info.insert(
             user_data,
             MathmlInfo {
-                operator_attrs: None,
+                is_space: true,
                 ..Default::default()
             },
         );
@@ -151,12 +158,17 @@ pub fn build_token<'a>(
 
     let mut list = vec![];
     let mut first_field_char = None;
-    for (field_num, field) in fields.enumerate() {
-        let (field, field_user_data) = field;
-        if field_num == 0 {
-            first_field_char = try_extract_char(&field);
-        }
-        let expr = MathExpression::new(MathItem::Field(field), field_user_data);
+    for (child_num, child) in children.enumerate() {
+        let expr = match child {
+            Child::Field((field, field_user_data)) => {
+                if child_num == 0 {
+                    first_field_char = try_extract_char(&field);
+                }
+                MathExpression::new(MathItem::Field(field), field_user_data)
+            }
+            // an alignment mark between the characters of the token
+            Child::Expression(expr) => expr,
+        };
         list.push(expr);
     }
 
@@ -181,6 +193,31 @@ pub fn build_token<'a>(
         },
     );
 
+    // the size, colours and direction are changed by wrapping the token, which stays an
+    // embellished operator
+    let style = attributes.token_style;
+    if style.math_size.is_some()
+        || style.math_color.is_some()
+        || style.math_background.is_some()
+        || style.direction.is_some()
+    {
+        let styled_user_data = context.new_user_data();
+        let info = MathmlInfo {
+            operator_attrs: context.operator_attrs(&expr).cloned(),
+            ..Default::default()
+        };
+        context.mathml_info.insert(styled_user_data, info);
+        let item = MathItem::Styled(Styled {
+            content: Some(expr),
+            math_size: style.math_size,
+            math_color: style.math_color,
+            math_background: style.math_background,
+            direction: style.direction,
+            ..Default::default()
+        });
+        return Ok(MathExpression::new(item, styled_user_data));
+    }
+
     Ok(expr)
 }
 
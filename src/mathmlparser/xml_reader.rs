@@ -1,143 +1,402 @@
 use super::error::{ErrorType, ParsingError, Result};
+#[cfg(feature = "mathml_parser")]
+use super::source::QuickXmlSource;
+use super::source::{Namespaces, XmlEvent, XmlSource};
 use super::{
-    escape::StringExtUnescape, match_math_element, operator, parse_fixed_schema, parse_list_schema,
-    token, ArgumentRequirements, AttributeParse, ElementType, MathmlElement, ParseContext,
-    SchemaAttributes, StringExtMathml,
+    match_math_element, operator, parse_fixed_schema, parse_group_align_list, parse_line_thickness,
+    parse_list_schema, parse_math_size, parse_operator_size, parse_padded_length, token,
+    ArgumentRequirements, AttributeParse, Child, ElementType, MathmlElement, MathmlInfo,
+    ParseContext, SchemaAttributes, StringExtMathml,
 };
 
-use crate::{unicode_math::Family, Field, Length, MathExpression};
-pub use quick_xml::error::ResultPos;
-pub use quick_xml::{Element, Event, XmlReader};
+use crate::{
+    unicode_math::Family, Annotation, Field, Glyph, HorizontalAlign, Length, MathExpression,
+    MathItem, MathSpace, MultiScripts, PseudoUnit, ScriptPair, Semantics, Table, TableCell,
+    TableRow,
+};
+#[cfg(feature = "mathml_parser")]
 use std::io::BufRead;
 
+#[cfg(feature = "mathml_parser")]
 pub fn parse<R: BufRead>(file: R) -> Result<MathExpression> {
-    let mut parser = XmlReader::from_reader(file).trim_text(true);
+    parse_with_context(file, &mut ParseContext::default())
+}
+
+/// Parses MathML like [`parse`], but replaces elements that contain errors by `merror` elements
+/// that show the error message instead of failing. Only errors in the XML structure, like a
+/// missing end element, still fail the parse.
+///
+/// Returns the formula together with the errors that have been replaced.
+#[cfg(feature = "mathml_parser")]
+pub fn parse_with_recovery<R: BufRead>(file: R) -> Result<(MathExpression, Vec<ParsingError>)> {
+    let mut context = ParseContext::with_recovery();
+    let expr = parse_with_context(file, &mut context)?;
+    Ok((expr, context.take_errors()))
+}
+
+/// Parses MathML with the given context, which keeps the information that has been gathered about
+/// the elements. E.g. [`ParseContext::span`] finds the element that produced a part of the
+/// layout.
+///
+/// # Examples
+/// ```
+/// # use math_render::mathmlparser::{self, ParseContext};
+/// let xml = "<mi>x</mi><mo>+</mo><mn>1</mn>";
+/// let mut context = ParseContext::default();
+/// let expr = mathmlparser::parse_with_context(xml.as_bytes(), &mut context).unwrap();
+/// assert_eq!(context.span(expr.get_user_data()), Some(0..xml.len()));
+/// ```
+#[cfg(feature = "mathml_parser")]
+pub fn parse_with_context<R: BufRead>(
+    file: R,
+    context: &mut ParseContext,
+) -> Result<MathExpression> {
+    parse_source_with_context(QuickXmlSource::new(file), context)
+}
+
+/// Parses MathML from the events of an [`XmlSource`], e.g. from the elements of a document tree
+/// that another XML or HTML parser has built. The result is the same as that of [`parse`] for a
+/// document with the same elements, attributes and text.
+///
+/// # Examples
+/// ```
+/// # use math_render::mathmlparser::{self, XmlEvent};
+/// let start = |name: &str| XmlEvent::Start {
+///     name: name.to_owned(),
+///     attributes: Vec::new(),
+/// };
+/// let end = |name: &str| XmlEvent::End(name.to_owned());
+/// let events = vec![
+///     start("msup"),
+///     start("mi"),
+///     XmlEvent::Text("x".to_owned()),
+///     end("mi"),
+///     start("mn"),
+///     XmlEvent::Text("2".to_owned()),
+///     end("mn"),
+///     end("msup"),
+/// ];
+/// let expr = mathmlparser::parse_source(events.into_iter()).unwrap();
+/// # #[cfg(feature = "mathml_parser")]
+/// # {
+/// let xml = "<msup><mi>x</mi><mn>2</mn></msup>";
+/// let parsed = mathmlparser::parse(xml.as_bytes()).unwrap();
+/// assert_eq!(format!("{:?}", expr), format!("{:?}", parsed));
+/// # }
+/// ```
+pub fn parse_source<S: XmlSource>(source: S) -> Result<MathExpression> {
+    parse_source_with_context(source, &mut ParseContext::default())
+}
+
+/// Parses MathML from the events of an [`XmlSource`] with the given context, like
+/// [`parse_with_context`]. The spans of the elements are only known if the source knows them.
+pub fn parse_source_with_context<S: XmlSource>(
+    source: S,
+    context: &mut ParseContext,
+) -> Result<MathExpression> {
+    parse_namespaced_with_context(Namespaces::new(source, Vec::new()), context)
+}
+
+// Parses MathML from a source whose element names have MathML prefixes removed.
+pub(crate) fn parse_namespaced_with_context<S: XmlSource>(
+    mut source: Namespaces<S>,
+    context: &mut ParseContext,
+) -> Result<MathExpression> {
     let root_elem = MathmlElement {
         identifier: "ROOT_ELEMENT", // this identifier is arbitrary and should not be used elsewhere
         elem_type: ElementType::MathmlRoot,
     };
-    let mut context = ParseContext::default();
 
-    parse_element(&mut parser, root_elem, std::iter::empty(), &mut context)
+    match parse_element(&mut source, root_elem, &[], context) {
+        Ok(expr) => {
+            if let Some(span) = source.span() {
+                context.set_span(&expr, 0..span.end);
+            }
+            Ok(expr)
+        }
+        Err(mut error) => {
+            error.position = error.position.or_else(|| Some(source.span()?.end));
+            Err(error)
+        }
+    }
 }
 
-pub fn parse_element<'a, R: BufRead, A>(
-    parser: &mut XmlReader<R>,
+pub fn parse_element<S: XmlSource>(
+    source: &mut S,
     elem: MathmlElement,
-    attributes: A,
+    attributes: &[(String, String)],
     context: &mut ParseContext,
-) -> Result<MathExpression>
-where
-    A: Iterator<Item = ResultPos<(&'a [u8], &'a [u8])>>,
-{
-    let attrs = attributes.filter_map(|res| {
-        res.ok()
-            .and_then(|(a, b)| Some((std::str::from_utf8(a).ok()?, std::str::from_utf8(b).ok()?)))
-    });
-    let user_data = context.mathml_info.len() as u64;
+) -> Result<MathExpression> {
+    let attrs = str_attributes(attributes);
+    let user_data = context.new_user_data();
     match elem.elem_type {
         ElementType::TokenElement => {
             let mut token_style = token::TokenStyle::default();
             let mut op_attrs = operator::Attributes::default();
-            let mut space = None;
-            attrs
-                .filter(|attr| !parse_token_attribute(&mut token_style, elem.identifier, &attr))
-                .filter(|attr| {
-                    if elem.is("mo") {
-                        !parse_operator_attribute(&mut op_attrs, &attr)
-                    } else {
-                        true
-                    }
-                })
-                .filter(|attr| !parse_mspace_attribute(&mut space, elem.identifier, &attr))
-                .fold((), |_, _| {});
+            let mut space = if elem.is("mspace") {
+                Some(MathSpace::default())
+            } else {
+                None
+            };
+            let mut group_align = None;
+            for attr in attrs {
+                let result = parse_token_attribute(&mut token_style, &attr).and_then(|is_parsed| {
+                    Ok(is_parsed
+                        || (elem.is("mo") && parse_operator_attribute(&mut op_attrs, &attr)?)
+                        || parse_mspace_attribute(&mut space, elem.identifier, &attr)?
+                        || parse_align_group_attribute(&mut group_align, elem.identifier, &attr)?)
+                });
+                if result.is_err() {
+                    context.report(invalid_attribute(source, elem, attr))?;
+                }
+            }
 
-            let fields = parse_token_contents(parser, elem, token_style)?;
+            if token_style.math_variant.is_none() {
+                token_style.math_variant = context.math_variant;
+            }
+            // only the variant of an identifier depends on its content
+            if token_style.math_variant.is_none() && !elem.is("mi") {
+                token_style.math_variant = Some(Family::Normal);
+            }
+            let children = parse_token_contents(source, elem, token_style, context)?;
 
             let attributes = token::Attributes {
                 operator_attributes: op_attrs,
                 token_style,
-                horizontal_space: space,
+                space,
+                group_align,
             };
 
             Ok(token::build_token(
-                fields, elem, attributes, context, user_data,
+                children, elem, attributes, context, user_data,
             )?)
         }
         ElementType::LayoutSchema {
             args: ArgumentRequirements::ArgumentList,
         }
         | ElementType::MathmlRoot => {
-            let mut list = parse_element_list(parser, elem, context)?;
+            let attributes = parse_schema_attributes(source, elem, attrs, context)?;
+
+            // the mathvariant is inherited by all token elements inside of this element
+            let inherited_math_variant = context.math_variant;
+            if attributes.math_variant.is_some() {
+                context.math_variant = attributes.math_variant;
+            }
+            let mut list = parse_element_list(source, elem, context)?;
+            context.math_variant = inherited_math_variant;
+
             operator::process_operators(&mut list, context);
-            Ok(parse_list_schema(list, elem, user_data))
+            Ok(parse_list_schema(
+                list, elem, attributes, context, user_data,
+            ))
         }
         ElementType::LayoutSchema {
             args: ArgumentRequirements::RequiredArguments(_),
         } => {
-            let mut attributes = SchemaAttributes::default();
-            for attr in attrs {
-                parse_schema_attribute(&mut attributes, &attr);
+            let attributes = parse_schema_attributes(source, elem, attrs, context)?;
+
+            // a wrong number of arguments is reported at the start of the element
+            let arguments = parse_element_list(source, elem, context)?;
+            parse_fixed_schema(arguments.into_iter(), elem, attributes, context, user_data)
+        }
+        ElementType::LayoutSchema {
+            args: ArgumentRequirements::Special,
+        } => {
+            if elem.is("mtable") {
+                let mut table = Table::default();
+                for attr in attrs {
+                    if parse_table_attribute(&mut table, &attr).is_err() {
+                        context.report(invalid_attribute(source, elem, attr))?;
+                    }
+                }
+                table.rows = parse_table_rows(source, elem, context)?;
+                Ok(MathExpression::new(MathItem::Table(table), user_data))
+            } else if elem.is("mmultiscripts") {
+                let multiscripts = parse_multiscripts(source, elem, context)?;
+                let info = MathmlInfo {
+                    operator_attrs: context
+                        .operator_attrs(multiscripts.nucleus.as_ref())
+                        .cloned(),
+                    ..Default::default()
+                };
+                context.mathml_info.insert(user_data, info);
+                Ok(MathExpression::new(
+                    MathItem::MultiScripts(multiscripts),
+                    user_data,
+                ))
+            } else if elem.is("semantics") {
+                let semantics = parse_semantics(source, elem, context)?;
+                let info = MathmlInfo {
+                    operator_attrs: context.operator_attrs(semantics.content.as_ref()).cloned(),
+                    ..Default::default()
+                };
+                context.mathml_info.insert(user_data, info);
+                Ok(MathExpression::new(
+                    MathItem::Semantics(semantics),
+                    user_data,
+                ))
+            } else {
+                let parent = if elem.is("none") || elem.is("mprescripts") {
+                    "a mmultiscripts element"
+                } else if elem.is("annotation") || elem.is("annotation-xml") {
+                    "a semantics element"
+                } else {
+                    "a table"
+                };
+                // skip the element so that the parser can go on after it
+                skip_element(source)?;
+                Err(ParsingError::from_string(
+                    source,
+                    format!(
+                        "\"{}\" element is only allowed inside of {}.",
+                        elem.identifier, parent
+                    ),
+                ))
             }
+        }
+    }
+}
 
-            let arguments = parse_fixed_arguments(parser, elem, context)?;
-            Ok(parse_fixed_schema(
-                arguments.into_iter(),
-                elem,
-                attributes,
-                context,
-                user_data,
-            ))
+fn parse_schema_attributes<'a, S: XmlSource>(
+    source: &S,
+    elem: MathmlElement,
+    attrs: impl Iterator<Item = (&'a str, &'a str)>,
+    context: &mut ParseContext,
+) -> Result<SchemaAttributes> {
+    let mut attributes = SchemaAttributes::default();
+    for attr in attrs {
+        if parse_schema_attribute(&mut attributes, &attr).is_err() {
+            context.report(invalid_attribute(source, elem, attr))?;
         }
-        _ => unimplemented!(),
     }
+    Ok(attributes)
+}
+
+fn invalid_attribute<S: XmlSource>(
+    source: &S,
+    elem: MathmlElement,
+    (attribute, value): (&str, &str),
+) -> ParsingError {
+    ParsingError::of_type(
+        source,
+        ErrorType::InvalidAttribute {
+            element: elem.identifier.to_owned(),
+            attribute: attribute.to_owned(),
+            value: value.to_owned(),
+        },
+    )
 }
 
-fn parse_sub_element<R: BufRead>(
-    parser: &mut XmlReader<R>,
-    elem: &Element,
+fn str_attributes(attributes: &[(String, String)]) -> impl Iterator<Item = (&str, &str)> {
+    attributes
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+}
+
+// An element whose start has been read.
+struct StartElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    // the position of the start tag in the input, if it is known
+    position: Option<usize>,
+}
+
+// Reads the events up to the end of the element whose start has been read last.
+fn skip_element<S: XmlSource>(source: &mut S) -> Result<()> {
+    let mut depth = 0;
+    loop {
+        match source.next_event() {
+            Some(Ok(XmlEvent::Start { .. })) => depth += 1,
+            Some(Ok(XmlEvent::End(_))) if depth == 0 => return Ok(()),
+            Some(Ok(XmlEvent::End(_))) => depth -= 1,
+            Some(Ok(XmlEvent::Text(_))) => {}
+            Some(Err(error)) => return Err(error),
+            None => {
+                return Err(ParsingError::of_type(
+                    source,
+                    ErrorType::UnexpectedEndOfInput,
+                ))
+            }
+        }
+    }
+}
+
+fn parse_sub_element<S: XmlSource>(
+    source: &mut S,
+    elem: &StartElement,
     context: &mut ParseContext,
 ) -> Result<MathExpression> {
-    let sub_elem = match_math_element(elem.name());
-    match sub_elem {
-        Some(sub_elem) => parse_element(parser, sub_elem, elem.attributes(), context),
+    let sub_elem = match_math_element(elem.name.as_bytes());
+    // the elements that fail are read completely, so that the parser can replace them when it is
+    // recovering from errors
+    let result = match sub_elem {
+        Some(sub_elem) => parse_element(source, sub_elem, &elem.attributes, context),
         None => {
-            let name = String::from_utf8_lossy(elem.name()).into_owned();
-            let result: Result<_> = parser.read_to_end(elem.name()).map_err(|err| err.into());
-            result.and(Err(ParsingError::of_type(
-                parser,
-                ErrorType::UnknownElement(name),
-            )))
+            skip_element(source)?;
+            Err(ParsingError::of_type(
+                source,
+                ErrorType::UnknownElement(elem.name.clone()),
+            ))
         }
+    };
+
+    let span = match (elem.position, source.span()) {
+        (Some(start), Some(end_span)) => Some(start..end_span.end),
+        _ => None,
+    };
+    let expr = result.or_else(|mut error| {
+        error.position = error.position.or(elem.position);
+        context.recover(error)
+    })?;
+    if let Some(span) = span {
+        context.set_span(&expr, span);
     }
+    Ok(expr)
 }
 
-fn parse_element_list<R: BufRead>(
-    parser: &mut XmlReader<R>,
+fn parse_element_list<S: XmlSource>(
+    source: &mut S,
     elem: MathmlElement,
     context: &mut ParseContext,
 ) -> Result<Vec<MathExpression>> {
+    parse_children(source, elem, context, parse_sub_element)
+}
+
+// Calls `parse_child` for every child element of `elem` until the end element of `elem` is
+// reached.
+fn parse_children<S: XmlSource, T, F>(
+    source: &mut S,
+    elem: MathmlElement,
+    context: &mut ParseContext,
+    mut parse_child: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(&mut S, &StartElement, &mut ParseContext) -> Result<T>,
+{
     let mut list = Vec::new();
     loop {
-        let next_event = parser.next();
+        let next_event = source.next_event();
         match next_event {
-            Some(Ok(Event::Start(ref start_elem))) => {
-                list.push(parse_sub_element(parser, start_elem, context)?)
+            Some(Ok(XmlEvent::Start { name, attributes })) => {
+                let start_elem = StartElement {
+                    name,
+                    attributes,
+                    position: source.span().map(|span| span.start),
+                };
+                list.push(parse_child(source, &start_elem, context)?)
             }
-            Some(Ok(Event::End(ref end_elem))) => {
+            Some(Ok(XmlEvent::End(name))) => {
                 if elem.elem_type == ElementType::MathmlRoot {
-                    let name = std::str::from_utf8(end_elem.name())?.to_string();
                     return Err(ParsingError::of_type(
-                        parser,
+                        source,
                         ErrorType::WrongEndElement(name),
                     ));
                 }
-                if end_elem.name() == elem.identifier.as_bytes() {
+                if name == elem.identifier {
                     break;
                 } else {
-                    let name = std::str::from_utf8(end_elem.name())?.to_string();
                     return Err(ParsingError::of_type(
-                        parser,
+                        source,
                         ErrorType::WrongEndElement(name),
                     ));
                 }
@@ -148,7 +407,7 @@ fn parse_element_list<R: BufRead>(
                     break;
                 } else {
                     return Err(ParsingError::of_type(
-                        parser,
+                        source,
                         ErrorType::UnexpectedEndOfInput,
                     ));
                 }
@@ -159,165 +418,626 @@ fn parse_element_list<R: BufRead>(
     Ok(list)
 }
 
-fn parse_fixed_arguments<'a, R: BufRead>(
-    parser: &mut XmlReader<R>,
+// The children of a `mmultiscripts` element.
+enum ScriptsChild {
+    Script(Option<MathExpression>),
+    PreScripts,
+}
+
+fn parse_multiscripts<S: XmlSource>(
+    source: &mut S,
     elem: MathmlElement,
     context: &mut ParseContext,
-) -> Result<Vec<MathExpression>> {
-    if let ElementType::LayoutSchema {
-        args: ArgumentRequirements::RequiredArguments(num_args),
-    } = elem.elem_type
-    {
-        let args = parse_element_list(parser, elem, context)?;
-        if args.len() == num_args as usize {
-            Ok(args)
-        } else {
-            Err(ParsingError::from_string(
-                parser,
-                format!(
-                    "\"{:?}\" element requires {:?} arguments. \
-                     Found {:?} arguments.",
-                    elem.identifier,
-                    num_args,
-                    args.len()
-                ),
+) -> Result<MultiScripts> {
+    let children =
+        parse_children(
+            source,
+            elem,
+            context,
+            |source, start_elem, context| match start_elem.name.as_bytes() {
+                b"none" => {
+                    skip_element(source)?;
+                    Ok(ScriptsChild::Script(None))
+                }
+                b"mprescripts" => {
+                    skip_element(source)?;
+                    Ok(ScriptsChild::PreScripts)
+                }
+                _ => Ok(ScriptsChild::Script(Some(parse_sub_element(
+                    source, start_elem, context,
+                )?))),
+            },
+        )?;
+
+    let mut children = children.into_iter();
+    let mut multiscripts = match children.next() {
+        Some(ScriptsChild::Script(nucleus)) => MultiScripts {
+            nucleus,
+            ..Default::default()
+        },
+        _ => {
+            return Err(ParsingError::from_string(
+                source,
+                "The first child of a mmultiscripts element has to be its base.",
             ))
         }
-    } else {
-        unreachable!();
+    };
+
+    let mut is_pre_script = false;
+    let mut subscript = None;
+    for child in children {
+        match child {
+            ScriptsChild::PreScripts if !is_pre_script && subscript.is_none() => {
+                is_pre_script = true;
+            }
+            ScriptsChild::PreScripts => {
+                return Err(ParsingError::from_string(
+                    source,
+                    "Unexpected mprescripts element.",
+                ))
+            }
+            ScriptsChild::Script(script) => {
+                let script = script.map(|script| {
+                    operator::guess_if_operator_with_form(script, operator::Form::Postfix, context)
+                });
+                match subscript.take() {
+                    None => subscript = Some(script),
+                    Some(subscript) => {
+                        let pair = ScriptPair {
+                            subscript,
+                            superscript: script,
+                        };
+                        if is_pre_script {
+                            multiscripts.pre_scripts.push(pair);
+                        } else {
+                            multiscripts.post_scripts.push(pair);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if subscript.is_some() {
+        return Err(ParsingError::from_string(
+            source,
+            "The scripts of a mmultiscripts element have to come in pairs.",
+        ));
     }
+    Ok(multiscripts)
+}
+
+// The first presentation child of a `semantics` element is its content. All other children are
+// annotations, except for an `annotation-xml` element with presentation MathML, which stands in
+// for a missing content. Unknown children, e.g. content MathML, are errors. When recovering they
+// are kept as annotations.
+fn parse_semantics<S: XmlSource>(
+    source: &mut S,
+    elem: MathmlElement,
+    context: &mut ParseContext,
+) -> Result<Semantics> {
+    let mut semantics = Semantics::default();
+    parse_children(source, elem, context, |source, start_elem, context| {
+        let encoding = start_elem
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute == "encoding")
+            .map(|(_, encoding)| encoding.clone());
+        match match_math_element(start_elem.name.as_bytes()) {
+            Some(child) if child.is("annotation") => {
+                semantics.annotations.push(Annotation {
+                    encoding,
+                    content: read_text(source)?,
+                    is_xml: false,
+                });
+            }
+            Some(child) if child.is("annotation-xml") => {
+                let is_presentation = matches!(
+                    encoding.as_deref(),
+                    Some("MathML-Presentation") | Some("application/mathml-presentation+xml")
+                );
+                if is_presentation && semantics.content.is_none() {
+                    let user_data = context.new_user_data();
+                    let mut list = parse_element_list(source, child, context)?;
+                    operator::process_operators(&mut list, context);
+                    let attributes = SchemaAttributes::default();
+                    let content = parse_list_schema(list, child, attributes, context, user_data);
+                    semantics.content = Some(content);
+                } else {
+                    semantics.annotations.push(Annotation {
+                        encoding,
+                        content: read_xml(source)?,
+                        is_xml: true,
+                    });
+                }
+            }
+            Some(_) if semantics.content.is_none() => {
+                semantics.content = Some(parse_sub_element(source, start_elem, context)?);
+            }
+            Some(_) => {
+                let name = start_elem.name.clone();
+                context.report(ParsingError::of_type(
+                    source,
+                    ErrorType::UnsupportedElement(name),
+                ))?;
+                skip_element(source)?;
+            }
+            None => {
+                let name = start_elem.name.clone();
+                context.report(ParsingError::of_type(
+                    source,
+                    ErrorType::UnknownElement(name),
+                ))?;
+                let mut content = String::new();
+                write_start_tag(&mut content, &start_elem.name, &start_elem.attributes);
+                content.push_str(&read_xml(source)?);
+                content.push_str(&format!("</{}>", start_elem.name));
+                semantics.annotations.push(Annotation {
+                    encoding: Some("MathML-Content".to_owned()),
+                    content,
+                    is_xml: true,
+                });
+            }
+        }
+        Ok(())
+    })?;
+    Ok(semantics)
+}
+
+// Reads the text up to the end of the element whose start has been read last. Elements inside of
+// it are left out.
+fn read_text<S: XmlSource>(source: &mut S) -> Result<String> {
+    let mut text = String::new();
+    loop {
+        match source.next_event() {
+            Some(Ok(XmlEvent::Text(content))) => text.push_str(&content),
+            Some(Ok(XmlEvent::Start { .. })) => skip_element(source)?,
+            Some(Ok(XmlEvent::End(_))) => return Ok(text),
+            Some(Err(error)) => return Err(error),
+            None => {
+                return Err(ParsingError::of_type(
+                    source,
+                    ErrorType::UnexpectedEndOfInput,
+                ))
+            }
+        }
+    }
+}
+
+// Reads the content of the element whose start has been read last and writes it as XML.
+fn read_xml<S: XmlSource>(source: &mut S) -> Result<String> {
+    let mut xml = String::new();
+    let mut depth = 0;
+    loop {
+        match source.next_event() {
+            Some(Ok(XmlEvent::Start { name, attributes })) => {
+                write_start_tag(&mut xml, &name, &attributes);
+                depth += 1;
+            }
+            Some(Ok(XmlEvent::End(_))) if depth == 0 => return Ok(xml),
+            Some(Ok(XmlEvent::End(name))) => {
+                xml.push_str(&format!("</{}>", name));
+                depth -= 1;
+            }
+            Some(Ok(XmlEvent::Text(text))) => push_escaped(&mut xml, &text),
+            Some(Err(error)) => return Err(error),
+            None => {
+                return Err(ParsingError::of_type(
+                    source,
+                    ErrorType::UnexpectedEndOfInput,
+                ))
+            }
+        }
+    }
+}
+
+fn write_start_tag(xml: &mut String, name: &str, attributes: &[(String, String)]) {
+    xml.push('<');
+    xml.push_str(name);
+    for (attribute, value) in attributes {
+        xml.push_str(&format!(" {}=\"", attribute));
+        push_escaped(xml, value);
+        xml.push('"');
+    }
+    xml.push('>');
+}
+
+fn push_escaped(xml: &mut String, text: &str) {
+    for chr in text.chars() {
+        match chr {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            chr => xml.push(chr),
+        }
+    }
+}
+
+// Elements other than `mtr` inside of a table are treated as if they were wrapped in their own
+// row. Likewise elements other than `mtd` inside of a row are treated as a single cell.
+fn parse_table_rows<S: XmlSource>(
+    source: &mut S,
+    elem: MathmlElement,
+    context: &mut ParseContext,
+) -> Result<Vec<TableRow>> {
+    parse_children(
+        source,
+        elem,
+        context,
+        |source, start_elem, context| match match_math_element(start_elem.name.as_bytes()) {
+            Some(row_elem) if row_elem.is("mtr") || row_elem.is("mlabeledtr") => {
+                parse_table_row(source, row_elem, &start_elem.attributes, context)
+            }
+            _ => Ok(TableRow {
+                cells: vec![parse_implicit_table_cell(source, start_elem, context)?],
+                ..Default::default()
+            }),
+        },
+    )
+}
+
+fn parse_table_row<S: XmlSource>(
+    source: &mut S,
+    elem: MathmlElement,
+    attributes: &[(String, String)],
+    context: &mut ParseContext,
+) -> Result<TableRow> {
+    let mut row = TableRow::default();
+    for attr in str_attributes(attributes) {
+        if parse_table_row_attribute(&mut row, &attr).is_err() {
+            context.report(invalid_attribute(source, elem, attr))?;
+        }
+    }
+    row.cells =
+        parse_children(
+            source,
+            elem,
+            context,
+            |source, start_elem, context| match match_math_element(start_elem.name.as_bytes()) {
+                Some(cell_elem) if cell_elem.is("mtd") => {
+                    parse_table_cell(source, cell_elem, &start_elem.attributes, context)
+                }
+                _ => parse_implicit_table_cell(source, start_elem, context),
+            },
+        )?;
+    // the first cell of a labeled row is its label
+    if elem.is("mlabeledtr") && !row.cells.is_empty() {
+        row.label = row.cells.remove(0).content;
+    }
+    Ok(row)
+}
+
+fn parse_table_cell<S: XmlSource>(
+    source: &mut S,
+    elem: MathmlElement,
+    attributes: &[(String, String)],
+    context: &mut ParseContext,
+) -> Result<TableCell> {
+    let mut cell = TableCell::default();
+    for attr in str_attributes(attributes) {
+        if parse_table_cell_attribute(&mut cell, &attr).is_err() {
+            context.report(invalid_attribute(source, elem, attr))?;
+        }
+    }
+    let user_data = context.new_user_data();
+    let mut list = parse_element_list(source, elem, context)?;
+    operator::process_operators(&mut list, context);
+    cell.content = Some(parse_list_schema(
+        list,
+        elem,
+        SchemaAttributes::default(),
+        context,
+        user_data,
+    ));
+    Ok(cell)
+}
+
+fn parse_implicit_table_cell<S: XmlSource>(
+    source: &mut S,
+    elem: &StartElement,
+    context: &mut ParseContext,
+) -> Result<TableCell> {
+    Ok(TableCell {
+        content: Some(parse_sub_element(source, elem, context)?),
+        ..Default::default()
+    })
 }
 
 // invoked after a token expression
 // the cursor is moved behind the end element of the token expression
 // the result (if ok) is guaranteed to not be empty
-pub fn parse_token_contents<R: BufRead>(
-    parser: &mut XmlReader<R>,
+pub fn parse_token_contents<S: XmlSource>(
+    source: &mut S,
     elem: MathmlElement,
     token_style: token::TokenStyle,
-) -> Result<impl ExactSizeIterator<Item = (Field, u64)>> {
-    let mut fields: Vec<(Field, u64)> = Vec::new();
+    context: &mut ParseContext,
+) -> Result<impl ExactSizeIterator<Item = Child>> {
+    let mut children = Vec::new();
 
-    while let Some(event) = parser.next() {
+    while let Some(event) = source.next_event() {
         match event? {
-            Event::Text(text) => {
-                let text = std::str::from_utf8(text.content())?;
-
-                let text = text.unescape().map(|text| {
-                    text.adapt_to_family(token_style.math_variant)
-                        .replace_anomalous_characters(elem)
-                })?;
+            XmlEvent::Text(text) => {
+                // whitespace at the start and the end of a token is not part of its content, but
+                // characters like a no-break space that only Unicode counts as whitespace are
+                let text = text.trim_matches(|c| matches!(c, ' ' | '\t' | '\r' | '\n'));
+                if text.is_empty() {
+                    continue;
+                }
+                let text = text
+                    .adapt_to_family(token_style.math_variant)
+                    .replace_anomalous_characters(elem);
 
-                fields.push((Field::Unicode(text), 0));
+                let field = (Field::Unicode(text), context.new_user_data());
+                children.push(Child::Field(field));
             }
-            Event::Start(elem) => match elem.name() {
-                b"mglyph" | b"malignmark" => Err(ParsingError::from_string(
-                    parser,
-                    format!(
-                        "{:?} element is currently not \
-                         implemented.",
-                        elem.name()
-                    ),
-                ))?,
-                _ => Err(ParsingError::from_string(parser, "Unexpected new element."))?,
-            },
-            Event::End(ref end_elem) => {
-                if elem.identifier.as_bytes() == end_elem.name() {
+            XmlEvent::Start {
+                ref name,
+                ref attributes,
+            } if name == "mglyph" => {
+                match parse_glyph(str_attributes(attributes)) {
+                    Ok(glyph) => {
+                        let field = (Field::Glyph(glyph), context.new_user_data());
+                        children.push(Child::Field(field));
+                    }
+                    // a glyph that cannot be found is left out
+                    Err(err) => context.report(ParsingError::from_string(source, err))?,
+                }
+                skip_element(source)?;
+            }
+            XmlEvent::Start { ref name, .. } if name == "malignmark" => {
+                skip_element(source)?;
+                let mark = MathExpression::new(MathItem::AlignMark, context.new_user_data());
+                children.push(Child::Expression(mark));
+            }
+            XmlEvent::Start { name, .. } => {
+                let error = ParsingError::of_type(source, ErrorType::UnsupportedElement(name));
+                context.report(error)?;
+                skip_element(source)?;
+            }
+            XmlEvent::End(name) => {
+                if elem.identifier == name {
                     break;
                 }
             }
+        }
+    }
+    Ok(children.into_iter())
+}
+
+// Builds the glyph referenced by the attributes of a `mglyph` element.
+fn parse_glyph<'a>(
+    attributes: impl Iterator<Item = (&'a str, &'a str)>,
+) -> std::result::Result<Glyph, &'static str> {
+    let mut glyph_code = None;
+    for attr in attributes {
+        match attr {
+            ("index", index) => {
+                glyph_code = Some(index.trim().parse().map_err(|_| "invalid glyph index")?)
+            }
+            // glyphs are always taken from the font that is used for the layout
+            ("fontfamily", _) => {}
             _ => {}
         }
     }
-    Ok(fields.into_iter())
+    Ok(Glyph {
+        glyph_code: glyph_code.ok_or("mglyph element without an index attribute")?,
+        ..Default::default()
+    })
 }
 
 #[allow(match_same_arms)]
 fn parse_token_attribute<'a>(
     style: &mut token::TokenStyle,
-    element_identifier: &str,
     new_attribute: &(&'a str, &'a str),
-) -> bool {
+) -> std::result::Result<bool, ()> {
     match *new_attribute {
-        ("mathvariant", variant) => style.math_variant = variant.parse_xml().ok(),
-        ("dir", dir) => style.direction = dir.parse_xml().unwrap(),
-        _ => return false,
+        ("mathvariant", variant) => style.math_variant = Some(variant.parse_xml()?),
+        ("mathsize", size) => style.math_size = Some(parse_math_size(size).map_err(drop)?),
+        ("mathcolor", color) => style.math_color = Some(color.parse_xml().map_err(drop)?),
+        ("mathbackground", color) => style.math_background = Some(color.parse_xml().map_err(drop)?),
+        ("dir", dir) => style.direction = Some(dir.parse_xml()?),
+        _ => return Ok(false),
     }
-    match (element_identifier, style.math_variant) {
-        ("mi", None) => {}
-        (_, None) => style.math_variant = Some(Family::Normal),
-        _ => {}
-    }
-    true
+    Ok(true)
 }
 
-fn parse_operator_attribute(op_attrs: &mut operator::Attributes, new_attr: &(&str, &str)) -> bool {
-    match *new_attr {
-        ("form", form_str) => op_attrs.form = form_str.parse_xml().ok(),
+fn parse_operator_attribute(
+    op_attrs: &mut operator::Attributes,
+    new_attr: &(&str, &str),
+) -> std::result::Result<bool, ()> {
+    let flag = match *new_attr {
+        ("form", form_str) => {
+            op_attrs.form = Some(form_str.parse_xml().map_err(drop)?);
+            return Ok(true);
+        }
         ("lspace", lspace) => {
-            op_attrs.lspace = lspace.parse_xml().ok();
+            op_attrs.lspace = Some(lspace.parse_xml().map_err(drop)?);
+            return Ok(true);
         }
         ("rspace", rspace) => {
-            op_attrs.rspace = rspace.parse_xml().ok();
+            op_attrs.rspace = Some(rspace.parse_xml().map_err(drop)?);
+            return Ok(true);
         }
-        ("fence", is_fence) => {
-            if let Ok(is_fence) = is_fence.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::FENCE, is_fence);
-            }
+        ("minsize", min_size) => {
+            op_attrs.min_size = Some(parse_operator_size(min_size).map_err(drop)?);
+            return Ok(true);
         }
-        ("symmetric", is_symmetric) => {
-            if let Ok(is_symmetric) = is_symmetric.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::SYMMETRIC, is_symmetric);
-            }
+        ("maxsize", "infinity") => {
+            op_attrs.max_size = None;
+            return Ok(true);
         }
-        ("stretchy", is_stretchy) => {
-            if let Ok(is_stretchy) = is_stretchy.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::STRETCHY, is_stretchy);
-            }
+        ("maxsize", max_size) => {
+            op_attrs.max_size = Some(parse_operator_size(max_size).map_err(drop)?);
+            return Ok(true);
         }
-        ("largeop", is_largeop) => {
-            if let Ok(is_largeop) = is_largeop.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::LARGEOP, is_largeop);
-            }
+        ("linebreak", line_break) => {
+            op_attrs.line_break = line_break.parse_xml().map_err(drop)?;
+            return Ok(true);
         }
-        ("movablelimits", has_movable_limits) => {
-            if let Ok(has_movable_limits) = has_movable_limits.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::MOVABLE_LIMITS, has_movable_limits);
-            }
+        ("linebreakstyle", style) => {
+            op_attrs.line_break_style = style.parse_xml().map_err(drop)?;
+            return Ok(true);
         }
-        ("accent", is_accent) => {
-            if let Ok(is_accent) = is_accent.parse_xml() {
-                op_attrs.set_user_override(operator::Flags::ACCENT, is_accent);
-            }
+        ("indentalign", align) => {
+            op_attrs.indent_align = Some(align.parse_xml().map_err(drop)?);
+            return Ok(true);
         }
-        _ => return false,
-    }
-    true
+        ("fence", _) => operator::Flags::FENCE,
+        ("symmetric", _) => operator::Flags::SYMMETRIC,
+        ("stretchy", _) => operator::Flags::STRETCHY,
+        ("largeop", _) => operator::Flags::LARGEOP,
+        ("movablelimits", _) => operator::Flags::MOVABLE_LIMITS,
+        ("accent", _) => operator::Flags::ACCENT,
+        _ => return Ok(false),
+    };
+    let value = new_attr.1.parse_xml().map_err(drop)?;
+    op_attrs.set_user_override(flag, value);
+    Ok(true)
 }
 
 fn parse_mspace_attribute(
-    horiz_space: &mut Option<Length>,
+    space: &mut Option<MathSpace>,
     identifier: &str,
     new_attr: &(&str, &str),
-) -> bool {
-    if identifier != "mspace" {
-        return false;
+) -> std::result::Result<bool, ()> {
+    let space = match space {
+        Some(ref mut space) if identifier == "mspace" => space,
+        _ => return Ok(false),
+    };
+    let (dimension, value) = match *new_attr {
+        ("width", value) => (&mut space.width, value),
+        ("height", value) => (&mut space.ascent, value),
+        ("depth", value) => (&mut space.descent, value),
+        _ => return Ok(false),
+    };
+    *dimension = value.parse_xml().map_err(drop)?;
+    Ok(true)
+}
+
+fn parse_align_group_attribute(
+    group_align: &mut Option<HorizontalAlign>,
+    identifier: &str,
+    new_attr: &(&str, &str),
+) -> std::result::Result<bool, ()> {
+    match *new_attr {
+        ("groupalign", align) if identifier == "maligngroup" => {
+            *group_align = Some(align.parse_xml().map_err(drop)?)
+        }
+        _ => return Ok(false),
     }
+    Ok(true)
+}
+
+// The attribute parsers fail if the value of a known attribute is invalid. Unknown attributes
+// are ignored.
+fn parse_schema_attribute(
+    attributes: &mut SchemaAttributes,
+    new_attr: &(&str, &str),
+) -> std::result::Result<(), ()> {
     match *new_attr {
+        ("accent", is_accent) => attributes.accent = is_accent.parse_xml().map_err(drop)?,
+        ("accentunder", is_accent) => {
+            attributes.accentunder = is_accent.parse_xml().map_err(drop)?
+        }
+        ("notation", notation) => {
+            // unknown notations are ignored, so that the known ones are still drawn
+            let notation = notation
+                .split_whitespace()
+                .filter_map(|notation| notation.parse_xml().ok())
+                .collect();
+            attributes.notation = Some(notation)
+        }
+        ("display", display) => attributes.display = Some(display.parse_xml().map_err(drop)?),
+        ("displaystyle", display_style) => {
+            attributes.display_style = Some(display_style.parse_xml().map_err(drop)?)
+        }
+        ("scriptlevel", script_level) => {
+            attributes.script_level = Some(script_level.parse_xml().map_err(drop)?)
+        }
+        ("mathvariant", variant) => attributes.math_variant = Some(variant.parse_xml()?),
+        ("mathsize", size) => attributes.math_size = Some(parse_math_size(size).map_err(drop)?),
+        ("mathcolor", color) => attributes.math_color = Some(color.parse_xml().map_err(drop)?),
+        ("mathbackground", color) => {
+            attributes.math_background = Some(color.parse_xml().map_err(drop)?)
+        }
+        ("dir", dir) => attributes.direction = Some(dir.parse_xml()?),
         ("width", width) => {
-            if let Ok(width) = width.parse_xml() {
-                *horiz_space = Some(width);
+            attributes.width = Some(parse_padded_length(width, PseudoUnit::Width).map_err(drop)?)
+        }
+        ("height", height) => {
+            attributes.height = Some(parse_padded_length(height, PseudoUnit::Height).map_err(drop)?)
+        }
+        ("depth", depth) => {
+            attributes.depth = Some(parse_padded_length(depth, PseudoUnit::Depth).map_err(drop)?)
+        }
+        ("lspace", lspace) => {
+            attributes.lspace = Some(parse_padded_length(lspace, PseudoUnit::Width).map_err(drop)?)
+        }
+        ("voffset", voffset) => {
+            attributes.voffset =
+                Some(parse_padded_length(voffset, PseudoUnit::Height).map_err(drop)?)
+        }
+        ("linethickness", thickness) => {
+            attributes.line_thickness = Some(parse_line_thickness(thickness).map_err(drop)?)
+        }
+        ("numalign", align) => attributes.numerator_align = Some(align.parse_xml().map_err(drop)?),
+        ("denomalign", align) => {
+            attributes.denominator_align = Some(align.parse_xml().map_err(drop)?)
+        }
+        ("bevelled", is_bevelled) => attributes.bevelled = is_bevelled.parse_xml().map_err(drop)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_table_attribute(
+    table: &mut Table,
+    new_attr: &(&str, &str),
+) -> std::result::Result<(), ()> {
+    match *new_attr {
+        ("rowalign", align) => table.row_align = align.parse_xml().map_err(drop)?,
+        ("columnalign", align) => table.column_align = align.parse_xml().map_err(drop)?,
+        ("rowspacing", spacing) => table.row_spacing = spacing.parse_xml().map_err(drop)?,
+        ("columnspacing", spacing) => table.column_spacing = spacing.parse_xml().map_err(drop)?,
+        ("rowlines", lines) => table.row_lines = lines.parse_xml().map_err(drop)?,
+        ("columnlines", lines) => table.column_lines = lines.parse_xml().map_err(drop)?,
+        ("frame", frame) => table.frame = frame.parse_xml().map_err(drop)?,
+        ("groupalign", align) => table.group_align = parse_group_align_list(align).map_err(drop)?,
+        ("framespacing", spacing) => {
+            table.frame_spacing = match spacing.parse_xml::<Vec<Length>>().as_ref().map(|x| &x[..])
+            {
+                Ok(&[horizontal, vertical]) => (horizontal, vertical),
+                Ok(&[spacing]) => (spacing, spacing),
+                _ => return Err(()),
             }
-            true
         }
-        _ => false,
+        _ => {}
     }
+    Ok(())
 }
 
-fn parse_schema_attribute(attributes: &mut SchemaAttributes, new_attr: &(&str, &str)) {
+fn parse_table_row_attribute(
+    row: &mut TableRow,
+    new_attr: &(&str, &str),
+) -> std::result::Result<(), ()> {
+    match *new_attr {
+        ("rowalign", align) => row.row_align = Some(align.parse_xml().map_err(drop)?),
+        ("columnalign", align) => row.column_align = align.parse_xml().map_err(drop)?,
+        ("groupalign", align) => row.group_align = parse_group_align_list(align).map_err(drop)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_table_cell_attribute(
+    cell: &mut TableCell,
+    new_attr: &(&str, &str),
+) -> std::result::Result<(), ()> {
     match *new_attr {
-        ("accent", is_accent) => attributes.accent = is_accent.parse().unwrap(),
-        ("accentunder", is_accent) => attributes.accentunder = is_accent.parse().unwrap(),
+        ("rowalign", align) => cell.row_align = Some(align.parse_xml().map_err(drop)?),
+        ("columnalign", align) => cell.column_align = Some(align.parse_xml().map_err(drop)?),
+        ("groupalign", align) => cell.group_align = align.parse_xml().map_err(drop)?,
         _ => {}
     }
+    Ok(())
 }
@@ -0,0 +1,991 @@
+//! A writer that turns a math expression back into presentation MathML.
+//!
+//! The output is meant to be read by the MathML parser of this crate as well as by browsers. It is
+//! kept as short as possible: attributes are only written where the parser would not arrive at
+//! the same value by itself. This is most notable for operators, whose spacing and stretchiness
+//! usually follow from the operator dictionary and their position inside of a row.
+//!
+//! # Examples
+//! ```
+//! # use math_render::{mathmlwriter, Field, MathExpression, MathItem};
+//! let expr = MathExpression::new(MathItem::Field(Field::Unicode("x".into())), 0);
+//! assert_eq!(mathmlwriter::to_string(&expr), "<math><mi mathvariant=\"normal\">x</mi></math>");
+//! ```
+
+use std::fmt::{self, Write};
+
+use crate::mathmlparser::operator_dict::find_entry;
+use crate::mathmlparser::{Flags, Form};
+use crate::types::{
+    Atom, Enclosure, Field, GeneralizedFraction, HorizontalAlign, Length, LengthUnit, LineBreak,
+    LineBreakStyle, LineStyle, MathExpression, MathItem, MathSpace, MathStyle, MultiScripts,
+    Notation, Operator, OverUnder, Padded, PaddedLength, PaddedValue, PseudoUnit, Root,
+    ScriptLevel, ScriptPair, Semantics, Styled, Table, TableCell, TableRow, TextDirection,
+    VerticalAlign,
+};
+use crate::unicode_math::{convert_character_to_family, Family};
+
+/// Writes `expr` as a `math` element to `out`.
+///
+/// Expressions of type `MathItem::Other` have no MathML representation and are written as empty
+/// `mrow` elements.
+pub fn write<W: Write>(expr: &MathExpression, out: &mut W) -> fmt::Result {
+    let mut writer = Writer { out };
+    writer.start("math", &[])?;
+    writer.row_contents(Some(expr))?;
+    writer.end("math")
+}
+
+/// Returns the MathML representation of `expr`.
+pub fn to_string(expr: &MathExpression) -> String {
+    let mut string = String::new();
+    write(expr, &mut string).expect("writing to a string cannot fail");
+    string
+}
+
+type Attributes = Vec<(&'static str, String)>;
+
+struct Writer<'a, W> {
+    out: &'a mut W,
+}
+
+impl<'a, W: Write> Writer<'a, W> {
+    /// Writes the children of an element that the parser treats like a `mrow`.
+    ///
+    /// The parser only decides about the form of an operator once the row it appears in is
+    /// complete: the first operator is a prefix, the last one a postfix and all others are infix
+    /// operators. Whitespace and alignment elements do not count.
+    fn row_contents(&mut self, content: Option<&MathExpression>) -> fmt::Result {
+        let list = match content.map(|content| &*content.item) {
+            Some(MathItem::List(list)) if list.len() != 1 => list,
+            _ => return self.expr(content, Some(Form::Infix), None),
+        };
+        let len = list.iter().filter(|expr| !expr.is_space_like()).count();
+        let mut index = 0;
+        for expr in list {
+            if expr.is_space_like() {
+                self.expr(Some(expr), None, None)?;
+                continue;
+            }
+            let form = match index {
+                0 if len > 1 => Form::Prefix,
+                _ if index == len - 1 && len > 1 => Form::Postfix,
+                _ => Form::Infix,
+            };
+            self.expr(Some(expr), Some(form), None)?;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes a single expression.
+    ///
+    /// `form` is the form that the parser assumes for an operator at this position. It is `None`
+    /// where the parser keeps `mo` elements as plain text. `movable_limits` is set if the
+    /// `movablelimits` attribute of the core of an embellished operator has to be written.
+    fn expr(
+        &mut self,
+        expr: Option<&MathExpression>,
+        form: Option<Form>,
+        movable_limits: Option<bool>,
+    ) -> fmt::Result {
+        let expr = match expr {
+            Some(expr) => expr,
+            None => return self.empty("mrow", &[]),
+        };
+        if form.is_none() && core_operator(expr).is_some() {
+            // a row with a single element turns an embellished operator into an infix operator
+            self.start("mrow", &[])?;
+            self.expr(Some(expr), Some(Form::Infix), movable_limits)?;
+            return self.end("mrow");
+        }
+        match *expr.item {
+            MathItem::Field(ref field) => self.token(field, None),
+            MathItem::Space(space) => self.space(space),
+            MathItem::Atom(ref atom) => self.atom(atom, form, movable_limits),
+            MathItem::MultiScripts(ref multiscripts) => {
+                self.multiscripts(multiscripts, form, movable_limits)
+            }
+            MathItem::OverUnder(ref over_under) => {
+                self.over_under(over_under, form, movable_limits)
+            }
+            MathItem::GeneralizedFraction(ref frac) => self.fraction(frac, form, movable_limits),
+            MathItem::Root(ref root) => self.root(root),
+            MathItem::Operator(ref operator) => {
+                self.operator(operator, form.unwrap_or_default(), movable_limits)
+            }
+            MathItem::List(_) => {
+                self.start("mrow", &[])?;
+                self.row_contents(Some(expr))?;
+                self.end("mrow")
+            }
+            MathItem::Table(ref table) => self.table(table),
+            MathItem::Enclosure(ref enclosure) => self.enclosure(enclosure),
+            MathItem::Styled(ref styled) => self.styled(styled),
+            MathItem::Padded(ref padded) => self.padded(padded),
+            MathItem::Phantom(ref phantom) => {
+                self.start("mphantom", &[])?;
+                self.row_contents(phantom.content.as_ref())?;
+                self.end("mphantom")
+            }
+            MathItem::Semantics(ref semantics) => self.semantics(semantics, form, movable_limits),
+            MathItem::AlignMark => self.empty("malignmark", &[]),
+            MathItem::AlignGroup(group) => {
+                let mut attributes = Attributes::new();
+                if let Some(align) = group.group_align {
+                    attributes.push(("groupalign", horizontal_align(align).to_owned()));
+                }
+                self.empty("maligngroup", &attributes)
+            }
+            MathItem::Other(_) => self.empty("mrow", &[]),
+        }
+    }
+
+    /// Writes a field as a token element. Operators pass their attributes in `operator`.
+    fn token(&mut self, field: &Field, operator: Option<Attributes>) -> fmt::Result {
+        let text = match *field {
+            Field::Unicode(ref text) => text,
+            Field::Glyph(glyph) => {
+                let identifier = if operator.is_some() { "mo" } else { "mi" };
+                self.start(identifier, &operator.unwrap_or_default())?;
+                self.empty("mglyph", &[("index", glyph.glyph_code.to_string())])?;
+                return self.end(identifier);
+            }
+            Field::Empty => return self.empty("mrow", &[]),
+        };
+        let identifier = if operator.is_some() {
+            "mo"
+        } else if text.chars().any(char::is_whitespace) {
+            "mtext"
+        } else if text.chars().any(|chr| chr.is_ascii_digit())
+            && text.chars().all(|chr| chr.is_ascii_digit() || chr == '.')
+        {
+            "mn"
+        } else {
+            "mi"
+        };
+        let mut attributes = operator.unwrap_or_default();
+        // the parser sets single characters in italics unless told otherwise
+        if let Some(chr) = single_char(field) {
+            if convert_character_to_family(chr, Family::Italics) != chr {
+                attributes.push(("mathvariant", "normal".to_owned()));
+            }
+        }
+        self.start(identifier, &attributes)?;
+        self.text(text)?;
+        self.end(identifier)
+    }
+
+    fn operator(
+        &mut self,
+        operator: &Operator,
+        form: Form,
+        movable_limits: Option<bool>,
+    ) -> fmt::Result {
+        let entry = single_char(&operator.field)
+            .and_then(|chr| find_entry(chr, form))
+            .unwrap_or_default();
+        let mut attributes = Attributes::new();
+        if operator.leading_space != Length::em(f32::from(entry.lspace) / 18.0) {
+            attributes.push(("lspace", length(operator.leading_space)));
+        }
+        if operator.trailing_space != Length::em(f32::from(entry.rspace) / 18.0) {
+            attributes.push(("rspace", length(operator.trailing_space)));
+        }
+        let is_stretchy = entry.flags.contains(Flags::STRETCHY);
+        match operator.stretch_constraints {
+            Some(constraints) => {
+                if !is_stretchy {
+                    attributes.push(("stretchy", "true".to_owned()));
+                }
+                if constraints.symmetric != entry.flags.contains(Flags::SYMMETRIC) {
+                    attributes.push(("symmetric", constraints.symmetric.to_string()));
+                }
+                if let Some(min_size) = constraints.min_size {
+                    attributes.push(("minsize", length(min_size)));
+                }
+                if let Some(max_size) = constraints.max_size {
+                    attributes.push(("maxsize", length(max_size)));
+                }
+            }
+            None if is_stretchy => attributes.push(("stretchy", "false".to_owned())),
+            None => {}
+        }
+        if operator.is_large_op != entry.flags.contains(Flags::LARGEOP) {
+            attributes.push(("largeop", operator.is_large_op.to_string()));
+        }
+        if let Some(movable_limits) = movable_limits {
+            attributes.push(("movablelimits", movable_limits.to_string()));
+        }
+        if operator.line_break != LineBreak::Auto {
+            attributes.push(("linebreak", line_break(operator.line_break).to_owned()));
+        }
+        match operator.line_break_style {
+            LineBreakStyle::Before => {}
+            LineBreakStyle::After => attributes.push(("linebreakstyle", "after".to_owned())),
+            LineBreakStyle::Duplicate => {
+                attributes.push(("linebreakstyle", "duplicate".to_owned()))
+            }
+        }
+        if let Some(align) = operator.indent_align {
+            attributes.push(("indentalign", horizontal_align(align).to_owned()));
+        }
+        self.token(&operator.field, Some(attributes))
+    }
+
+    fn space(&mut self, space: MathSpace) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        for &(name, value) in &[
+            ("width", space.width),
+            ("height", space.ascent),
+            ("depth", space.descent),
+        ] {
+            if !value.is_null() {
+                attributes.push((name, length(value)));
+            }
+        }
+        self.empty("mspace", &attributes)
+    }
+
+    fn atom(&mut self, atom: &Atom, form: Option<Form>, limits: Option<bool>) -> fmt::Result {
+        if atom.top_left.is_some() || atom.bottom_left.is_some() {
+            let multiscripts = MultiScripts {
+                nucleus: atom.nucleus.clone(),
+                pre_scripts: vec![ScriptPair {
+                    subscript: atom.bottom_left.clone(),
+                    superscript: atom.top_left.clone(),
+                }],
+                post_scripts: vec![ScriptPair {
+                    subscript: atom.bottom_right.clone(),
+                    superscript: atom.top_right.clone(),
+                }],
+            };
+            return self.multiscripts(&multiscripts, form, limits);
+        }
+        let identifier = match (&atom.bottom_right, &atom.top_right) {
+            (Some(_), Some(_)) => "msubsup",
+            (Some(_), None) => "msub",
+            (None, Some(_)) => "msup",
+            (None, None) => return self.expr(atom.nucleus.as_ref(), form, limits),
+        };
+        self.start(identifier, &[])?;
+        self.expr(atom.nucleus.as_ref(), form, limits)?;
+        for script in atom.bottom_right.iter().chain(atom.top_right.iter()) {
+            self.expr(Some(script), Some(Form::Postfix), None)?;
+        }
+        self.end(identifier)
+    }
+
+    fn multiscripts(
+        &mut self,
+        multiscripts: &MultiScripts,
+        form: Option<Form>,
+        limits: Option<bool>,
+    ) -> fmt::Result {
+        self.start("mmultiscripts", &[])?;
+        match multiscripts.nucleus {
+            Some(ref nucleus) => self.expr(Some(nucleus), form, limits)?,
+            None => self.empty("none", &[])?,
+        }
+        self.script_pairs(&multiscripts.post_scripts)?;
+        if !multiscripts.pre_scripts.is_empty() {
+            self.empty("mprescripts", &[])?;
+            self.script_pairs(&multiscripts.pre_scripts)?;
+        }
+        self.end("mmultiscripts")
+    }
+
+    fn script_pairs(&mut self, pairs: &[ScriptPair]) -> fmt::Result {
+        for pair in pairs {
+            for script in &[&pair.subscript, &pair.superscript] {
+                match **script {
+                    Some(ref script) => self.expr(Some(script), Some(Form::Postfix), None)?,
+                    None => self.empty("none", &[])?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn over_under(
+        &mut self,
+        over_under: &OverUnder,
+        form: Option<Form>,
+        limits: Option<bool>,
+    ) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if over_under.over_is_accent {
+            attributes.push(("accent", "true".to_owned()));
+        }
+        if over_under.under_is_accent {
+            attributes.push(("accentunder", "true".to_owned()));
+        }
+        // the parser only places limits of embellished operators with movable limits
+        let limits = limits.or_else(|| {
+            let operator = core_operator(over_under.nucleus.as_ref()?)?;
+            let entry = find_entry(single_char(&operator.field)?, form?).unwrap_or_default();
+            let has_movable_limits = entry.flags.contains(Flags::MOVABLE_LIMITS);
+            if over_under.is_limits != has_movable_limits {
+                Some(over_under.is_limits)
+            } else {
+                None
+            }
+        });
+        let identifier = match (&over_under.under, &over_under.over) {
+            (Some(_), Some(_)) => "munderover",
+            (Some(_), None) => "munder",
+            (None, Some(_)) => "mover",
+            (None, None) => return self.expr(over_under.nucleus.as_ref(), form, limits),
+        };
+        self.start(identifier, &attributes)?;
+        self.expr(over_under.nucleus.as_ref(), form, limits)?;
+        for script in over_under.under.iter().chain(over_under.over.iter()) {
+            self.expr(Some(script), Some(Form::Postfix), None)?;
+        }
+        self.end(identifier)
+    }
+
+    fn fraction(
+        &mut self,
+        frac: &GeneralizedFraction,
+        form: Option<Form>,
+        limits: Option<bool>,
+    ) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if let Some(thickness) = frac.thickness {
+            attributes.push(("linethickness", length(thickness)));
+        }
+        if frac.numerator_align != HorizontalAlign::Center {
+            attributes.push((
+                "numalign",
+                horizontal_align(frac.numerator_align).to_owned(),
+            ));
+        }
+        if frac.denominator_align != HorizontalAlign::Center {
+            attributes.push((
+                "denomalign",
+                horizontal_align(frac.denominator_align).to_owned(),
+            ));
+        }
+        if frac.is_bevelled {
+            attributes.push(("bevelled", "true".to_owned()));
+        }
+        self.start("mfrac", &attributes)?;
+        self.expr(frac.numerator.as_ref(), form, limits)?;
+        self.expr(frac.denominator.as_ref(), None, None)?;
+        self.end("mfrac")
+    }
+
+    fn root(&mut self, root: &Root) -> fmt::Result {
+        match root.degree {
+            None => {
+                self.start("msqrt", &[])?;
+                self.row_contents(root.radicand.as_ref())?;
+                self.end("msqrt")
+            }
+            Some(ref degree) => {
+                self.start("mroot", &[])?;
+                self.expr(root.radicand.as_ref(), None, None)?;
+                self.expr(Some(degree), None, None)?;
+                self.end("mroot")
+            }
+        }
+    }
+
+    fn table(&mut self, table: &Table) -> fmt::Result {
+        let default = Table::default();
+        let mut attributes = Attributes::new();
+        if table.row_align != default.row_align {
+            attributes.push(("rowalign", list(&table.row_align, vertical_align)));
+        }
+        if table.column_align != default.column_align {
+            attributes.push(("columnalign", list(&table.column_align, horizontal_align)));
+        }
+        if table.row_spacing != default.row_spacing {
+            attributes.push(("rowspacing", list(&table.row_spacing, length)));
+        }
+        if table.column_spacing != default.column_spacing {
+            attributes.push(("columnspacing", list(&table.column_spacing, length)));
+        }
+        if table.row_lines != default.row_lines {
+            attributes.push(("rowlines", list(&table.row_lines, line_style)));
+        }
+        if table.column_lines != default.column_lines {
+            attributes.push(("columnlines", list(&table.column_lines, line_style)));
+        }
+        if table.frame != default.frame {
+            attributes.push(("frame", line_style(table.frame).to_owned()));
+        }
+        if table.frame_spacing != default.frame_spacing {
+            let (horizontal, vertical) = table.frame_spacing;
+            let spacing = format!("{} {}", length(horizontal), length(vertical));
+            attributes.push(("framespacing", spacing));
+        }
+        if !table.group_align.is_empty() {
+            attributes.push(("groupalign", group_align_list(&table.group_align)));
+        }
+        self.start("mtable", &attributes)?;
+        for row in &table.rows {
+            self.table_row(row)?;
+        }
+        self.end("mtable")
+    }
+
+    fn table_row(&mut self, row: &TableRow) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if let Some(align) = row.row_align {
+            attributes.push(("rowalign", vertical_align(align).to_owned()));
+        }
+        if !row.column_align.is_empty() {
+            attributes.push(("columnalign", list(&row.column_align, horizontal_align)));
+        }
+        if !row.group_align.is_empty() {
+            attributes.push(("groupalign", group_align_list(&row.group_align)));
+        }
+        let identifier = if row.label.is_some() {
+            "mlabeledtr"
+        } else {
+            "mtr"
+        };
+        self.start(identifier, &attributes)?;
+        if let Some(ref label) = row.label {
+            self.start("mtd", &[])?;
+            self.row_contents(Some(label))?;
+            self.end("mtd")?;
+        }
+        for cell in &row.cells {
+            self.table_cell(cell)?;
+        }
+        self.end(identifier)
+    }
+
+    fn table_cell(&mut self, cell: &TableCell) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if let Some(align) = cell.row_align {
+            attributes.push(("rowalign", vertical_align(align).to_owned()));
+        }
+        if let Some(align) = cell.column_align {
+            attributes.push(("columnalign", horizontal_align(align).to_owned()));
+        }
+        if !cell.group_align.is_empty() {
+            attributes.push(("groupalign", list(&cell.group_align, horizontal_align)));
+        }
+        self.start("mtd", &attributes)?;
+        if cell.content.is_some() {
+            self.row_contents(cell.content.as_ref())?;
+        }
+        self.end("mtd")
+    }
+
+    fn enclosure(&mut self, enclosure: &Enclosure) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if enclosure.notations != [Notation::LongDiv] {
+            attributes.push(("notation", list(&enclosure.notations, notation)));
+        }
+        self.start("menclose", &attributes)?;
+        self.row_contents(enclosure.content.as_ref())?;
+        self.end("menclose")
+    }
+
+    fn styled(&mut self, styled: &Styled) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        if let Some(math_style) = styled.math_style {
+            let display_style = math_style == MathStyle::Display;
+            attributes.push(("displaystyle", display_style.to_string()));
+        }
+        match styled.script_level {
+            Some(ScriptLevel::Absolute(level)) => {
+                attributes.push(("scriptlevel", level.to_string()))
+            }
+            Some(ScriptLevel::Relative(change)) => {
+                attributes.push(("scriptlevel", format!("{:+}", change)))
+            }
+            None => {}
+        }
+        if let Some(math_size) = styled.math_size {
+            attributes.push(("mathsize", length(math_size)));
+        }
+        if let Some(color) = styled.math_color {
+            attributes.push(("mathcolor", color.to_string()));
+        }
+        if let Some(background) = styled.math_background {
+            attributes.push(("mathbackground", background.to_string()));
+        }
+        match styled.direction {
+            Some(TextDirection::Ltr) => attributes.push(("dir", "ltr".to_string())),
+            Some(TextDirection::Rtl) => attributes.push(("dir", "rtl".to_string())),
+            None => {}
+        }
+        self.start("mstyle", &attributes)?;
+        self.row_contents(styled.content.as_ref())?;
+        self.end("mstyle")
+    }
+
+    fn padded(&mut self, padded: &Padded) -> fmt::Result {
+        let mut attributes = Attributes::new();
+        for &(name, value) in &[
+            ("width", padded.width),
+            ("height", padded.height),
+            ("depth", padded.depth),
+            ("lspace", padded.lspace),
+            ("voffset", padded.voffset),
+        ] {
+            if let Some(value) = value {
+                attributes.push((name, padded_length(value)));
+            }
+        }
+        self.start("mpadded", &attributes)?;
+        self.row_contents(padded.content.as_ref())?;
+        self.end("mpadded")
+    }
+
+    /// Writes a `semantics` element. Its content takes the place of the element in a row.
+    fn semantics(
+        &mut self,
+        semantics: &Semantics,
+        form: Option<Form>,
+        movable_limits: Option<bool>,
+    ) -> fmt::Result {
+        self.start("semantics", &[])?;
+        self.expr(semantics.content.as_ref(), form, movable_limits)?;
+        for annotation in &semantics.annotations {
+            let mut attributes = Attributes::new();
+            if let Some(ref encoding) = annotation.encoding {
+                attributes.push(("encoding", encoding.clone()));
+            }
+            if annotation.is_xml {
+                self.start("annotation-xml", &attributes)?;
+                self.out.write_str(&annotation.content)?;
+                self.end("annotation-xml")?;
+            } else {
+                self.start("annotation", &attributes)?;
+                self.text(&annotation.content)?;
+                self.end("annotation")?;
+            }
+        }
+        self.end("semantics")
+    }
+
+    fn start(&mut self, identifier: &str, attributes: &[(&str, String)]) -> fmt::Result {
+        self.tag(identifier, attributes)?;
+        self.out.write_char('>')
+    }
+
+    fn empty(&mut self, identifier: &str, attributes: &[(&str, String)]) -> fmt::Result {
+        self.tag(identifier, attributes)?;
+        self.out.write_str("/>")
+    }
+
+    fn tag(&mut self, identifier: &str, attributes: &[(&str, String)]) -> fmt::Result {
+        write!(self.out, "<{}", identifier)?;
+        for &(name, ref value) in attributes {
+            write!(self.out, " {}=\"", name)?;
+            self.text(value)?;
+            self.out.write_char('"')?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, identifier: &str) -> fmt::Result {
+        write!(self.out, "</{}>", identifier)
+    }
+
+    fn text(&mut self, text: &str) -> fmt::Result {
+        for chr in text.chars() {
+            match chr {
+                '&' => self.out.write_str("&amp;")?,
+                '<' => self.out.write_str("&lt;")?,
+                '>' => self.out.write_str("&gt;")?,
+                '"' => self.out.write_str("&quot;")?,
+                chr => self.out.write_char(chr)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the operator at the core of `expr` if `expr` is an embellished operator whose form is
+/// decided by the position of `expr`.
+fn core_operator(expr: &MathExpression) -> Option<&Operator> {
+    let core = match *expr.item {
+        MathItem::Operator(ref operator) => return Some(operator),
+        MathItem::Atom(ref atom) => &atom.nucleus,
+        MathItem::OverUnder(ref over_under) => &over_under.nucleus,
+        MathItem::MultiScripts(ref multiscripts) => &multiscripts.nucleus,
+        MathItem::GeneralizedFraction(ref frac) => &frac.numerator,
+        _ => return None,
+    };
+    core.as_ref().and_then(core_operator)
+}
+
+fn single_char(field: &Field) -> Option<char> {
+    match *field {
+        Field::Unicode(ref text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(chr), None) => Some(chr),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The named spaces of MathML indexed by their width in math units.
+const NAMED_SPACES: [&str; 8] = [
+    "",
+    "veryverythinmathspace",
+    "verythinmathspace",
+    "thinmathspace",
+    "mediummathspace",
+    "thickmathspace",
+    "verythickmathspace",
+    "veryverythickmathspace",
+];
+
+/// Formats a length. Units that are relative to a font parameter or to the size of an operator
+/// are written as plain numbers, which MathML reads as multiples of the attribute's default value.
+fn length(length: Length) -> String {
+    match length.unit {
+        LengthUnit::Point => format!("{}pt", length.value),
+        LengthUnit::Em => format!("{}em", length.value),
+        LengthUnit::Ex => format!("{}ex", length.value),
+        LengthUnit::Pixel => format!("{}px", length.value),
+        LengthUnit::Inch => format!("{}in", length.value),
+        LengthUnit::Centimeter => format!("{}cm", length.value),
+        LengthUnit::Millimeter => format!("{}mm", length.value),
+        LengthUnit::Percent => format!("{}%", length.value),
+        // MathML has no math units, but their small multiples are named spaces
+        LengthUnit::MathUnit => match NAMED_SPACES.get(length.value.abs() as usize) {
+            Some(name) if length.value.fract() == 0.0 && length.value != 0.0 => {
+                let sign = if length.value < 0.0 { "negative" } else { "" };
+                format!("{}{}", sign, name)
+            }
+            _ => format!("{}em", length.value / 18.0),
+        },
+        LengthUnit::DisplayOperatorMinHeight
+        | LengthUnit::FractionRuleThickness
+        | LengthUnit::NormalSize => length.value.to_string(),
+    }
+}
+
+fn padded_length(length: PaddedLength) -> String {
+    // a sign marks the value as an increment
+    let (sign, magnitude): (_, fn(f32) -> f32) = match length.value {
+        PaddedValue::Length(Length { value, .. }) | PaddedValue::Dimension(value, _)
+            if length.is_increment =>
+        {
+            (if value < 0.0 { "-" } else { "+" }, f32::abs)
+        }
+        _ => ("", |value| value),
+    };
+    match length.value {
+        PaddedValue::Length(Length { value, unit }) => {
+            format!(
+                "{}{}",
+                sign,
+                self::length(Length::new(magnitude(value), unit))
+            )
+        }
+        PaddedValue::Dimension(value, pseudo_unit) => {
+            let pseudo_unit = match pseudo_unit {
+                PseudoUnit::Width => "width",
+                PseudoUnit::Height => "height",
+                PseudoUnit::Depth => "depth",
+            };
+            format!("{}{}{}", sign, magnitude(value), pseudo_unit)
+        }
+    }
+}
+
+fn list<T: Copy, S: AsRef<str>>(values: &[T], format: impl Fn(T) -> S) -> String {
+    let values = values
+        .iter()
+        .map(|&value| format(value).as_ref().to_owned())
+        .collect::<Vec<_>>();
+    values.join(" ")
+}
+
+/// Formats the group alignments of every column in braces.
+fn group_align_list(columns: &[Vec<HorizontalAlign>]) -> String {
+    let columns = columns
+        .iter()
+        .map(|aligns| format!("{{{}}}", list(aligns, horizontal_align)))
+        .collect::<Vec<_>>();
+    columns.join(" ")
+}
+
+fn horizontal_align(align: HorizontalAlign) -> &'static str {
+    match align {
+        HorizontalAlign::Left => "left",
+        HorizontalAlign::Center => "center",
+        HorizontalAlign::Right => "right",
+    }
+}
+
+fn line_break(line_break: LineBreak) -> &'static str {
+    match line_break {
+        LineBreak::Auto => "auto",
+        LineBreak::NewLine => "newline",
+        LineBreak::NoBreak => "nobreak",
+        LineBreak::GoodBreak => "goodbreak",
+        LineBreak::BadBreak => "badbreak",
+    }
+}
+
+fn vertical_align(align: VerticalAlign) -> &'static str {
+    match align {
+        VerticalAlign::Top => "top",
+        VerticalAlign::Bottom => "bottom",
+        VerticalAlign::Center => "center",
+        VerticalAlign::Baseline => "baseline",
+        VerticalAlign::Axis => "axis",
+    }
+}
+
+fn line_style(style: LineStyle) -> &'static str {
+    match style {
+        LineStyle::None => "none",
+        LineStyle::Solid => "solid",
+        LineStyle::Dashed => "dashed",
+    }
+}
+
+fn notation(notation: Notation) -> &'static str {
+    match notation {
+        Notation::LongDiv => "longdiv",
+        Notation::Actuarial => "actuarial",
+        Notation::Radical => "radical",
+        Notation::Box => "box",
+        Notation::RoundedBox => "roundedbox",
+        Notation::Circle => "circle",
+        Notation::Left => "left",
+        Notation::Right => "right",
+        Notation::Top => "top",
+        Notation::Bottom => "bottom",
+        Notation::UpDiagonalStrike => "updiagonalstrike",
+        Notation::DownDiagonalStrike => "downdiagonalstrike",
+        Notation::VerticalStrike => "verticalstrike",
+        Notation::HorizontalStrike => "horizontalstrike",
+        Notation::UpDiagonalArrow => "updiagonalarrow",
+        Notation::DownDiagonalArrow => "southeastarrow",
+        Notation::UpArrow => "uparrow",
+        Notation::DownArrow => "downarrow",
+        Notation::LeftArrow => "leftarrow",
+        Notation::RightArrow => "rightarrow",
+        Notation::MAdruwb => "madruwb",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AlignGroup, StretchConstraints};
+
+    fn field(text: &str) -> MathExpression {
+        MathExpression::new(MathItem::Field(Field::Unicode(text.into())), 0)
+    }
+
+    fn operator(text: &str, leading_space: f32, trailing_space: f32) -> Operator {
+        Operator {
+            field: Field::Unicode(text.into()),
+            leading_space: Length::em(leading_space / 18.0),
+            trailing_space: Length::em(trailing_space / 18.0),
+            ..Default::default()
+        }
+    }
+
+    fn list(items: Vec<MathItem>) -> MathExpression {
+        let items = items
+            .into_iter()
+            .map(|item| MathExpression::new(item, 0))
+            .collect();
+        MathExpression::new(MathItem::List(items), 0)
+    }
+
+    #[test]
+    fn test_tokens() {
+        let expr = list(vec![
+            MathItem::Field(Field::Unicode("\u{1D465}".into())),
+            MathItem::Field(Field::Unicode("12.5".into())),
+            MathItem::Field(Field::Unicode("sin".into())),
+            MathItem::Field(Field::Unicode("a < b".into())),
+        ]);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mi>\u{1D465}</mi><mn>12.5</mn><mi>sin</mi><mtext>a &lt; b</mtext></math>"
+        );
+    }
+
+    #[test]
+    fn test_operator_defaults() {
+        // the spacing of "+" depends on whether it is used as a prefix or infix operator
+        let expr = list(vec![
+            MathItem::Operator(operator("+", 0.0, 1.0)),
+            MathItem::Field(Field::Unicode("a".into())),
+            MathItem::Operator(operator("+", 4.0, 4.0)),
+            MathItem::Field(Field::Unicode("b".into())),
+        ]);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mo>+</mo><mi mathvariant=\"normal\">a</mi><mo>+</mo>\
+             <mi mathvariant=\"normal\">b</mi></math>"
+        );
+    }
+
+    #[test]
+    fn test_operator_attributes() {
+        let relation = Operator {
+            stretch_constraints: Some(StretchConstraints {
+                min_size: Some(Length::em(2.0)),
+                max_size: None,
+                symmetric: true,
+            }),
+            is_large_op: true,
+            ..operator("=", 0.0, 2.0)
+        };
+        let expr = MathExpression::new(MathItem::Operator(relation), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mo lspace=\"0em\" rspace=\"0.11111111em\" stretchy=\"true\" \
+             symmetric=\"true\" minsize=\"2em\" largeop=\"true\">=</mo></math>"
+        );
+
+        let fence = Operator {
+            stretch_constraints: None,
+            ..operator("(", 0.0, 0.0)
+        };
+        let expr = MathExpression::new(MathItem::Operator(fence), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mo stretchy=\"false\">(</mo></math>"
+        );
+
+        let break_point = Operator {
+            line_break: LineBreak::NewLine,
+            line_break_style: LineBreakStyle::Duplicate,
+            indent_align: Some(HorizontalAlign::Left),
+            ..operator("+", 0.0, 0.0)
+        };
+        let expr = MathExpression::new(MathItem::Operator(break_point), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mo lspace=\"0em\" rspace=\"0em\" linebreak=\"newline\" \
+             linebreakstyle=\"duplicate\" indentalign=\"left\">+</mo></math>"
+        );
+
+        let spaced = Operator {
+            leading_space: Length::new(-3.0, LengthUnit::MathUnit),
+            trailing_space: Length::new(9.0, LengthUnit::MathUnit),
+            ..operator("+", 0.0, 0.0)
+        };
+        let expr = MathExpression::new(MathItem::Operator(spaced), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mo lspace=\"negativethinmathspace\" rspace=\"0.5em\">+</mo></math>"
+        );
+    }
+
+    #[test]
+    fn test_embellished_operator() {
+        // the parser would read an operator in the denominator of a fraction as plain text
+        let frac = GeneralizedFraction {
+            numerator: Some(field("1")),
+            denominator: Some(MathExpression::new(
+                MathItem::Operator(operator("=", 5.0, 5.0)),
+                0,
+            )),
+            ..Default::default()
+        };
+        let expr = MathExpression::new(MathItem::GeneralizedFraction(frac), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mfrac><mn>1</mn><mrow><mo>=</mo></mrow></mfrac></math>"
+        );
+
+        // a sum without limits in display style
+        let sum = OverUnder {
+            nucleus: Some(MathExpression::new(
+                MathItem::Operator(Operator {
+                    is_large_op: true,
+                    ..operator("\u{2211}", 3.0, 3.0)
+                }),
+                0,
+            )),
+            under: Some(field("i")),
+            is_limits: false,
+            ..Default::default()
+        };
+        let expr = MathExpression::new(MathItem::OverUnder(sum), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><munder><mo lspace=\"0.16666667em\" rspace=\"0.16666667em\" \
+             movablelimits=\"false\">\u{2211}</mo><mi mathvariant=\"normal\">i</mi></munder></math>"
+        );
+    }
+
+    #[test]
+    fn test_schemata() {
+        let expr = list(vec![
+            MathItem::Root(Root {
+                radicand: Some(field("2")),
+                degree: Some(field("3")),
+            }),
+            MathItem::Space(MathSpace::horizontal_space(Length::em(1.0))),
+            MathItem::GeneralizedFraction(GeneralizedFraction {
+                numerator: Some(field("1")),
+                denominator: Some(field("2")),
+                thickness: Some(Length::new(2.0, LengthUnit::FractionRuleThickness)),
+                numerator_align: HorizontalAlign::Left,
+                ..Default::default()
+            }),
+            MathItem::Atom(Atom {
+                nucleus: Some(field("C")),
+                top_left: Some(field("14")),
+                ..Default::default()
+            }),
+        ]);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mroot><mn>2</mn><mn>3</mn></mroot><mspace width=\"1em\"/>\
+             <mfrac linethickness=\"2\" numalign=\"left\"><mn>1</mn><mn>2</mn></mfrac>\
+             <mmultiscripts><mi mathvariant=\"normal\">C</mi><none/><none/><mprescripts/>\
+             <none/><mn>14</mn></mmultiscripts></math>"
+        );
+    }
+
+    #[test]
+    fn test_alignment() {
+        // alignment elements do not change the form of the operators next to them
+        let cell = list(vec![
+            MathItem::AlignGroup(AlignGroup::default()),
+            MathItem::Field(Field::Unicode("x".into())),
+            MathItem::AlignMark,
+            MathItem::Operator(operator("=", 5.0, 5.0)),
+            MathItem::Field(Field::Unicode("y".into())),
+            MathItem::AlignGroup(AlignGroup {
+                group_align: Some(HorizontalAlign::Right),
+            }),
+        ]);
+        let table = Table {
+            rows: vec![TableRow {
+                cells: vec![TableCell {
+                    content: Some(cell),
+                    group_align: vec![HorizontalAlign::Left],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            group_align: vec![vec![HorizontalAlign::Left, HorizontalAlign::Center]],
+            ..Default::default()
+        };
+        let expr = MathExpression::new(MathItem::Table(table), 0);
+        assert_eq!(
+            to_string(&expr),
+            "<math><mtable groupalign=\"{left center}\"><mtr><mtd groupalign=\"left\">\
+             <maligngroup/><mi mathvariant=\"normal\">x</mi><malignmark/><mo>=</mo>\
+             <mi mathvariant=\"normal\">y</mi><maligngroup groupalign=\"right\"/>\
+             </mtd></mtr></mtable></math>"
+        );
+    }
+}
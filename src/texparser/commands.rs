@@ -0,0 +1,200 @@
+//! Tables of the TeX control sequences that stand for a single symbol.
+
+use crate::unicode_math::Family;
+
+/// The meaning of a control sequence that does not take any arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Symbol {
+    /// An identifier that is set in italics unless another math alphabet is selected.
+    Identifier(char),
+    /// An identifier that is set upright, like the capital Greek letters.
+    UprightIdentifier(char),
+    /// An operator. Its spacing and stretchiness are taken from the operator dictionary.
+    Operator(char),
+    /// A named function like `\sin`. If `limits` is true, scripts are placed above and below the
+    /// name in display style.
+    Function { name: &'static str, limits: bool },
+    /// Horizontal space, measured in em.
+    Space(f32),
+}
+
+#[rustfmt::skip]
+static IDENTIFIERS: [(&str, char); 38] = [
+    ("alpha", 'α'), ("beta", 'β'), ("gamma", 'γ'), ("delta", 'δ'), ("epsilon", 'ϵ'),
+    ("varepsilon", 'ε'), ("zeta", 'ζ'), ("eta", 'η'), ("theta", 'θ'), ("vartheta", 'ϑ'),
+    ("iota", 'ι'), ("kappa", 'κ'), ("lambda", 'λ'), ("mu", 'μ'), ("nu", 'ν'), ("xi", 'ξ'),
+    ("pi", 'π'), ("varpi", 'ϖ'), ("rho", 'ρ'), ("varrho", 'ϱ'), ("sigma", 'σ'),
+    ("varsigma", 'ς'), ("tau", 'τ'), ("upsilon", 'υ'), ("phi", 'ϕ'), ("varphi", 'φ'),
+    ("chi", 'χ'), ("psi", 'ψ'), ("omega", 'ω'),
+    ("imath", 'ı'), ("jmath", 'ȷ'), ("ell", 'ℓ'), ("wp", '℘'), ("hbar", 'ℏ'),
+    ("partial", '∂'), ("aleph", 'ℵ'), ("Re", 'ℜ'), ("Im", 'ℑ'),
+];
+
+#[rustfmt::skip]
+static UPRIGHT_IDENTIFIERS: [(&str, char); 19] = [
+    ("Gamma", 'Γ'), ("Delta", 'Δ'), ("Theta", 'Θ'), ("Lambda", 'Λ'), ("Xi", 'Ξ'), ("Pi", 'Π'),
+    ("Sigma", 'Σ'), ("Upsilon", 'Υ'), ("Phi", 'Φ'), ("Psi", 'Ψ'), ("Omega", 'Ω'),
+    ("infty", '∞'), ("nabla", '∇'), ("emptyset", '∅'), ("varnothing", '∅'),
+    ("#", '#'), ("$", '$'), ("%", '%'), ("_", '_'),
+];
+
+#[rustfmt::skip]
+static OPERATORS: [(&str, char); 124] = [
+    // binary operators
+    ("pm", '±'), ("mp", '∓'), ("times", '×'), ("div", '÷'), ("cdot", '⋅'), ("ast", '∗'),
+    ("star", '⋆'), ("circ", '∘'), ("bullet", '∙'), ("oplus", '⊕'), ("ominus", '⊖'),
+    ("otimes", '⊗'), ("oslash", '⊘'), ("odot", '⊙'), ("cup", '∪'), ("cap", '∩'),
+    ("uplus", '⊎'), ("sqcup", '⊔'), ("sqcap", '⊓'), ("setminus", '∖'), ("wedge", '∧'),
+    ("land", '∧'), ("vee", '∨'), ("lor", '∨'), ("wr", '≀'), ("dagger", '†'), ("ddagger", '‡'),
+    // relations
+    ("leq", '≤'), ("le", '≤'), ("geq", '≥'), ("ge", '≥'), ("neq", '≠'), ("ne", '≠'),
+    ("equiv", '≡'), ("approx", '≈'), ("sim", '∼'), ("simeq", '≃'), ("cong", '≅'),
+    ("propto", '∝'), ("ll", '≪'), ("gg", '≫'), ("prec", '≺'), ("succ", '≻'), ("preceq", '⪯'),
+    ("succeq", '⪰'), ("subset", '⊂'), ("supset", '⊃'), ("subseteq", '⊆'), ("supseteq", '⊇'),
+    ("in", '∈'), ("notin", '∉'), ("ni", '∋'), ("perp", '⊥'), ("parallel", '∥'), ("mid", '∣'),
+    ("vdash", '⊢'), ("dashv", '⊣'), ("models", '⊨'), ("asymp", '≍'), ("doteq", '≐'),
+    // arrows
+    ("to", '→'), ("rightarrow", '→'), ("leftarrow", '←'), ("gets", '←'),
+    ("leftrightarrow", '↔'), ("Rightarrow", '⇒'), ("Leftarrow", '⇐'), ("Leftrightarrow", '⇔'),
+    ("longrightarrow", '⟶'), ("longleftarrow", '⟵'), ("Longrightarrow", '⟹'),
+    ("Longleftarrow", '⟸'), ("implies", '⟹'), ("iff", '⟺'), ("mapsto", '↦'),
+    ("uparrow", '↑'), ("downarrow", '↓'), ("Uparrow", '⇑'), ("Downarrow", '⇓'),
+    // large operators
+    ("sum", '∑'), ("prod", '∏'), ("coprod", '∐'), ("int", '∫'), ("iint", '∬'),
+    ("iiint", '∭'), ("oint", '∮'), ("bigcup", '⋃'), ("bigcap", '⋂'), ("bigvee", '⋁'),
+    ("bigwedge", '⋀'), ("bigoplus", '⨁'), ("bigotimes", '⨂'), ("bigodot", '⨀'),
+    ("biguplus", '⨄'),
+    // delimiters
+    ("{", '{'), ("}", '}'), ("lbrace", '{'), ("rbrace", '}'), ("langle", '⟨'),
+    ("rangle", '⟩'), ("lfloor", '⌊'), ("rfloor", '⌋'), ("lceil", '⌈'), ("rceil", '⌉'),
+    ("|", '‖'), ("vert", '|'), ("Vert", '‖'), ("lvert", '|'), ("rvert", '|'), ("lVert", '‖'),
+    ("rVert", '‖'), ("backslash", '∖'),
+    // miscellaneous
+    ("forall", '∀'), ("exists", '∃'), ("neg", '¬'), ("lnot", '¬'), ("prime", '′'),
+    ("ldots", '…'), ("dots", '…'), ("cdots", '⋯'), ("vdots", '⋮'), ("ddots", '⋱'),
+    ("colon", ':'), ("&", '&'),
+];
+
+#[rustfmt::skip]
+static FUNCTIONS: [(&str, &str, bool); 33] = [
+    ("arccos", "arccos", false), ("arcsin", "arcsin", false), ("arctan", "arctan", false),
+    ("arg", "arg", false), ("cos", "cos", false), ("cosh", "cosh", false), ("cot", "cot", false),
+    ("coth", "coth", false), ("csc", "csc", false), ("deg", "deg", false), ("dim", "dim", false),
+    ("exp", "exp", false), ("hom", "hom", false), ("ker", "ker", false), ("lg", "lg", false),
+    ("ln", "ln", false), ("log", "log", false), ("sec", "sec", false), ("sin", "sin", false),
+    ("sinh", "sinh", false), ("tan", "tan", false), ("tanh", "tanh", false),
+    ("det", "det", true), ("gcd", "gcd", true), ("inf", "inf", true), ("lim", "lim", true),
+    ("liminf", "lim inf", true), ("limsup", "lim sup", true), ("max", "max", true),
+    ("min", "min", true), ("Pr", "Pr", true), ("sup", "sup", true), ("injlim", "inj lim", true),
+];
+
+#[rustfmt::skip]
+static SPACES: [(&str, f32); 13] = [
+    (",", 3.0 / 18.0), (":", 4.0 / 18.0), (">", 4.0 / 18.0), (";", 5.0 / 18.0),
+    ("!", -3.0 / 18.0), (" ", 1.0 / 3.0), ("thinspace", 3.0 / 18.0), ("medspace", 4.0 / 18.0),
+    ("thickspace", 5.0 / 18.0), ("negthinspace", -3.0 / 18.0), ("enspace", 0.5),
+    ("quad", 1.0), ("qquad", 2.0),
+];
+
+/// Control sequences with arguments or other special meaning that are handled by the parser.
+static STRUCTURAL_COMMANDS: [&str; 32] = [
+    "frac",
+    "dfrac",
+    "tfrac",
+    "binom",
+    "sqrt",
+    "left",
+    "right",
+    "middle",
+    "text",
+    "mbox",
+    "operatorname",
+    "begin",
+    "end",
+    "overline",
+    "underline",
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "scriptscriptstyle",
+    "tiny",
+    "scriptsize",
+    "footnotesize",
+    "small",
+    "normalsize",
+    "large",
+    "Large",
+    "LARGE",
+    "huge",
+    "Huge",
+    "limits",
+    "nolimits",
+    "\\",
+];
+
+/// Returns the meaning of a control sequence that stands for a single symbol.
+pub fn symbol(name: &str) -> Option<Symbol> {
+    fn find<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+        table
+            .iter()
+            .find(|&&(entry, _)| entry == name)
+            .map(|&(_, value)| value)
+    }
+    find(&IDENTIFIERS, name)
+        .map(Symbol::Identifier)
+        .or_else(|| find(&UPRIGHT_IDENTIFIERS, name).map(Symbol::UprightIdentifier))
+        .or_else(|| find(&OPERATORS, name).map(Symbol::Operator))
+        .or_else(|| find(&SPACES, name).map(Symbol::Space))
+        .or_else(|| {
+            FUNCTIONS
+                .iter()
+                .find(|&&(entry, _, _)| entry == name)
+                .map(|&(_, name, limits)| Symbol::Function { name, limits })
+        })
+}
+
+/// Returns the math alphabet selected by commands like `\mathbb`.
+pub fn math_alphabet(name: &str) -> Option<Family> {
+    match name {
+        "mathrm" => Some(Family::Normal),
+        "mathit" => Some(Family::Italics),
+        "mathbf" => Some(Family::Bold),
+        "boldsymbol" => Some(Family::BoldItalics),
+        "mathcal" | "mathscr" => Some(Family::Script),
+        "mathfrak" => Some(Family::Fraktur),
+        "mathbb" => Some(Family::DoubleStruck),
+        "mathsf" => Some(Family::SansSerif),
+        "mathtt" => Some(Family::Monospace),
+        _ => None,
+    }
+}
+
+/// Returns the accent character for accent commands like `\hat` and whether it stretches to the
+/// width of the accented expression.
+pub fn accent(name: &str) -> Option<(char, bool)> {
+    match name {
+        "hat" => Some(('^', false)),
+        "widehat" => Some(('^', true)),
+        "check" => Some(('ˇ', false)),
+        "tilde" => Some(('~', false)),
+        "widetilde" => Some(('~', true)),
+        "acute" => Some(('´', false)),
+        "grave" => Some(('`', false)),
+        "breve" => Some(('˘', false)),
+        "bar" => Some(('¯', false)),
+        "vec" => Some(('→', false)),
+        "overrightarrow" => Some(('→', true)),
+        "overleftarrow" => Some(('←', true)),
+        "dot" => Some(('˙', false)),
+        "ddot" => Some(('¨', false)),
+        _ => None,
+    }
+}
+
+/// Returns true if `name` is a control sequence that is defined without any user macros.
+pub fn is_builtin(name: &str) -> bool {
+    STRUCTURAL_COMMANDS.contains(&name)
+        || symbol(name).is_some()
+        || math_alphabet(name).is_some()
+        || accent(name).is_some()
+}
@@ -0,0 +1,126 @@
+use crate::mathmlparser::{ErrorType, ParsingError};
+
+/// The smallest unit of TeX input. Every token is stored together with the byte offset in the
+/// source where it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A control sequence like `\frac` or a control symbol like `\,` (without the backslash).
+    Command(String),
+    Character(char),
+    /// One or more consecutive whitespace characters.
+    Space,
+    BeginGroup,
+    EndGroup,
+    Superscript,
+    Subscript,
+    /// The column separator `&` inside of environments.
+    Alignment,
+    /// A macro parameter like `#1`.
+    Parameter(u8),
+}
+
+/// Splits TeX source code into tokens.
+///
+/// Comments are dropped and spaces following a control word are skipped, as they would be by TeX.
+pub fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParsingError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((position, chr)) = chars.next() {
+        let token = match chr {
+            '\\' => {
+                let mut name = String::new();
+                match chars.next() {
+                    Some((_, first)) if first.is_ascii_alphabetic() => {
+                        name.push(first);
+                        while let Some(&(_, chr)) = chars.peek() {
+                            if !chr.is_ascii_alphabetic() {
+                                break;
+                            }
+                            name.push(chr);
+                            chars.next();
+                        }
+                        while chars
+                            .peek()
+                            .map(|&(_, chr)| chr.is_whitespace())
+                            .unwrap_or(false)
+                        {
+                            chars.next();
+                        }
+                    }
+                    Some((_, symbol)) => name.push(symbol),
+                    None => {
+                        return Err(ParsingError {
+                            position: Some(position),
+                            error_type: ErrorType::UnexpectedEndOfInput,
+                        })
+                    }
+                }
+                Token::Command(name)
+            }
+            '%' => {
+                // the line break ending the comment is dropped as well
+                while chars.next().map(|(_, chr)| chr != '\n').unwrap_or(false) {}
+                continue;
+            }
+            '#' => match chars.next() {
+                Some((_, digit @ '1'..='9')) => Token::Parameter(digit as u8 - b'0'),
+                _ => {
+                    return Err(ParsingError {
+                        position: Some(position),
+                        error_type: ErrorType::OtherError(
+                            "\"#\" has to be followed by a parameter number.".to_owned(),
+                        ),
+                    })
+                }
+            },
+            chr if chr.is_whitespace() => {
+                while chars
+                    .peek()
+                    .map(|&(_, chr)| chr.is_whitespace())
+                    .unwrap_or(false)
+                {
+                    chars.next();
+                }
+                Token::Space
+            }
+            '{' => Token::BeginGroup,
+            '}' => Token::EndGroup,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '&' => Token::Alignment,
+            chr => Token::Character(chr),
+        };
+        tokens.push((token, position));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("\\frac 1{x_i}\\, % comment\n#2")
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Command("frac".to_owned()),
+                Token::Character('1'),
+                Token::BeginGroup,
+                Token::Character('x'),
+                Token::Subscript,
+                Token::Character('i'),
+                Token::EndGroup,
+                Token::Command(",".to_owned()),
+                Token::Space,
+                Token::Parameter(2),
+            ]
+        );
+        assert!(tokenize("x\\").is_err());
+    }
+}
@@ -0,0 +1,1031 @@
+//! A parser for formulas written in LaTeX math notation.
+//!
+//! The formula is translated into the tree that the MathML parser builds for the equivalent
+//! presentation markup (e.g. `\frac{a}{b}` becomes `<mfrac><mi>a</mi><mi>b</mi></mfrac>`). This way
+//! both front ends share the treatment of embellished operators and take the spacing and
+//! stretchiness of operators from the same operator dictionary.
+
+mod commands;
+mod lexer;
+
+use std::collections::HashMap;
+
+use self::commands::Symbol;
+use self::lexer::Token;
+use crate::mathmlparser::operator_dict::find_entry;
+use crate::mathmlparser::{
+    Attributes, ErrorType, Flags, Form, OperatorAttributes, ParseContext, ParsingError,
+    SchemaAttributes, TokenAttributes,
+};
+use crate::types::{
+    HorizontalAlign, Length, LengthUnit, MathExpression, MathItem, Notation, ScriptLevel, Table,
+    TableCell, TableRow,
+};
+use crate::unicode_math::Family;
+
+pub type Result<T> = std::result::Result<T, ParsingError>;
+
+/// Macros may expand to other macros, but a definition like `\newcommand{\a}{\a}` must not keep
+/// the parser busy forever.
+const MAX_MACRO_EXPANSIONS: usize = 10000;
+
+/// Neither must a definition like `\newcommand{\a}[1]{\a{#1#1}}` grow the remaining input without
+/// bounds.
+const MAX_EXPANDED_TOKENS: usize = 100000;
+
+/// Parses a formula in LaTeX math notation (without the surrounding `$` signs).
+///
+/// Errors report the byte offset into `source` at which they occurred.
+///
+/// # Examples
+/// ```
+/// # use math_render::texparser;
+/// let expr = texparser::parse(r"\newcommand{\half}{\frac{1}{2}} x^{\half}").unwrap();
+/// assert!(texparser::parse(r"\frac{1}").is_err());
+/// ```
+pub fn parse(source: &str) -> Result<MathExpression> {
+    let mut parser = Parser::new(source)?;
+    let list = parser.parse_list(Terminator::EndOfInput)?;
+    parser.context.schema("math", Attributes::default(), list)
+}
+
+/// The token that ends a list of expressions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Terminator {
+    EndOfInput,
+    EndGroup,
+    /// The `\right` of a `\left ... \right` pair.
+    Right,
+    /// The `]` of an optional argument.
+    Bracket,
+    /// `&`, `\\` or `\end` inside of an environment.
+    Cell,
+    /// The `$` that ends a formula inside of `\text`.
+    Dollar,
+}
+
+impl Terminator {
+    fn matches(self, token: &Token) -> bool {
+        match (self, token) {
+            (Terminator::EndGroup, Token::EndGroup) => true,
+            (Terminator::Right, Token::Command(name)) => name == "right",
+            (Terminator::Bracket, Token::Character(']')) => true,
+            (Terminator::Cell, Token::Alignment) => true,
+            (Terminator::Cell, Token::Command(name)) => name == "\\" || name == "end",
+            (Terminator::Dollar, Token::Character('$')) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Macro {
+    parameters: u8,
+    body: Vec<Token>,
+}
+
+struct Parser {
+    /// The remaining input in reverse order, so that the next token can be popped off the end.
+    tokens: Vec<(Token, usize)>,
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+    context: ParseContext,
+    end_position: usize,
+}
+
+fn with_schema(schema: SchemaAttributes) -> Attributes {
+    Attributes {
+        schema,
+        ..Default::default()
+    }
+}
+
+/// Returns the attributes of the `mstyle` element corresponding to style switches like
+/// `\displaystyle` or size switches like `\large`.
+fn style_switch(name: &str) -> Option<SchemaAttributes> {
+    let (display_style, script_level) = match name {
+        "displaystyle" => (true, 0),
+        "textstyle" => (false, 0),
+        "scriptstyle" => (false, 1),
+        "scriptscriptstyle" => (false, 2),
+        _ => {
+            // the sizes of LaTeX for a document in 10pt, relative to the surrounding size
+            let percent = match name {
+                "tiny" => 50.0,
+                "scriptsize" => 70.0,
+                "footnotesize" => 80.0,
+                "small" => 90.0,
+                "normalsize" => 100.0,
+                "large" => 120.0,
+                "Large" => 144.0,
+                "LARGE" => 172.8,
+                "huge" => 207.4,
+                "Huge" => 248.8,
+                _ => return None,
+            };
+            return Some(SchemaAttributes {
+                math_size: Some(Length::new(percent, LengthUnit::Percent)),
+                ..Default::default()
+            });
+        }
+    };
+    Some(SchemaAttributes {
+        display_style: Some(display_style),
+        script_level: Some(ScriptLevel::Absolute(script_level)),
+        ..Default::default()
+    })
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Parser> {
+        let mut tokens = lexer::tokenize(source)?;
+        tokens.reverse();
+        Ok(Parser {
+            tokens,
+            macros: HashMap::new(),
+            expansions: 0,
+            context: ParseContext::default(),
+            end_position: source.len(),
+        })
+    }
+
+    fn error<T, S: ToString>(&self, position: usize, message: S) -> Result<T> {
+        Err(ParsingError {
+            position: Some(position),
+            error_type: ErrorType::OtherError(message.to_string()),
+        })
+    }
+
+    fn end_of_input<T>(&self) -> Result<T> {
+        Err(ParsingError {
+            position: Some(self.end_position),
+            error_type: ErrorType::UnexpectedEndOfInput,
+        })
+    }
+
+    /// Returns the next token without expanding macros.
+    fn next_raw(&mut self) -> Result<(Token, usize)> {
+        match self.tokens.pop() {
+            Some(token) => Ok(token),
+            None => self.end_of_input(),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(&(Token::Space, _)) = self.tokens.last() {
+            self.tokens.pop();
+        }
+    }
+
+    /// Skips spaces and expands all macros at the front of the input.
+    fn expand(&mut self) -> Result<()> {
+        loop {
+            self.skip_spaces();
+            let (name, position) = match self.tokens.last() {
+                Some((Token::Command(name), position)) if self.macros.contains_key(name) => {
+                    (name.clone(), *position)
+                }
+                _ => return Ok(()),
+            };
+            self.tokens.pop();
+            self.expansions += 1;
+            if self.expansions > MAX_MACRO_EXPANSIONS {
+                return self.error(
+                    position,
+                    format!("Too many macro expansions in \"\\{}\".", name),
+                );
+            }
+
+            let definition = self.macros[&name].clone();
+            let mut arguments = Vec::with_capacity(definition.parameters as usize);
+            for _ in 0..definition.parameters {
+                arguments.push(self.read_argument_tokens()?);
+            }
+            let length: usize = definition
+                .body
+                .iter()
+                .map(|token| match *token {
+                    Token::Parameter(index) => arguments[index as usize - 1].len(),
+                    _ => 1,
+                })
+                .sum();
+            if self.tokens.len() + length > MAX_EXPANDED_TOKENS {
+                return self.error(
+                    position,
+                    format!("The expansion of \"\\{}\" is too long.", name),
+                );
+            }
+            // the tokens of the expansion are reported at the position of the macro
+            let expansion = definition
+                .body
+                .iter()
+                .flat_map(|token| match *token {
+                    Token::Parameter(index) => arguments[index as usize - 1].clone(),
+                    ref token => vec![token.clone()],
+                })
+                .map(|token| (token, position))
+                .collect::<Vec<_>>();
+            self.tokens.extend(expansion.into_iter().rev());
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<Token>> {
+        self.expand()?;
+        Ok(self.tokens.last().map(|(token, _)| token.clone()))
+    }
+
+    fn next(&mut self) -> Result<(Token, usize)> {
+        self.expand()?;
+        self.next_raw()
+    }
+
+    /// Reads the unexpanded tokens of a macro argument: either a single token or the contents of a
+    /// group.
+    fn read_argument_tokens(&mut self) -> Result<Vec<Token>> {
+        self.skip_spaces();
+        let (token, position) = self.next_raw()?;
+        match token {
+            Token::BeginGroup => {
+                let mut depth = 0;
+                let mut tokens = Vec::new();
+                loop {
+                    let (token, _) = self.next_raw()?;
+                    match token {
+                        Token::BeginGroup => depth += 1,
+                        Token::EndGroup if depth == 0 => return Ok(tokens),
+                        Token::EndGroup => depth -= 1,
+                        _ => {}
+                    }
+                    tokens.push(token);
+                }
+            }
+            Token::EndGroup => self.error(position, "Missing argument."),
+            token => Ok(vec![token]),
+        }
+    }
+
+    /// Handles `\newcommand{\name}[parameters]{body}` and `\renewcommand`.
+    fn define_macro(&mut self, command: &str, position: usize) -> Result<()> {
+        let name = match &self.read_argument_tokens()?[..] {
+            [Token::Command(name)] => name.clone(),
+            _ => {
+                return self.error(
+                    position,
+                    format!("\"\\{}\" has to be followed by a command name.", command),
+                )
+            }
+        };
+        let is_defined = self.macros.contains_key(&name) || commands::is_builtin(&name);
+        if command == "newcommand" && is_defined {
+            return self.error(position, format!("\"\\{}\" is already defined.", name));
+        } else if command == "renewcommand" && !is_defined {
+            return self.error(position, format!("\"\\{}\" is not defined.", name));
+        }
+
+        let mut parameters = 0;
+        self.skip_spaces();
+        if let Some(&(Token::Character('['), _)) = self.tokens.last() {
+            self.tokens.pop();
+            let mut number = String::new();
+            loop {
+                match self.next_raw()? {
+                    (Token::Character(']'), _) => break,
+                    (Token::Character(chr), _) => number.push(chr),
+                    (Token::Space, _) => {}
+                    (_, position) => return self.error(position, "Invalid number of parameters."),
+                }
+            }
+            parameters = match number.parse() {
+                Ok(number) if number <= 9 => number,
+                _ => return self.error(position, "Invalid number of parameters."),
+            };
+        }
+
+        let body = self.read_argument_tokens()?;
+        let undefined_parameter = body.iter().any(|token| match *token {
+            Token::Parameter(index) => index > parameters,
+            _ => false,
+        });
+        if undefined_parameter {
+            return self.error(
+                position,
+                format!("\"\\{}\" uses a parameter it does not declare.", name),
+            );
+        }
+        self.macros.insert(name, Macro { parameters, body });
+        Ok(())
+    }
+
+    fn parse_list(&mut self, terminator: Terminator) -> Result<Vec<MathExpression>> {
+        let mut list = Vec::new();
+        loop {
+            let token = match self.peek()? {
+                Some(token) => token,
+                None if terminator == Terminator::EndOfInput => return Ok(list),
+                None => return self.end_of_input(),
+            };
+            if terminator.matches(&token) {
+                return Ok(list);
+            }
+            match token {
+                Token::Command(ref name) if name == "newcommand" || name == "renewcommand" => {
+                    let (_, position) = self.next_raw()?;
+                    self.define_macro(name, position)?;
+                }
+                Token::Command(ref name) if name == "middle" && terminator == Terminator::Right => {
+                    self.next_raw()?;
+                    list.extend(self.parse_delimiter(Form::Infix)?);
+                }
+                Token::Command(ref name) if style_switch(name).is_some() => {
+                    // style switches apply to the remainder of the current list
+                    self.next_raw()?;
+                    let rest = self.parse_list(terminator)?;
+                    let attributes = with_schema(style_switch(name).unwrap());
+                    list.push(self.context.schema("mstyle", attributes, rest)?);
+                    return Ok(list);
+                }
+                _ => list.push(self.parse_scripted()?),
+            }
+        }
+    }
+
+    /// Parses an expression together with its sub- and superscripts.
+    fn parse_scripted(&mut self) -> Result<MathExpression> {
+        let nucleus = match self.peek()? {
+            Some(Token::Superscript) | Some(Token::Subscript) => {
+                self.context
+                    .schema("mrow", Attributes::default(), Vec::new())?
+            }
+            _ => self.parse_primary()?,
+        };
+
+        let mut limits = None;
+        while let Some(Token::Command(name)) = self.peek()? {
+            if name != "limits" && name != "nolimits" {
+                break;
+            }
+            let (_, position) = self.next_raw()?;
+            if self.context.operator_attrs(&nucleus).is_none() {
+                return self.error(position, "Limit controls must follow a math operator.");
+            }
+            limits = Some(name == "limits");
+        }
+
+        let mut subscript = None;
+        let mut superscript = None;
+        let mut primes = String::new();
+        loop {
+            let is_subscript = match self.peek()? {
+                Some(Token::Subscript) => true,
+                Some(Token::Superscript) => false,
+                Some(Token::Character('\'')) => {
+                    let (_, position) = self.next_raw()?;
+                    if superscript.is_some() {
+                        return self.error(position, "Double superscript.");
+                    }
+                    primes.push('′');
+                    continue;
+                }
+                _ => break,
+            };
+            let (_, position) = self.next_raw()?;
+            let script = if is_subscript {
+                &mut subscript
+            } else {
+                &mut superscript
+            };
+            if script.is_some() {
+                let message = if is_subscript {
+                    "Double subscript."
+                } else {
+                    "Double superscript."
+                };
+                return self.error(position, message);
+            }
+            *script = Some(self.parse_argument()?);
+        }
+        if !primes.is_empty() {
+            // primes are part of the superscript
+            let primes = self
+                .context
+                .operator(&primes, OperatorAttributes::default())?;
+            superscript = Some(match superscript {
+                Some(superscript) => {
+                    self.context
+                        .schema("mrow", Attributes::default(), vec![primes, superscript])?
+                }
+                None => primes,
+            });
+        }
+
+        if subscript.is_none() && superscript.is_none() {
+            return Ok(nucleus);
+        }
+        let as_limits = limits.unwrap_or_else(|| self.has_movable_limits(&nucleus));
+        if limits == Some(true) {
+            // `\limits` keeps the scripts above and below even in inline style
+            if let Some(attributes) = self.context.operator_attrs_mut(&nucleus) {
+                attributes.set_user_override(Flags::MOVABLE_LIMITS, false);
+            }
+        }
+        let (identifier, arguments) = match (subscript, superscript) {
+            (Some(sub), None) => (
+                if as_limits { "munder" } else { "msub" },
+                vec![nucleus, sub],
+            ),
+            (None, Some(sup)) => (if as_limits { "mover" } else { "msup" }, vec![nucleus, sup]),
+            (Some(sub), Some(sup)) => (
+                if as_limits { "munderover" } else { "msubsup" },
+                vec![nucleus, sub, sup],
+            ),
+            (None, None) => unreachable!(),
+        };
+        self.context
+            .schema(identifier, Attributes::default(), arguments)
+    }
+
+    /// Returns true if the scripts of `expr` are placed above and below it in display style.
+    fn has_movable_limits(&self, expr: &MathExpression) -> bool {
+        let attributes = match self.context.operator_attrs(expr) {
+            Some(attributes) => attributes,
+            None => return false,
+        };
+        if attributes.user_overrides.contains(Flags::MOVABLE_LIMITS) {
+            return attributes.flags.contains(Flags::MOVABLE_LIMITS);
+        }
+        attributes
+            .character
+            .and_then(|chr| find_entry(chr, Form::Prefix))
+            .map(|entry| entry.flags.contains(Flags::MOVABLE_LIMITS))
+            .unwrap_or(false)
+    }
+
+    /// Parses the argument of a command or a script: a group or a single token.
+    fn parse_argument(&mut self) -> Result<MathExpression> {
+        match self.peek()? {
+            Some(Token::Character(chr)) => {
+                self.next_raw()?;
+                self.character(chr)
+            }
+            Some(_) => self.parse_primary(),
+            None => self.end_of_input(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<MathExpression> {
+        let (token, position) = self.next()?;
+        match token {
+            Token::BeginGroup => {
+                let list = self.parse_list(Terminator::EndGroup)?;
+                self.next_raw()?;
+                self.context.schema("mrow", Attributes::default(), list)
+            }
+            Token::Character(chr) if chr.is_ascii_digit() => {
+                let mut number = chr.to_string();
+                while let Some(&(Token::Character(chr), _)) = self.tokens.last() {
+                    let is_decimal_point = chr == '.'
+                        && !number.contains('.')
+                        && match self.tokens.len().checked_sub(2).map(|i| &self.tokens[i]) {
+                            Some(&(Token::Character(next), _)) => next.is_ascii_digit(),
+                            _ => false,
+                        };
+                    if !chr.is_ascii_digit() && !is_decimal_point {
+                        break;
+                    }
+                    number.push(chr);
+                    self.tokens.pop();
+                }
+                self.context.number(&number)
+            }
+            Token::Character(chr) => self.character(chr),
+            Token::Command(name) => self.parse_command(&name, position),
+            Token::EndGroup => self.error(position, "Unmatched \"}\"."),
+            Token::Superscript | Token::Subscript => self.error(position, "Missing script."),
+            Token::Alignment => self.error(position, "\"&\" is only allowed in environments."),
+            Token::Parameter(_) => self.error(
+                position,
+                "Parameters are only allowed in macro definitions.",
+            ),
+            Token::Space => unreachable!("spaces are skipped"),
+        }
+    }
+
+    fn parse_command(&mut self, name: &str, position: usize) -> Result<MathExpression> {
+        if let Some(symbol) = commands::symbol(name) {
+            return match symbol {
+                Symbol::Identifier(chr) => self.context.identifier(&chr.to_string(), None),
+                Symbol::UprightIdentifier(chr) => self
+                    .context
+                    .identifier(&chr.to_string(), Some(Family::Normal)),
+                Symbol::Operator(chr) => self.ordinary_operator(chr),
+                Symbol::Function { name, limits } => self.context.function(name, limits),
+                Symbol::Space(width) => self.context.space(Length::em(width)),
+            };
+        }
+        if let Some(family) = commands::math_alphabet(name) {
+            let inherited_math_variant = self.context.math_variant;
+            self.context.math_variant = Some(family);
+            let result = self.parse_argument();
+            self.context.math_variant = inherited_math_variant;
+            return result;
+        }
+        if let Some((accent, is_stretchy)) = commands::accent(name) {
+            let base = self.parse_argument()?;
+            let mut attributes = OperatorAttributes::default();
+            attributes.set_user_override(Flags::STRETCHY, is_stretchy);
+            let accent = self.context.operator(&accent.to_string(), attributes)?;
+            let schema = SchemaAttributes {
+                accent: true,
+                ..Default::default()
+            };
+            return self
+                .context
+                .schema("mover", with_schema(schema), vec![base, accent]);
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "binom" => {
+                let numerator = self.parse_argument()?;
+                let denominator = self.parse_argument()?;
+                let mut schema = SchemaAttributes::default();
+                if name == "binom" {
+                    schema.line_thickness = Some(Length::em(0.0));
+                }
+                let fraction = self.context.schema(
+                    "mfrac",
+                    with_schema(schema),
+                    vec![numerator, denominator],
+                )?;
+                match name {
+                    "dfrac" => {
+                        let style = with_schema(style_switch("displaystyle").unwrap());
+                        self.context.schema("mstyle", style, vec![fraction])
+                    }
+                    "tfrac" => {
+                        let style = with_schema(style_switch("textstyle").unwrap());
+                        self.context.schema("mstyle", style, vec![fraction])
+                    }
+                    "binom" => {
+                        let open = self.fence('(', Form::Prefix)?;
+                        let close = self.fence(')', Form::Postfix)?;
+                        self.context.schema(
+                            "mrow",
+                            Attributes::default(),
+                            vec![open, fraction, close],
+                        )
+                    }
+                    _ => Ok(fraction),
+                }
+            }
+            "sqrt" => {
+                let degree = if self.peek()? == Some(Token::Character('[')) {
+                    self.next_raw()?;
+                    let degree = self.parse_list(Terminator::Bracket)?;
+                    self.next_raw()?;
+                    Some(self.context.schema("mrow", Attributes::default(), degree)?)
+                } else {
+                    None
+                };
+                let radicand = self.parse_argument()?;
+                match degree {
+                    Some(degree) => {
+                        self.context
+                            .schema("mroot", Attributes::default(), vec![radicand, degree])
+                    }
+                    None => self
+                        .context
+                        .schema("msqrt", Attributes::default(), vec![radicand]),
+                }
+            }
+            "left" => {
+                let mut list = Vec::new();
+                list.extend(self.parse_delimiter(Form::Prefix)?);
+                list.extend(self.parse_list(Terminator::Right)?);
+                self.next_raw()?;
+                list.extend(self.parse_delimiter(Form::Postfix)?);
+                self.context.schema("mrow", Attributes::default(), list)
+            }
+            "text" | "mbox" => {
+                let mut list = Vec::new();
+                let text = self.read_text(Some(&mut list))?;
+                if list.is_empty() {
+                    return self
+                        .context
+                        .token("mtext", text, TokenAttributes::default());
+                }
+                if !text.is_empty() {
+                    list.push(
+                        self.context
+                            .token("mtext", text, TokenAttributes::default())?,
+                    );
+                }
+                self.context.schema("mrow", Attributes::default(), list)
+            }
+            "operatorname" => {
+                let text = self.read_text(None)?;
+                self.context.function(&text, false)
+            }
+            "overline" | "underline" => {
+                let content = self.parse_argument()?;
+                let notation = if name == "overline" {
+                    Notation::Top
+                } else {
+                    Notation::Bottom
+                };
+                let schema = SchemaAttributes {
+                    notation: Some(vec![notation]),
+                    ..Default::default()
+                };
+                self.context
+                    .schema("menclose", with_schema(schema), vec![content])
+            }
+            "begin" => self.parse_environment(position),
+            "newcommand" | "renewcommand" => {
+                self.define_macro(name, position)?;
+                self.parse_primary()
+            }
+            "right" => self.error(position, "\"\\right\" without matching \"\\left\"."),
+            "middle" => self.error(
+                position,
+                "\"\\middle\" outside of \"\\left\" and \"\\right\".",
+            ),
+            "end" | "\\" => self.error(
+                position,
+                format!("\"\\{}\" is only allowed in environments.", name),
+            ),
+            "limits" | "nolimits" => {
+                self.error(position, "Limit controls must follow a math operator.")
+            }
+            _ if style_switch(name).is_some() => {
+                self.error(position, format!("\"\\{}\" is not allowed here.", name))
+            }
+            _ => Err(ParsingError {
+                position: Some(position),
+                error_type: ErrorType::UnknownCommand(name.to_owned()),
+            }),
+        }
+    }
+
+    /// Parses the delimiter following `\left`, `\middle` or `\right`. The empty delimiter `.`
+    /// yields `None`.
+    fn parse_delimiter(&mut self, form: Form) -> Result<Option<MathExpression>> {
+        let (token, position) = self.next()?;
+        let delimiter = match token {
+            Token::Character('.') => return Ok(None),
+            Token::Character('<') => '⟨',
+            Token::Character('>') => '⟩',
+            Token::Character(chr) if "()[]|/".contains(chr) => chr,
+            Token::Command(ref name) => match commands::symbol(name) {
+                Some(Symbol::Operator(chr)) if "{}⟨⟩⌊⌋⌈⌉|‖∖↑↓⇑⇓".contains(chr) => {
+                    chr
+                }
+                _ => return self.error(position, "Missing delimiter."),
+            },
+            _ => return self.error(position, "Missing delimiter."),
+        };
+        self.fence(delimiter, form).map(Some)
+    }
+
+    /// Reads the argument of commands like `\text` as plain text, keeping its spaces.
+    ///
+    /// If `formulas` is given, `$` switches back to math mode: the text in front of each formula
+    /// and the formula are added to `formulas`, and the text after the last formula is returned.
+    fn read_text(&mut self, mut formulas: Option<&mut Vec<MathExpression>>) -> Result<String> {
+        self.skip_spaces();
+        match self.next_raw()? {
+            (Token::BeginGroup, _) => {}
+            (Token::Character(chr), _) => return Ok(chr.to_string()),
+            (_, position) => return self.error(position, "Missing text argument."),
+        }
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            let (token, position) = self.next_raw()?;
+            match token {
+                Token::Character('$') => match formulas {
+                    Some(ref mut formulas) => {
+                        if !text.is_empty() {
+                            let text = std::mem::take(&mut text);
+                            let attributes = TokenAttributes::default();
+                            formulas.push(self.context.token("mtext", text, attributes)?);
+                        }
+                        let list = self.parse_list(Terminator::Dollar)?;
+                        self.next_raw()?;
+                        let formula = self.context.schema("mrow", Attributes::default(), list)?;
+                        formulas.push(formula);
+                    }
+                    None => return self.error(position, "Math mode is not allowed here."),
+                },
+                Token::Character(chr) => text.push(chr),
+                Token::Space => text.push(' '),
+                Token::BeginGroup => depth += 1,
+                Token::EndGroup if depth == 0 => return Ok(text),
+                Token::EndGroup => depth -= 1,
+                Token::Command(ref name) if "{}$%&#_ ".contains(name.as_str()) => {
+                    text.push_str(name)
+                }
+                Token::Command(name) => {
+                    return Err(ParsingError {
+                        position: Some(position),
+                        error_type: ErrorType::UnknownCommand(name),
+                    })
+                }
+                _ => return self.error(position, "Unexpected character in text."),
+            }
+        }
+    }
+
+    fn parse_environment(&mut self, position: usize) -> Result<MathExpression> {
+        let name = self.read_text(None)?;
+        let (open, close) = match name.as_str() {
+            "matrix" => (None, None),
+            "pmatrix" => (Some('('), Some(')')),
+            "bmatrix" => (Some('['), Some(']')),
+            "Bmatrix" => (Some('{'), Some('}')),
+            "vmatrix" => (Some('|'), Some('|')),
+            "Vmatrix" => (Some('‖'), Some('‖')),
+            "cases" => (Some('{'), None),
+            _ => return self.error(position, format!("Unknown environment \"{}\".", name)),
+        };
+
+        let mut table = Table::default();
+        if name == "cases" {
+            table.column_align = vec![HorizontalAlign::Left];
+        }
+        table.rows = self.parse_table_rows(&name)?;
+        let table = MathExpression::new(MathItem::Table(table), self.context.new_user_data());
+        if open.is_none() && close.is_none() {
+            return Ok(table);
+        }
+
+        let mut list = Vec::new();
+        if let Some(open) = open {
+            list.push(self.fence(open, Form::Prefix)?);
+        }
+        list.push(table);
+        if let Some(close) = close {
+            list.push(self.fence(close, Form::Postfix)?);
+        }
+        self.context.schema("mrow", Attributes::default(), list)
+    }
+
+    fn parse_table_rows(&mut self, environment: &str) -> Result<Vec<TableRow>> {
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            let content = self.parse_list(Terminator::Cell)?;
+            let is_empty = content.is_empty();
+            let cell = TableCell {
+                content: Some(
+                    self.context
+                        .schema("mrow", Attributes::default(), content)?,
+                ),
+                ..Default::default()
+            };
+            match self.next_raw()? {
+                (Token::Alignment, _) => cells.push(cell),
+                (Token::Command(ref name), _) if name == "\\" => {
+                    cells.push(cell);
+                    rows.push(TableRow {
+                        cells: std::mem::take(&mut cells),
+                        ..Default::default()
+                    });
+                }
+                (_, position) => {
+                    let end = self.read_text(None)?;
+                    if end != environment {
+                        return self.error(
+                            position,
+                            format!("\"{}\" environment ended by \"{}\".", environment, end),
+                        );
+                    }
+                    // a line break before the end of the environment does not start a new row
+                    if !is_empty || !cells.is_empty() || rows.is_empty() {
+                        cells.push(cell);
+                        rows.push(TableRow {
+                            cells,
+                            ..Default::default()
+                        });
+                    }
+                    return Ok(rows);
+                }
+            }
+        }
+    }
+
+    fn character(&mut self, chr: char) -> Result<MathExpression> {
+        match chr {
+            _ if chr.is_alphabetic() => self.context.identifier(&chr.to_string(), None),
+            _ if chr.is_numeric() => self.context.number(&chr.to_string()),
+            '~' => self.context.space(Length::em(1.0 / 3.0)),
+            '-' => self.ordinary_operator('−'),
+            '*' => self.ordinary_operator('∗'),
+            '\'' => self.ordinary_operator('′'),
+            _ => self.ordinary_operator(chr),
+        }
+    }
+
+    /// Creates an operator that is not part of `\left ... \right`. Unlike in MathML such fences
+    /// keep their natural size.
+    fn ordinary_operator(&mut self, chr: char) -> Result<MathExpression> {
+        let mut attributes = OperatorAttributes::default();
+        let is_fence = [Form::Prefix, Form::Infix, Form::Postfix]
+            .iter()
+            .any(|&form| {
+                find_entry(chr, form)
+                    .map(|entry| entry.flags.contains(Flags::FENCE))
+                    .unwrap_or(false)
+            });
+        if is_fence {
+            attributes.set_user_override(Flags::STRETCHY, false);
+        }
+        self.context.operator(&chr.to_string(), attributes)
+    }
+
+    /// Creates a delimiter that stretches to the height of the surrounding list.
+    fn fence(&mut self, chr: char, form: Form) -> Result<MathExpression> {
+        let mut attributes = OperatorAttributes {
+            form: Some(form),
+            ..Default::default()
+        };
+        attributes.set_user_override(Flags::FENCE, true);
+        attributes.set_user_override(Flags::STRETCHY, true);
+        attributes.set_user_override(Flags::SYMMETRIC, true);
+        self.context.operator(&chr.to_string(), attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::*;
+
+    fn single(expr: MathExpression) -> MathExpression {
+        match *expr.item {
+            MathItem::List(mut list) => {
+                assert_eq!(list.len(), 1, "Expected a single expression.");
+                list.pop().unwrap()
+            }
+            _ => expr,
+        }
+    }
+
+    fn text(expr: &MathExpression) -> &str {
+        match *expr.item {
+            MathItem::Field(Field::Unicode(ref text)) => text,
+            ref other_item => panic!("Expected Field. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_fraction_and_scripts() {
+        let expr = single(parse(r"\frac{a}{x^2_i}").unwrap());
+        let denominator = match *expr.item {
+            MathItem::GeneralizedFraction(GeneralizedFraction {
+                denominator: Some(ref denominator),
+                ..
+            }) => denominator,
+            ref other_item => panic!("Expected fraction. Found {:?}", other_item),
+        };
+        match *denominator.item {
+            MathItem::Atom(Atom {
+                top_right: Some(ref sup),
+                bottom_right: Some(ref sub),
+                ..
+            }) => {
+                assert_eq!(text(sup), "2");
+                assert_eq!(text(sub), "𝑖");
+            }
+            ref other_item => panic!("Expected atom. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let expr = single(parse(r"\sum\limits_{i=1}^n").unwrap());
+        match *expr.item {
+            MathItem::OverUnder(ref over_under) => assert!(!over_under.is_limits),
+            ref other_item => panic!("Expected OverUnder. Found {:?}", other_item),
+        }
+        let expr = single(parse(r"\sum_{i=1}^n").unwrap());
+        match *expr.item {
+            MathItem::OverUnder(ref over_under) => assert!(over_under.is_limits),
+            ref other_item => panic!("Expected OverUnder. Found {:?}", other_item),
+        }
+        let expr = single(parse(r"\int_0^1").unwrap());
+        match *expr.item {
+            MathItem::Atom(_) => {}
+            ref other_item => panic!("Expected Atom. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_stretchy_fences() {
+        let is_stretchy = |expr: &MathExpression| match *expr.item {
+            MathItem::Operator(ref operator) => operator.stretch_constraints.is_some(),
+            ref other_item => panic!("Expected Operator. Found {:?}", other_item),
+        };
+        match *parse(r"\left( x \middle| y \right.").unwrap().item {
+            MathItem::List(ref list) => {
+                assert_eq!(list.len(), 4);
+                assert!(is_stretchy(&list[0]));
+                assert!(is_stretchy(&list[2]));
+            }
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        }
+        match *parse(r"(x)").unwrap().item {
+            MathItem::List(ref list) => assert!(!is_stretchy(&list[0])),
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_sqrt_and_alphabets() {
+        let expr = single(parse(r"\sqrt[3]{\mathbb{R}}").unwrap());
+        match *expr.item {
+            MathItem::Root(Root {
+                radicand: Some(ref radicand),
+                degree: Some(ref degree),
+            }) => {
+                assert_eq!(text(radicand), "ℝ");
+                assert_eq!(text(degree), "3");
+            }
+            ref other_item => panic!("Expected Root. Found {:?}", other_item),
+        }
+        match *parse(r"\text{if $x$}").unwrap().item {
+            MathItem::List(ref list) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(text(&list[0]), "if ");
+                assert_eq!(text(&single(list[1].clone())), "𝑥");
+            }
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        }
+        assert_eq!(text(&single(parse(r"\text{if \$x}").unwrap())), "if $x");
+        assert!(parse(r"\text{if $x}").is_err());
+        assert!(parse(r"\operatorname{$x$}").is_err());
+    }
+
+    #[test]
+    fn test_size_switches() {
+        let expr = parse(r"a \Huge b").unwrap();
+        let list = match *expr.item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        };
+        match *list[1].item {
+            MathItem::Styled(ref styled) => {
+                assert_eq!(
+                    styled.math_size,
+                    Some(Length::new(248.8, LengthUnit::Percent))
+                );
+                assert_eq!(text(styled.content.as_ref().unwrap()), "𝑏");
+            }
+            ref other_item => panic!("Expected Styled. Found {:?}", other_item),
+        }
+    }
+
+    #[test]
+    fn test_matrix() {
+        let expr = parse(r"\begin{pmatrix} a & b \\ c & d \\ \end{pmatrix}").unwrap();
+        let list = match *expr.item {
+            MathItem::List(ref list) => list,
+            ref other_item => panic!("Expected List. Found {:?}", other_item),
+        };
+        assert_eq!(list.len(), 3);
+        match *list[1].item {
+            MathItem::Table(ref table) => {
+                assert_eq!(table.rows.len(), 2);
+                assert!(table.rows.iter().all(|row| row.cells.len() == 2));
+            }
+            ref other_item => panic!("Expected Table. Found {:?}", other_item),
+        }
+        assert!(parse(r"\begin{matrix} a \end{pmatrix}").is_err());
+    }
+
+    #[test]
+    fn test_macros() {
+        let expr = single(parse(r"\newcommand{\inv}[1]{\frac{1}{#1}} \inv{x}").unwrap());
+        match *expr.item {
+            MathItem::GeneralizedFraction(_) => {}
+            ref other_item => panic!("Expected fraction. Found {:?}", other_item),
+        }
+        assert!(parse(r"\newcommand{\frac}{x}").is_err());
+        assert!(parse(r"\renewcommand{\foo}{x}").is_err());
+        assert!(parse(r"\newcommand{\a}{\a} \a").is_err());
+        assert!(parse(r"\newcommand{\a}[1]{\a{#1#1}} \a{x}").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse(r"x + \foo").unwrap_err();
+        assert_eq!(error.position, Some(4));
+        match error.error_type {
+            ErrorType::UnknownCommand(ref name) => assert_eq!(name, "foo"),
+            ref other => panic!("Expected UnknownCommand. Found {:?}", other),
+        }
+        assert!(parse(r"\frac{1}").is_err());
+        assert!(parse(r"x^1^2").is_err());
+        assert!(parse(r"x \right)").is_err());
+        assert!(parse(r"{x").is_err());
+    }
+}
@@ -32,6 +32,37 @@ impl MathExpression {
     pub fn get_user_data(&self) -> u64 {
         self.user_data
     }
+
+    /// Returns the annotations of the first `Semantics` item in this expression, which usually
+    /// describe the whole formula.
+    pub fn annotations(&self) -> &[Annotation] {
+        match *self.item {
+            MathItem::Semantics(ref semantics) => &semantics.annotations,
+            ref item => item
+                .sub_expressions()
+                .into_iter()
+                .map(MathExpression::annotations)
+                .find(|annotations| !annotations.is_empty())
+                .unwrap_or(&[]),
+        }
+    }
+
+    /// Returns the first of the [`annotations`](Self::annotations) with the given encoding, e.g.
+    /// the TeX source of the formula for `application/x-tex`.
+    pub fn annotation(&self, encoding: &str) -> Option<&Annotation> {
+        self.annotations()
+            .iter()
+            .find(|annotation| annotation.encoding.as_deref() == Some(encoding))
+    }
+
+    /// Returns true for spaces and alignment points, which do not keep a row around an operator
+    /// from being an embellished operator.
+    pub fn is_space_like(&self) -> bool {
+        matches!(
+            *self.item,
+            MathItem::Space(_) | MathItem::AlignMark | MathItem::AlignGroup(_)
+        )
+    }
 }
 
 /// A `MathItem` is the abstract representation of mathematical notation that manages the layout
@@ -46,6 +77,9 @@ pub enum MathItem {
     /// An expression that consists of a base (called nucleus) and optionally of attachments at
     /// each corner (e.g. subscripts and superscripts).
     Atom(Atom),
+    /// An expression that consists of a base and any number of pairs of subscripts and
+    /// superscripts before and after the base.
+    MultiScripts(MultiScripts),
     /// An expression that consists of a base and optionally of attachments that go above or below
     /// the nucleus like e.g. accents.
     OverUnder(OverUnder),
@@ -59,10 +93,87 @@ pub enum MathItem {
     Operator(Operator),
     /// A list of math expressions to be laid out sequentially.
     List(Vec<MathExpression>),
+    /// Math expressions arranged in rows and columns.
+    Table(Table),
+    /// An expression with enclosing notations like boxes, circles or strikes.
+    Enclosure(Enclosure),
+    /// An expression with a different style than its surroundings.
+    Styled(Styled),
+    /// An expression with modified dimensions.
+    Padded(Padded),
+    /// An invisible expression.
+    Phantom(Phantom),
+    /// An expression together with descriptions of it in other formats, which are not drawn.
+    Semantics(Semantics),
+    /// An invisible point that is placed below the corresponding points of the other cells in a
+    /// table column or of the other lines of a broken row.
+    AlignMark,
+    /// The start of a part of a table cell that is aligned with the corresponding parts of the
+    /// other cells in its column.
+    AlignGroup(AlignGroup),
     /// Any math expression of another type.
     Other(Arc<dyn MathLayout + Send + Sync>),
 }
 
+impl MathItem {
+    /// Returns the expressions that are directly contained in this item.
+    pub(crate) fn sub_expressions(&self) -> Vec<&MathExpression> {
+        let mut expressions = Vec::new();
+        match *self {
+            MathItem::Atom(ref atom) => expressions.extend(
+                [
+                    &atom.nucleus,
+                    &atom.top_left,
+                    &atom.top_right,
+                    &atom.bottom_left,
+                    &atom.bottom_right,
+                ]
+                .iter()
+                .filter_map(|expr| expr.as_ref()),
+            ),
+            MathItem::MultiScripts(ref scripts) => {
+                expressions.extend(scripts.nucleus.as_ref());
+                for pair in scripts.pre_scripts.iter().chain(&scripts.post_scripts) {
+                    expressions.extend(pair.subscript.as_ref());
+                    expressions.extend(pair.superscript.as_ref());
+                }
+            }
+            MathItem::OverUnder(ref over_under) => expressions.extend(
+                [&over_under.nucleus, &over_under.over, &over_under.under]
+                    .iter()
+                    .filter_map(|expr| expr.as_ref()),
+            ),
+            MathItem::GeneralizedFraction(ref frac) => {
+                expressions.extend(frac.numerator.as_ref());
+                expressions.extend(frac.denominator.as_ref());
+            }
+            MathItem::Root(ref root) => {
+                expressions.extend(root.radicand.as_ref());
+                expressions.extend(root.degree.as_ref());
+            }
+            MathItem::List(ref list) => expressions.extend(list),
+            MathItem::Table(ref table) => {
+                for row in &table.rows {
+                    expressions.extend(row.label.as_ref());
+                    expressions.extend(row.cells.iter().filter_map(|cell| cell.content.as_ref()));
+                }
+            }
+            MathItem::Enclosure(ref enclosure) => expressions.extend(enclosure.content.as_ref()),
+            MathItem::Styled(ref styled) => expressions.extend(styled.content.as_ref()),
+            MathItem::Padded(ref padded) => expressions.extend(padded.content.as_ref()),
+            MathItem::Phantom(ref phantom) => expressions.extend(phantom.content.as_ref()),
+            MathItem::Semantics(ref semantics) => expressions.extend(semantics.content.as_ref()),
+            MathItem::Field(_)
+            | MathItem::Space(_)
+            | MathItem::Operator(_)
+            | MathItem::AlignMark
+            | MathItem::AlignGroup(_)
+            | MathItem::Other(_) => {}
+        }
+        expressions
+    }
+}
+
 impl Default for MathItem {
     fn default() -> MathItem {
         MathItem::Field(Field::Empty)
@@ -89,9 +200,6 @@ pub enum Field {
     /// OpenType.
     Unicode(String),
     /// Represents a specific glyph in the current font.
-    /// 
-    /// *Beware*: This is not yet implemented!
-    // TODO
     Glyph(Glyph),
 }
 impl Default for Field {
@@ -154,6 +262,25 @@ pub struct Atom {
 }
 
 
+/// A subscript and a superscript that are attached to the same side of a base.
+#[derive(Default, Debug, Clone)]
+pub struct ScriptPair {
+    pub subscript: Option<MathExpression>,
+    pub superscript: Option<MathExpression>,
+}
+
+/// An expression that consists of a base (called nucleus) and any number of pairs of subscripts
+/// and superscripts before and after the base (e.g. tensors or isotopes).
+#[derive(Default, Debug, Clone)]
+pub struct MultiScripts {
+    /// The base of the expression.
+    pub nucleus: Option<MathExpression>,
+    /// The script pairs in front of the nucleus from left to right.
+    pub pre_scripts: Vec<ScriptPair>,
+    /// The script pairs after the nucleus from left to right.
+    pub post_scripts: Vec<ScriptPair>,
+}
+
 /// An expression that consists of a base (called nucleus) and attachments that go above or below
 /// the nucleus like e.g. accents.
 #[derive(Debug, Default, Clone)]
@@ -190,7 +317,14 @@ pub struct GeneralizedFraction {
     pub denominator: Option<MathExpression>,
     /// Thickness of the fraction line. If this is zero the fraction is drawn as a stack. If
     /// thickness is None the default fraction thickness is used.
-    pub thickness: Option<MathExpression>,
+    pub thickness: Option<Length>,
+    /// Horizontal alignment of the numerator if it is narrower than the denominator.
+    pub numerator_align: HorizontalAlign,
+    /// Horizontal alignment of the denominator if it is narrower than the numerator.
+    pub denominator_align: HorizontalAlign,
+    /// Draw the fraction skewed, with the numerator to the upper left and the denominator to the
+    /// lower right of a slanted line.
+    pub is_bevelled: bool,
 }
 
 /// An expression consisting of a radical symbol encapsulating the radicand and an optional degree
@@ -203,6 +337,313 @@ pub struct Root {
     pub degree: Option<MathExpression>,
 }
 
+/// Horizontal alignment of an expression inside the space that is available to it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+// the `Default` impls of enums are written by hand, `#[default]` needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for HorizontalAlign {
+    fn default() -> HorizontalAlign {
+        HorizontalAlign::Center
+    }
+}
+
+/// Vertical alignment of the cells inside of a table row.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VerticalAlign {
+    /// The top of the cell is aligned with the top of the row.
+    Top,
+    /// The bottom of the cell is aligned with the bottom of the row.
+    Bottom,
+    /// The cell is centered between the top and the bottom of the row.
+    Center,
+    /// The baseline of the cell is aligned with the baseline of the row.
+    Baseline,
+    /// The math axis of the cell is aligned with the math axis of the row.
+    Axis,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for VerticalAlign {
+    fn default() -> VerticalAlign {
+        VerticalAlign::Baseline
+    }
+}
+
+/// The style of the lines that separate the rows and columns of a table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineStyle {
+    None,
+    Solid,
+    Dashed,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for LineStyle {
+    fn default() -> LineStyle {
+        LineStyle::None
+    }
+}
+
+/// A table of math expressions arranged in rows and columns (e.g. a matrix).
+///
+/// Attributes that can be specified for every row or column are given as lists. If a list has
+/// fewer entries than there are rows or columns, its last entry is used for all remaining ones.
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// The rows of the table from top to bottom.
+    pub rows: Vec<TableRow>,
+    /// Vertical alignment of the cells in each row.
+    pub row_align: Vec<VerticalAlign>,
+    /// Horizontal alignment of the cells in each column.
+    pub column_align: Vec<HorizontalAlign>,
+    /// Vertical space between consecutive rows.
+    pub row_spacing: Vec<Length>,
+    /// Horizontal space between consecutive columns.
+    pub column_spacing: Vec<Length>,
+    /// Lines drawn between consecutive rows.
+    pub row_lines: Vec<LineStyle>,
+    /// Lines drawn between consecutive columns.
+    pub column_lines: Vec<LineStyle>,
+    /// The line drawn around the whole table.
+    pub frame: LineStyle,
+    /// The horizontal and vertical space between the frame and the cells. This is only used if
+    /// the table has a frame.
+    pub frame_spacing: (Length, Length),
+    /// Horizontal alignment of the alignment groups in each column. Groups without an entry are
+    /// aligned to the left.
+    pub group_align: Vec<Vec<HorizontalAlign>>,
+}
+
+impl Default for Table {
+    fn default() -> Table {
+        // A spacing of 1ex is approximated by half an em.
+        Table {
+            rows: Vec::new(),
+            row_align: vec![VerticalAlign::Baseline],
+            column_align: vec![HorizontalAlign::Center],
+            row_spacing: vec![Length::em(0.5)],
+            column_spacing: vec![Length::em(0.8)],
+            row_lines: vec![LineStyle::None],
+            column_lines: vec![LineStyle::None],
+            frame: LineStyle::None,
+            frame_spacing: (Length::em(0.4), Length::em(0.25)),
+            group_align: Vec::new(),
+        }
+    }
+}
+
+/// A single row of a `Table`.
+#[derive(Debug, Default, Clone)]
+pub struct TableRow {
+    /// The cells of the row from left to right.
+    pub cells: Vec<TableCell>,
+    /// An optional label (e.g. an equation number) that is displayed next to the row.
+    pub label: Option<MathExpression>,
+    /// Overrides the vertical alignment of the table for this row.
+    pub row_align: Option<VerticalAlign>,
+    /// Overrides the horizontal alignment of the table's columns for this row.
+    pub column_align: Vec<HorizontalAlign>,
+    /// Overrides the alignment of the groups in the table's columns for this row.
+    pub group_align: Vec<Vec<HorizontalAlign>>,
+}
+
+/// A single cell of a `TableRow`.
+#[derive(Debug, Default, Clone)]
+pub struct TableCell {
+    pub content: Option<MathExpression>,
+    /// Overrides the vertical alignment of the row for this cell.
+    pub row_align: Option<VerticalAlign>,
+    /// Overrides the horizontal alignment of the column for this cell.
+    pub column_align: Option<HorizontalAlign>,
+    /// Overrides the alignment of the groups in the column for this cell.
+    pub group_align: Vec<HorizontalAlign>,
+}
+
+/// The start of an alignment group inside of a table cell.
+///
+/// The n-th groups of all cells in a column are placed below each other. Inside of the space that
+/// is available to it, a group is aligned at its first `MathItem::AlignMark` or, if it has none,
+/// according to its group alignment.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AlignGroup {
+    /// Overrides the group alignment of the table for this group.
+    pub group_align: Option<HorizontalAlign>,
+}
+
+/// A way of drawing an enclosure around an expression.
+///
+/// These are the notations of the MathML `menclose` element.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Notation {
+    /// A long division symbol: a closing parenthesis on the left and a bar on top.
+    LongDiv,
+    /// A bar on the top and on the right (actuarial notation).
+    Actuarial,
+    /// A radical symbol without a degree.
+    Radical,
+    /// A rectangle around the expression.
+    Box,
+    /// A rectangle with rounded corners around the expression.
+    RoundedBox,
+    /// An ellipse around the expression.
+    Circle,
+    /// A line on the left side.
+    Left,
+    /// A line on the right side.
+    Right,
+    /// A line on the top.
+    Top,
+    /// A line on the bottom.
+    Bottom,
+    /// A line from the bottom left to the top right corner.
+    UpDiagonalStrike,
+    /// A line from the top left to the bottom right corner.
+    DownDiagonalStrike,
+    /// A vertical line through the center.
+    VerticalStrike,
+    /// A horizontal line through the center.
+    HorizontalStrike,
+    /// An arrow from the bottom left to the top right corner.
+    UpDiagonalArrow,
+    /// An arrow from the top left to the bottom right corner.
+    DownDiagonalArrow,
+    /// A vertical arrow through the center pointing upwards.
+    UpArrow,
+    /// A vertical arrow through the center pointing downwards.
+    DownArrow,
+    /// A horizontal arrow through the center pointing to the left.
+    LeftArrow,
+    /// A horizontal arrow through the center pointing to the right.
+    RightArrow,
+    /// A bar on the right and on the bottom (the arabic factorial symbol).
+    MAdruwb,
+}
+
+/// An expression surrounded by one or more enclosing notations (e.g. a box or a strike).
+#[derive(Debug, Default, Clone)]
+pub struct Enclosure {
+    /// The expression inside of the enclosure.
+    pub content: Option<MathExpression>,
+    /// The notations that are drawn on top of each other.
+    pub notations: Vec<Notation>,
+}
+
+/// A dimension of the content of a `Padded` expression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PseudoUnit {
+    Width,
+    Height,
+    Depth,
+}
+
+/// The value of a dimension of a `Padded` expression.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PaddedValue {
+    Length(Length),
+    /// A multiple of a dimension of the content.
+    Dimension(f32, PseudoUnit),
+}
+
+/// A length that can refer to the dimensions of the content of a `Padded` expression.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PaddedLength {
+    /// If `true` the value is added to the original value of the dimension instead of replacing
+    /// it.
+    pub is_increment: bool,
+    pub value: PaddedValue,
+}
+
+/// An expression with modified spacing around it.
+///
+/// Every dimension that is `None` keeps the value of the content.
+#[derive(Debug, Default, Clone)]
+pub struct Padded {
+    pub content: Option<MathExpression>,
+    /// The advance width of the expression.
+    pub width: Option<PaddedLength>,
+    /// The ascent of the expression.
+    pub height: Option<PaddedLength>,
+    /// The descent of the expression.
+    pub depth: Option<PaddedLength>,
+    /// The horizontal offset of the content.
+    pub lspace: Option<PaddedLength>,
+    /// The vertical offset of the content. Positive values move the content up.
+    pub voffset: Option<PaddedLength>,
+}
+
+/// An expression that takes up the space of its content without drawing it.
+#[derive(Debug, Default, Clone)]
+pub struct Phantom {
+    pub content: Option<MathExpression>,
+}
+
+/// An expression with annotations, like the TeX source it has been converted from. Only the
+/// content is laid out.
+#[derive(Debug, Default, Clone)]
+pub struct Semantics {
+    pub content: Option<MathExpression>,
+    pub annotations: Vec<Annotation>,
+}
+
+/// A description of an expression in another format.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// The format of the annotation, e.g. `application/x-tex` for TeX source.
+    pub encoding: Option<String>,
+    /// The text of the annotation. For XML annotations this is the XML source of the content.
+    pub content: String,
+    /// `true` if the content is XML, as for the `annotation-xml` elements of MathML.
+    pub is_xml: bool,
+}
+
+/// A change of the script level of an expression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptLevel {
+    /// Sets the script level to the given value.
+    Absolute(u8),
+    /// Increments (or decrements if negative) the script level by the given value.
+    Relative(i8),
+}
+
+impl ScriptLevel {
+    /// Returns the script level that results from applying `self` to `script_level`.
+    pub fn apply(self, script_level: u8) -> u8 {
+        match self {
+            ScriptLevel::Absolute(level) => level,
+            ScriptLevel::Relative(change) if change < 0 => {
+                script_level.saturating_sub(change.wrapping_neg() as u8)
+            }
+            ScriptLevel::Relative(change) => script_level.saturating_add(change as u8),
+        }
+    }
+}
+
+/// An expression that is laid out with a style that differs from the style of its surroundings.
+#[derive(Debug, Default, Clone)]
+pub struct Styled {
+    pub content: Option<MathExpression>,
+    /// If present, overrides the math style of the content.
+    pub math_style: Option<MathStyle>,
+    /// If present, changes the script level of the content.
+    pub script_level: Option<ScriptLevel>,
+    /// If present, changes the size of the content. Lengths relative to the font, like `em` or
+    /// percentages, are relative to the size of the surroundings.
+    pub math_size: Option<Length>,
+    /// If present, the content is drawn in this colour unless parts of it have their own colour.
+    pub math_color: Option<Color>,
+    /// If present, a rectangle of this colour is drawn behind the content.
+    pub math_background: Option<Color>,
+    /// If present, sets the direction in which the content is laid out. The content is mirrored
+    /// if the direction differs from the direction of the surroundings.
+    pub direction: Option<TextDirection>,
+}
+
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct StretchConstraints {
     pub min_size: Option<Length>,
@@ -210,13 +651,81 @@ pub struct StretchConstraints {
     pub symmetric: bool,
 }
 
+/// Whether a formula that is too wide for a single line may be broken at an operator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineBreak {
+    /// The layout decides whether to break at the operator.
+    Auto,
+    /// Always start a new line at the operator.
+    NewLine,
+    /// Never break at the operator.
+    NoBreak,
+    /// Prefer a break at this operator to breaks at other operators.
+    GoodBreak,
+    /// Avoid breaking at this operator if possible.
+    BadBreak,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for LineBreak {
+    fn default() -> LineBreak {
+        LineBreak::Auto
+    }
+}
+
+/// The line on which an operator is placed if a formula is broken at it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineBreakStyle {
+    /// The operator starts the new line.
+    Before,
+    /// The operator ends the old line.
+    After,
+    /// The operator is displayed both at the end of the old and at the start of the new line.
+    Duplicate,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for LineBreakStyle {
+    fn default() -> LineBreakStyle {
+        LineBreakStyle::Before
+    }
+}
+
+/// The classes that TeX distinguishes when it puts space between the atoms of a formula.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtomClass {
+    Ord,
+    Op,
+    Bin,
+    Rel,
+    Open,
+    Close,
+    Punct,
+    Inner,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for AtomClass {
+    fn default() -> AtomClass {
+        AtomClass::Ord
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Operator {
     pub stretch_constraints: Option<StretchConstraints>,
     pub is_large_op: bool,
+    /// The class of the operator in TeX's terms, which determines the space around it with
+    /// `SpacingMode::Tex`.
+    pub atom_class: AtomClass,
     pub leading_space: Length,
     pub trailing_space: Length,
     pub field: Field,
+    pub line_break: LineBreak,
+    pub line_break_style: LineBreakStyle,
+    /// The alignment of the line that starts after a break at this operator. If this is `None`
+    /// the line is aligned to the left.
+    pub indent_align: Option<HorizontalAlign>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -225,8 +734,24 @@ pub enum LengthUnit {
     Point,
     /// Current EM-Size.
     Em,
+    /// The x-height of the font.
+    Ex,
+    /// A CSS pixel, which is 3/4 of a point.
+    Pixel,
+    Inch,
+    Centimeter,
+    Millimeter,
+    /// A math unit is 1/18 em as in TeX. The named spaces of MathML are multiples of it.
+    MathUnit,
+    /// A percentage of the em size.
+    Percent,
     /// The minimum height to display a display operator.
     DisplayOperatorMinHeight,
+    /// The default thickness of a fraction rule.
+    FractionRuleThickness,
+    /// The size of a stretchy operator before it is stretched. This is meant for the `minsize`
+    /// and `maxsize` of operators, other lengths treat it like `Em`.
+    NormalSize,
 }
 
 /// Lengths are specified with a numeric value an a unit.
@@ -262,39 +787,49 @@ impl Default for Length {
     }
 }
 
-/// A type for representing fractional scale values in percent. A value of 100 means original size,
-/// 50 means scaled to half the original size.
+/// A factor by which glyphs are scaled, given in percent. A value of 100 means original size, 50
+/// means scaled to half and 200 to twice the original size.
 ///
 /// # Examples
 /// ```
-/// # use math_render::PercentValue;
-/// let scale = PercentValue::new(50);
+/// # use math_render::ScaleFactor;
+/// let scale = ScaleFactor::new(50);
 /// let num = 300;
 /// assert_eq!(150, num * scale);
+/// assert_eq!(600, num * ScaleFactor::new(200));
 /// ```
-#[derive(Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
-pub struct PercentValue {
-    percent: u8,
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct ScaleFactor {
+    percent: u32,
 }
 
-impl PercentValue {
-    /// Create a new `PercentValue` from an integer between 0 and 100 representing the percentage.
-    pub fn new(value: u8) -> PercentValue {
-        debug_assert!(value <= 100, "Not a valid percent value");
-        // for release builds still make sure that percentage is valid
-        let value = if value > 100 { 100u8 } else { value };
-        PercentValue { percent: value }
+impl ScaleFactor {
+    /// Create a new `ScaleFactor` from an integer representing the percentage.
+    pub fn new(value: u32) -> ScaleFactor {
+        ScaleFactor { percent: value }
+    }
+
+    /// Create a new `ScaleFactor` from a multiplier, which is rounded to whole percents.
+    /// Negative multipliers are treated as zero.
+    ///
+    /// # Examples
+    /// ```
+    /// # use math_render::ScaleFactor;
+    /// assert_eq!(ScaleFactor::new(125), ScaleFactor::from_scale_mult(1.25));
+    /// ```
+    pub fn from_scale_mult(value: f32) -> ScaleFactor {
+        ScaleFactor::new((value * 100f32).round().max(0f32) as u32)
     }
 
     /// Returns the percentage as an unsigned integer.
     ///
     /// # Examples
     /// ```
-    /// # use math_render::PercentValue;
-    /// let percent = PercentValue::new(64);
+    /// # use math_render::ScaleFactor;
+    /// let percent = ScaleFactor::new(64);
     /// assert_eq!( 64, percent.as_percentage() );
     /// ```
-    pub fn as_percentage(self) -> u8 {
+    pub fn as_percentage(self) -> u32 {
         self.percent
     }
 
@@ -303,73 +838,139 @@ impl PercentValue {
     ///
     /// # Examples
     /// ```
-    /// # use math_render::PercentValue;
-    /// let percent = PercentValue::new(50);
+    /// # use math_render::ScaleFactor;
+    /// let percent = ScaleFactor::new(50);
     /// assert_eq!( 0.5f32, percent.as_scale_mult() );
     /// ```
     pub fn as_scale_mult(self) -> f32 {
         (self.percent as f32) / 100f32
     }
+
+    /// Returns the scale factor that scales like this one applied after `other`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use math_render::ScaleFactor;
+    /// let percent = ScaleFactor::new(50).combine(ScaleFactor::new(70));
+    /// assert_eq!( 35, percent.as_percentage() );
+    /// ```
+    pub fn combine(self, other: ScaleFactor) -> ScaleFactor {
+        ScaleFactor::new(self.percent.saturating_mul(other.percent) / 100)
+    }
 }
 
-impl fmt::Debug for PercentValue {
+impl Default for ScaleFactor {
+    /// Returns the factor that keeps the original size.
+    fn default() -> ScaleFactor {
+        ScaleFactor::new(100)
+    }
+}
+
+impl fmt::Debug for ScaleFactor {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?} %", self.percent)
     }
 }
 
 
-impl Mul<i32> for PercentValue {
+impl Mul<i32> for ScaleFactor {
     type Output = i32;
 
     fn mul(self, _rhs: i32) -> i32 {
-        let value = _rhs.saturating_mul(self.percent as i32);
-        value / 100i32
+        let value = i64::from(_rhs) * i64::from(self.percent) / 100;
+        value.max(i64::from(i32::MIN)).min(i64::from(i32::MAX)) as i32
     }
 }
 
-impl Mul<PercentValue> for i32 {
+impl Mul<ScaleFactor> for i32 {
     type Output = i32;
 
-    fn mul(self, _rhs: PercentValue) -> i32 {
+    fn mul(self, _rhs: ScaleFactor) -> i32 {
         _rhs * self
     }
 }
 
-impl Div<PercentValue> for i32 {
+impl Div<ScaleFactor> for i32 {
     type Output = i32;
 
-    fn div(self, _rhs: PercentValue) -> i32 {
-        if _rhs.percent == 100 {
-            self
-        } else {
-            let value = self * 100i32;
-            value / (_rhs.percent as i32)
+    fn div(self, _rhs: ScaleFactor) -> i32 {
+        // a factor of zero cannot be undone, so the value is kept as it is
+        match _rhs.percent {
+            100 | 0 => self,
+            percent => (i64::from(self) * 100 / i64::from(percent)) as i32,
         }
     }
 }
 
-impl Div<PercentValue> for u32 {
+impl Div<ScaleFactor> for u32 {
     type Output = u32;
 
-    fn div(self, _rhs: PercentValue) -> u32 {
-        if _rhs.percent == 100 {
-            self
-        } else {
-            let value = self * 100u32;
-            value / (_rhs.percent as u32)
+    fn div(self, _rhs: ScaleFactor) -> u32 {
+        match _rhs.percent {
+            100 | 0 => self,
+            percent => (u64::from(self) * 100 / u64::from(percent)) as u32,
+        }
+    }
+}
+
+/// A colour in the sRGB colour space together with its opacity.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    /// The opacity of the colour. 0 is fully transparent and 255 is opaque.
+    pub alpha: u8,
+}
+
+impl Color {
+    /// Returns the opaque colour with the given components.
+    pub fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha: 255,
         }
     }
 }
 
+impl fmt::Display for Color {
+    /// Formats the colour in hexadecimal notation like `#ff8000`. The opacity is only appended
+    /// if the colour is not opaque.
+    ///
+    /// # Examples
+    /// ```
+    /// # use math_render::Color;
+    /// assert_eq!(Color::rgb(255, 128, 0).to_string(), "#ff8000");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)?;
+        if self.alpha != 255 {
+            write!(f, "{:02x}", self.alpha)?;
+        }
+        Ok(())
+    }
+}
+
 /// A font-dependent representation of a (possibly scaled) glyph.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Glyph {
     /// The identifier of the glyph inside the font.
     pub glyph_code: GlyphCode,
 
-    /// The scaling to apply to this glyph
-    pub scale: PercentValue,
+    /// The scaling to apply to this glyph in addition to the scaling of the current script level.
+    pub scale: ScaleFactor,
+}
+
+impl Default for Glyph {
+    /// Returns the glyph with code 0 at its original size.
+    fn default() -> Glyph {
+        Glyph {
+            glyph_code: 0,
+            scale: ScaleFactor::new(100),
+        }
+    }
 }
 
 /// Vertical layout style for equations.
@@ -381,6 +982,41 @@ pub enum MathStyle {
     Inline,
 }
 
+/// The direction in which rows, scripts, fractions and radicals are laid out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextDirection {
+    /// Left to right, as in most languages.
+    Ltr,
+    /// Right to left, e.g. for Arabic mathematical notation. The layout is the mirror image of
+    /// the left-to-right layout.
+    Rtl,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for TextDirection {
+    fn default() -> TextDirection {
+        TextDirection::Ltr
+    }
+}
+
+/// How the space between the elements of a row is determined.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpacingMode {
+    /// Operators are surrounded by their leading and trailing space, which usually comes from the
+    /// MathML operator dictionary.
+    OperatorDictionary,
+    /// The elements are classified like the atoms of TeX and spaced like in TeX. This is meant
+    /// for output that has to match LaTeX.
+    Tex,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for SpacingMode {
+    fn default() -> SpacingMode {
+        SpacingMode::OperatorDictionary
+    }
+}
+
 /// Determines the general style how a math expression should be laid out.
 ///
 /// This affects lots of parameters when laying out an equation.
@@ -391,6 +1027,10 @@ pub struct LayoutStyle {
     /// When the `script_level` property is non-null the glyphs of the font are scaled down. To be
     /// used e.g. when rendering subscripts.
     pub script_level: u8,
+    /// Scales the glyphs, the constants of the font and the lengths that are relative to the font
+    /// size in addition to the scaling of the script level, e.g. to follow the `mathsize` of
+    /// MathML.
+    pub scale: ScaleFactor,
     /// If `true` superscripts and similar protrude less at the top.
     pub is_cramped: bool,
     /// If `true`, try to display flatter versions of accents.
@@ -399,6 +1039,10 @@ pub struct LayoutStyle {
     pub stretch_constraints: Option<Vector<i32>>,
     /// Specifies whether a diacritic should be typeset as an accent.
     pub as_accent: bool,
+    /// Determines the space between the elements of a row.
+    pub spacing: SpacingMode,
+    /// The direction of the layout.
+    pub direction: TextDirection,
 }
 
 impl LayoutStyle {
@@ -421,6 +1065,14 @@ impl LayoutStyle {
         }
     }
 
+    pub fn with_spacing(self, spacing: SpacingMode) -> Self {
+        LayoutStyle { spacing, ..self }
+    }
+
+    pub fn with_scale(self, scale: ScaleFactor) -> Self {
+        LayoutStyle { scale, ..self }
+    }
+
     pub fn with_increased_script_level(self) -> Self {
         LayoutStyle {
             script_level: self.script_level.saturating_add(1),
@@ -458,7 +1110,7 @@ impl LayoutStyle {
     pub fn superscript_style(self) -> LayoutStyle {
         LayoutStyle {
             math_style: MathStyle::Inline,
-            script_level: self.script_level + 1,
+            script_level: self.script_level.saturating_add(1),
             ..self
         }
     }
@@ -474,10 +1126,13 @@ impl Default for LayoutStyle {
         LayoutStyle {
             math_style: MathStyle::Display,
             script_level: 0,
+            scale: ScaleFactor::new(100),
             is_cramped: false,
             flat_accent: false,
             stretch_constraints: None,
             as_accent: false,
+            spacing: SpacingMode::OperatorDictionary,
+            direction: TextDirection::Ltr,
         }
     }
 }
@@ -551,9 +1206,12 @@ mod tests {
     use super::*;
 
     #[test]
-    #[should_panic(expected = "Not a valid percent value")]
-    fn percent_test() {
-        let val = PercentValue::new(101);
-        assert_eq!(val.as_percentage(), 101);
+    fn scale_test() {
+        // scale factors may enlarge
+        let val = ScaleFactor::new(250);
+        assert_eq!(val.as_percentage(), 250);
+        assert_eq!(val.combine(ScaleFactor::new(70)), ScaleFactor::new(175));
+        assert_eq!(1000 / val, 400);
+        assert_eq!(1000 / ScaleFactor::new(0), 1000);
     }
 }
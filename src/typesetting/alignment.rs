@@ -0,0 +1,171 @@
+//! Alignment of table cells and of the lines of a broken row at alignment groups and marks.
+//!
+//! The content of a table cell is split into groups, each of which starts at a
+//! `MathItem::AlignGroup`. The n-th groups of all cells in a column are placed below each other
+//! such that their alignment points line up. The alignment point of a group is its first
+//! `MathItem::AlignMark`. A group without a mark is aligned at its left or right edge or at its
+//! center.
+
+use std::cmp::max;
+
+use super::layout::LayoutOptions;
+use super::linebreak::{collect_items, line_positions, LineItem};
+use super::math_box::MathBox;
+use crate::types::{HorizontalAlign, MathExpression, MathItem};
+
+/// The part that an element of a row plays in the alignment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Alignment {
+    /// The start of a group, which may override the alignment of the group.
+    Group(Option<HorizontalAlign>),
+    Mark,
+}
+
+/// Returns the position of the first alignment mark among `items`, which are placed at
+/// `positions`.
+///
+/// A mark is located at the left edge of the element following it. This excludes the space in
+/// front of an operator, so that the operators after marks are aligned no matter whether their
+/// leading space has been dropped at the start of a line.
+pub fn mark_position(items: &[LineItem], positions: &[i32]) -> Option<i32> {
+    let mark = items
+        .iter()
+        .position(|item| item.alignment == Some(Alignment::Mark))?;
+    let position = items[mark..]
+        .iter()
+        .zip(&positions[mark..])
+        .find(|(item, _)| item.alignment.is_none())
+        .map_or(positions[mark], |(item, &x)| x + item.leading_space);
+    Some(position)
+}
+
+/// A part of a table cell that is aligned with the corresponding parts of the other cells in its
+/// column.
+pub struct AlignmentGroup {
+    /// The boxes of the group relative to its left edge.
+    boxes: Vec<MathBox>,
+    width: i32,
+    /// The position of the first mark inside of the group.
+    mark: Option<i32>,
+    /// The alignment of a group without a mark. Groups are aligned to the left by default.
+    pub align: Option<HorizontalAlign>,
+}
+
+impl AlignmentGroup {
+    /// Returns the distance of the alignment point from the left edge of the group.
+    fn alignment_point(&self) -> i32 {
+        match (self.mark, self.align.unwrap_or(HorizontalAlign::Left)) {
+            (Some(mark), _) => mark,
+            (None, HorizontalAlign::Left) => 0,
+            (None, HorizontalAlign::Center) => self.width / 2,
+            (None, HorizontalAlign::Right) => self.width,
+        }
+    }
+}
+
+/// Lays out the content of a table cell as alignment groups.
+///
+/// Returns `None` if the content contains neither groups nor marks. Elements in front of the
+/// first group belong to it. Rows nested inside of the content are flattened, so that marks
+/// inside of token elements take part in the alignment.
+pub fn alignment_groups(
+    content: &MathExpression,
+    options: LayoutOptions,
+) -> Option<Vec<AlignmentGroup>> {
+    let list = match *content.item {
+        MathItem::List(ref list) => list,
+        _ => return None,
+    };
+    let style = (options.style_provider)(options.style, content.get_user_data());
+    let options = options.style(style).user_data(content.get_user_data());
+    let mut items = Vec::new();
+    collect_items(list, options, 0, &mut items);
+    if items.iter().all(|item| item.alignment.is_none()) {
+        return None;
+    }
+
+    let (positions, width) = line_positions(&items, false, false);
+    let group_indices = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| matches!(item.alignment, Some(Alignment::Group(_))))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let starts = Some(0)
+        .into_iter()
+        .chain(group_indices.iter().skip(1).cloned())
+        .collect::<Vec<_>>();
+    let ends = starts[1..].iter().cloned().chain(Some(items.len()));
+
+    let mut groups = Vec::with_capacity(starts.len());
+    for (group_index, (start, end)) in starts.iter().cloned().zip(ends).enumerate() {
+        let left = positions[start];
+        let right = positions.get(end).cloned().unwrap_or(width);
+        let align = match group_indices
+            .get(group_index)
+            .map(|&index| items[index].alignment)
+        {
+            Some(Some(Alignment::Group(align))) => align,
+            _ => None,
+        };
+        let mark = mark_position(&items[start..end], &positions[start..end]);
+        let boxes = items[start..end]
+            .iter_mut()
+            .zip(&positions[start..end])
+            .map(|(item, &x)| {
+                let mut math_box = item.math_box.take().expect("element is placed twice");
+                math_box.origin.x += x - left;
+                math_box
+            })
+            .collect();
+        groups.push(AlignmentGroup {
+            boxes,
+            width: right - left,
+            mark: mark.map(|mark| mark - left),
+            align,
+        });
+    }
+    Some(groups)
+}
+
+/// Places the groups of the cells of a table column below each other and returns a box for every
+/// cell. All boxes are as wide as the widest cell.
+pub fn align_groups(cells: Vec<(Vec<AlignmentGroup>, u64)>) -> Vec<MathBox> {
+    let num_groups = cells
+        .iter()
+        .map(|(groups, _)| groups.len())
+        .max()
+        .unwrap_or_default();
+    // the space to the left and to the right of the alignment point of each group
+    let spaces = (0..num_groups)
+        .map(|index| {
+            cells
+                .iter()
+                .filter_map(|(groups, _)| groups.get(index))
+                .fold((0, 0), |(left, right), group| {
+                    let point = group.alignment_point();
+                    (max(left, point), max(right, group.width - point))
+                })
+        })
+        .collect::<Vec<_>>();
+    let width = spaces.iter().map(|&(left, right)| left + right).sum();
+
+    cells
+        .into_iter()
+        .map(|(groups, user_data)| {
+            let mut boxes = Vec::new();
+            let mut group_left = 0;
+            for (group, &(left, right)) in groups.into_iter().zip(&spaces) {
+                let offset = group_left + left - group.alignment_point();
+                boxes.extend(group.boxes.into_iter().map(|mut math_box| {
+                    math_box.origin.x += offset;
+                    math_box
+                }));
+                group_left += left + right;
+            }
+            let mut math_box = MathBox::with_vec(boxes, user_data);
+            math_box.metrics.advance_width = width;
+            math_box
+        })
+        .collect()
+}
@@ -2,7 +2,9 @@
 use crate::types::*;
 use std::cmp::{max, min};
 
-use super::math_box::{Extents, MathBox, MathBoxMetrics, Vector};
+use super::alignment::{align_groups, alignment_groups, AlignmentGroup};
+use super::linebreak::{has_forced_break, layout_lines};
+use super::math_box::{Extents, MathBox, MathBoxMetrics, Metrics, Vector};
 use super::multiscripts::*;
 use super::shaper::{MathConstant, MathShaper};
 use super::stretchy::*;
@@ -14,6 +16,9 @@ pub struct LayoutOptions<'a> {
     pub style: LayoutStyle,
     pub stretch_size: Option<Extents<i32>>,
     pub user_data: u64,
+    /// If set, a row that is wider than this is broken into several lines. Only the outermost
+    /// row of an expression is broken.
+    pub max_width: Option<i32>,
 }
 
 impl<'a> LayoutOptions<'a> {
@@ -27,6 +32,16 @@ impl<'a> LayoutOptions<'a> {
     pub fn user_data(self, user_data: u64) -> Self {
         LayoutOptions { user_data, ..self }
     }
+
+    pub fn max_width(self, max_width: Option<i32>) -> Self {
+        LayoutOptions { max_width, ..self }
+    }
+
+    /// Returns the value of a constant of the font that is a length, scaled like the glyphs of
+    /// the current style.
+    pub fn math_constant(self, c: MathConstant) -> i32 {
+        self.shaper.math_constant(c) * self.shaper.scale_factor(self.style)
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
@@ -41,22 +56,73 @@ pub struct OperatorProperties {
     pub leading_space: i32,
     pub trailing_space: i32,
     pub is_large_op: bool,
+    /// The class of the operator in TeX's terms.
+    pub atom_class: AtomClass,
+    pub line_break: LineBreak,
+    pub line_break_style: LineBreakStyle,
+    pub indent_align: Option<HorizontalAlign>,
 }
 
 impl Length {
-    fn to_font_units(self, shaper: &dyn MathShaper) -> i32 {
+    /// Converts the length to font units. Lengths that are relative to the font size grow and
+    /// shrink with the glyphs of the current style, absolute lengths keep their size.
+    fn to_font_units(self, options: LayoutOptions) -> i32 {
+        let font_units = self.to_unscaled_font_units(options.shaper);
+        match self.unit {
+            LengthUnit::Point
+            | LengthUnit::Pixel
+            | LengthUnit::Inch
+            | LengthUnit::Centimeter
+            | LengthUnit::Millimeter => font_units,
+            _ => font_units * options.shaper.scale_factor(options.style),
+        }
+    }
+
+    /// Converts the length to font units like `to_font_units`, but a percentage is a part of
+    /// `reference`, the value in font units that the length replaces.
+    fn to_font_units_of(self, reference: i32, options: LayoutOptions) -> i32 {
+        match self.unit {
+            LengthUnit::Percent => (reference as f32 * self.value / 100.0) as i32,
+            _ => self.to_font_units(options),
+        }
+    }
+
+    /// Converts the length to font units at the normal size of the font.
+    fn to_unscaled_font_units(self, shaper: &dyn MathShaper) -> i32 {
         if self.is_null() {
             return 0;
         }
         match self.unit {
             LengthUnit::Em => (shaper.em_size() as f32 * self.value) as i32,
+            LengthUnit::Ex => (shaper.x_height() as f32 * self.value) as i32,
             LengthUnit::Point => {
-                Length::em(self.value / shaper.ppem().0 as f32).to_font_units(shaper)
+                Length::em(self.value / shaper.ppem().0 as f32).to_unscaled_font_units(shaper)
+            }
+            LengthUnit::Pixel => {
+                Length::new(self.value * 0.75, LengthUnit::Point).to_unscaled_font_units(shaper)
+            }
+            LengthUnit::Inch => {
+                Length::new(self.value * 72.0, LengthUnit::Point).to_unscaled_font_units(shaper)
             }
+            LengthUnit::Centimeter => Length::new(self.value * 72.0 / 2.54, LengthUnit::Point)
+                .to_unscaled_font_units(shaper),
+            LengthUnit::Millimeter => Length::new(self.value * 72.0 / 25.4, LengthUnit::Point)
+                .to_unscaled_font_units(shaper),
+            LengthUnit::MathUnit => Length::em(self.value / 18.0).to_unscaled_font_units(shaper),
+            // a percentage of a length without a default value, like the width of an `mspace`
+            LengthUnit::Percent => 0,
             LengthUnit::DisplayOperatorMinHeight => {
                 (shaper.math_constant(MathConstant::DisplayOperatorMinHeight) as f32 * self.value)
                     as i32
             }
+            LengthUnit::FractionRuleThickness => {
+                (shaper.math_constant(MathConstant::FractionRuleThickness) as f32 * self.value)
+                    as i32
+            }
+            // only `minsize` and `maxsize` are relative to the size of the unstretched glyph,
+            // which `Operator::layout` resolves by itself. Other lengths have no glyph to refer
+            // to and take the em size, which an unstretched operator roughly spans
+            LengthUnit::NormalSize => Length::em(self.value).to_unscaled_font_units(shaper),
         }
     }
 }
@@ -98,7 +164,12 @@ impl MathLayout for Field {
     fn layout(&self, options: LayoutOptions) -> MathBox {
         match *self {
             Field::Empty => MathBox::default(),
-            Field::Glyph(ref glyph) => unimplemented!(),
+            Field::Glyph(ref glyph) => options.shaper.shape_glyph(
+                glyph.glyph_code,
+                glyph.scale,
+                options.style,
+                options.user_data,
+            ),
             Field::Unicode(ref content) => {
                 let shaper = options.shaper;
                 shaper.shape(&content, options.style, options.user_data)
@@ -109,6 +180,20 @@ impl MathLayout for Field {
 
 impl MathLayout for [MathExpression] {
     fn layout(&self, options: LayoutOptions) -> MathBox {
+        // the elements of a row are never broken themselves
+        let max_width = options.max_width;
+        let options = options.max_width(None);
+        if let Some(max_width) = max_width {
+            let line = self.layout(options);
+            if line.advance_width() <= max_width && !has_forced_break(self, options) {
+                return line;
+            }
+            if let Some(lines) = layout_lines(self, max_width, options) {
+                return lines;
+            }
+            return line;
+        }
+
         let boxes = layout_strechy_list(self, options);
 
         let mut cursor = 0i32;
@@ -125,6 +210,16 @@ impl MathLayout for [MathExpression] {
         });
         MathBox::with_vec(layouted.collect(), options.user_data)
     }
+
+    /// A row is an embellished operator if it consists of one and any number of space-like
+    /// elements.
+    fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        let mut elements = self.iter().filter(|expr| !expr.is_space_like());
+        match (elements.next(), elements.next()) {
+            (Some(operator), None) => operator.operator_properties(options),
+            _ => None,
+        }
+    }
 }
 
 impl MathLayout for Vec<MathExpression> {
@@ -135,10 +230,34 @@ impl MathLayout for Vec<MathExpression> {
 
 impl MathLayout for Atom {
     fn layout(&self, options: LayoutOptions) -> MathBox {
-        let subscript = self.bottom_right.as_ref();
-        let superscript = self.top_right.as_ref();
-        let nucleus = self.nucleus.as_ref();
-        layout_sub_superscript(subscript, superscript, nucleus, options)
+        let pre_scripts = [(self.bottom_left.as_ref(), self.top_left.as_ref())];
+        let post_scripts = [(self.bottom_right.as_ref(), self.top_right.as_ref())];
+        layout_scripts(self.nucleus.as_ref(), &pre_scripts, &post_scripts, options)
+    }
+
+    fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        self.nucleus
+            .as_ref()
+            .and_then(|nucleus| nucleus.operator_properties(options))
+    }
+}
+
+impl MathLayout for MultiScripts {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        fn as_refs(
+            pairs: &[ScriptPair],
+        ) -> Vec<(Option<&MathExpression>, Option<&MathExpression>)> {
+            pairs
+                .iter()
+                .map(|pair| (pair.subscript.as_ref(), pair.superscript.as_ref()))
+                .collect()
+        }
+        layout_scripts(
+            self.nucleus.as_ref(),
+            &as_refs(&self.pre_scripts),
+            &as_refs(&self.post_scripts),
+            options,
+        )
     }
 
     fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
@@ -153,92 +272,171 @@ fn layout_sub_superscript(
     superscript: Option<&MathExpression>,
     nucleus: Option<&MathExpression>,
     options: LayoutOptions,
+) -> MathBox {
+    layout_scripts(nucleus, &[], &[(subscript, superscript)], options)
+}
+
+/// Lays out a nucleus with pairs of a subscript and a superscript in front of it and after it.
+///
+/// The pairs are given from left to right. All subscripts are shifted down by the same amount and
+/// all superscripts are shifted up by the same amount. Only the scripts right next to the nucleus
+/// are kerned against it.
+fn layout_scripts(
+    nucleus: Option<&MathExpression>,
+    pre_scripts: &[(Option<&MathExpression>, Option<&MathExpression>)],
+    post_scripts: &[(Option<&MathExpression>, Option<&MathExpression>)],
+    options: LayoutOptions,
 ) -> MathBox {
     let nucleus = match nucleus {
         Some(nucleus) => nucleus,
         None => return MathBox::empty(Extents::default(), options.user_data),
     };
+    // only the nucleus of an embellished operator is stretched
     let subscript_options = LayoutOptions {
         style: options.style.subscript_style(),
+        stretch_size: None,
         ..options
     };
     let superscript_options = LayoutOptions {
         style: options.style.superscript_style(),
+        stretch_size: None,
         ..options
     };
-    let subscript = subscript.map(|x| x.layout(subscript_options));
-    let superscript = superscript.map(|x| x.layout(superscript_options));
+    let layout_pairs = |pairs: &[(Option<&MathExpression>, Option<&MathExpression>)]| {
+        pairs
+            .iter()
+            .filter(|&&(subscript, superscript)| subscript.is_some() || superscript.is_some())
+            .map(|&(subscript, superscript)| {
+                (
+                    subscript.map(|x| x.layout(subscript_options)),
+                    superscript.map(|x| x.layout(superscript_options)),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let pre_scripts = layout_pairs(pre_scripts);
+    let post_scripts = layout_pairs(post_scripts);
     let nucleus_is_largeop = nucleus.is_large_op(options);
     let mut nucleus = nucleus.layout(options);
 
-    let space_after_script = options.shaper.math_constant(MathConstant::SpaceAfterScript);
+    let space_after_script = options.math_constant(MathConstant::SpaceAfterScript);
 
-    if subscript.is_none() && superscript.is_none() {
+    if pre_scripts.is_empty() && post_scripts.is_empty() {
         return nucleus;
     }
 
-    let mut result = Vec::with_capacity(4);
-    match (subscript, superscript) {
-        (Some(mut subscript), Some(mut superscript)) => {
-            let (sub_shift, super_shift) =
-                get_subsup_shifts(&subscript, &superscript, &nucleus, options);
-            position_attachment(
-                &mut subscript,
-                &mut nucleus,
-                nucleus_is_largeop,
-                CornerPosition::BottomRight,
-                sub_shift,
-                options,
-            );
-            position_attachment(
-                &mut superscript,
-                &mut nucleus,
-                nucleus_is_largeop,
-                CornerPosition::TopRight,
-                super_shift,
-                options,
-            );
-            result.push(nucleus);
-            result.push(subscript);
-            result.push(superscript);
-        }
-        (Some(mut subscript), None) => {
-            let sub_shift = get_subscript_shift_dn(&subscript, &nucleus, options);
-            position_attachment(
-                &mut subscript,
-                &mut nucleus,
-                nucleus_is_largeop,
-                CornerPosition::BottomRight,
-                sub_shift,
-                options,
-            );
-            result.push(nucleus);
-            result.push(subscript);
-        }
-        (None, Some(mut superscript)) => {
-            let super_shift = get_superscript_shift_up(&superscript, &nucleus, options);
-            position_attachment(
-                &mut superscript,
-                &mut nucleus,
-                nucleus_is_largeop,
-                CornerPosition::TopRight,
-                super_shift,
-                options,
-            );
-            result.push(nucleus);
-            result.push(superscript);
+    let mut sub_shift = i32::MIN;
+    let mut super_shift = i32::MIN;
+    for pair in pre_scripts.iter().chain(post_scripts.iter()) {
+        match *pair {
+            (Some(ref subscript), Some(ref superscript)) => {
+                let shifts = get_subsup_shifts(subscript, superscript, &nucleus, options);
+                sub_shift = max(sub_shift, shifts.0);
+                super_shift = max(super_shift, shifts.1);
+            }
+            (Some(ref subscript), None) => {
+                let shift = get_subscript_shift_dn(subscript, &nucleus, options);
+                sub_shift = max(sub_shift, shift);
+            }
+            (None, Some(ref superscript)) => {
+                let shift = get_superscript_shift_up(superscript, &nucleus, options);
+                super_shift = max(super_shift, shift);
+            }
+            (None, None) => unreachable!(),
         }
-        // we dealt with this case earlier
-        (None, None) => unreachable!(),
     }
 
-    let mut space = MathBox::empty(Extents::new(0, space_after_script, 0, 0), options.user_data);
-    space.origin.x = result
-        .iter()
-        .map(|math_box| math_box.origin.x + math_box.advance_width())
-        .max()
-        .unwrap_or_default();
-    result.push(space);
+    let mut scripts = Vec::with_capacity(2 * (pre_scripts.len() + post_scripts.len()) + 1);
+
+    // the prescripts are placed from right to left and are right aligned in their column
+    let has_pre_scripts = !pre_scripts.is_empty();
+    let mut column_edge = nucleus.origin.x;
+    for (index, (subscript, superscript)) in pre_scripts.into_iter().rev().enumerate() {
+        let mut column_start = column_edge;
+        let pair = vec![
+            (subscript, CornerPosition::BottomLeft, sub_shift),
+            (superscript, CornerPosition::TopLeft, super_shift),
+        ];
+        for (script, corner, shift) in pair {
+            if let Some(mut script) = script {
+                if index == 0 {
+                    position_attachment(
+                        &mut script,
+                        &mut nucleus,
+                        nucleus_is_largeop,
+                        corner,
+                        shift,
+                        options,
+                    );
+                } else {
+                    script.origin.x = column_edge - script.advance_width();
+                    script.origin.y = if corner.is_top() { -shift } else { shift };
+                }
+                column_start = min(column_start, script.origin.x);
+                scripts.push(script);
+            }
+        }
+        column_edge = column_start - space_after_script;
+    }
+
+    // the postscripts are placed from left to right and are left aligned in their column
+    let has_post_scripts = !post_scripts.is_empty();
+    let mut column_edge = nucleus.origin.x + nucleus.advance_width();
+    for (index, (subscript, superscript)) in post_scripts.into_iter().enumerate() {
+        let mut column_end = column_edge;
+        let pair = vec![
+            (subscript, CornerPosition::BottomRight, sub_shift),
+            (superscript, CornerPosition::TopRight, super_shift),
+        ];
+        for (script, corner, shift) in pair {
+            if let Some(mut script) = script {
+                if index == 0 {
+                    position_attachment(
+                        &mut script,
+                        &mut nucleus,
+                        nucleus_is_largeop,
+                        corner,
+                        shift,
+                        options,
+                    );
+                } else {
+                    script.origin.x = column_edge;
+                    script.origin.y = if corner.is_top() { -shift } else { shift };
+                }
+                column_end = max(column_end, script.origin.x + script.advance_width());
+                scripts.push(script);
+            }
+        }
+        column_edge = column_end + space_after_script;
+    }
+
+    let mut result = Vec::with_capacity(scripts.len() + 2);
+    result.push(nucleus);
+    result.append(&mut scripts);
+
+    if has_post_scripts {
+        let mut space =
+            MathBox::empty(Extents::new(0, space_after_script, 0, 0), options.user_data);
+        space.origin.x = result
+            .iter()
+            .map(|math_box| math_box.origin.x + math_box.advance_width())
+            .max()
+            .unwrap_or_default();
+        result.push(space);
+    }
+
+    // move everything to the right to make room for the prescripts
+    if has_pre_scripts {
+        let offset = -result
+            .iter()
+            .map(|math_box| math_box.origin.x)
+            .min()
+            .unwrap_or_default()
+            + space_after_script;
+        for math_box in &mut result {
+            math_box.origin.x += offset;
+        }
+    }
 
     MathBox::with_vec(result, options.user_data)
 }
@@ -337,9 +535,7 @@ impl MathLayout for OverUnder {
             let (_, LayoutOptions { style, shaper, .. }, ..) = arguments[1];
 
             // enable flat accents if needed
-            let height = options
-                .shaper
-                .math_constant(MathConstant::FlattenedAccentBaseHeight);
+            let height = options.math_constant(MathConstant::FlattenedAccentBaseHeight);
             if self.over_is_accent && nucleus.extents().ascent >= height {
                 let (_, ref mut over_options, _) = arguments[1];
                 over_options.style.flat_accent = true;
@@ -391,39 +587,38 @@ fn layout_over_or_under(
     nucleus_is_large_op: bool,
     nucleus_is_horizontally_stretchy: bool,
 ) -> MathBox {
-    let (shaper, style) = (options.shaper, options.style);
     let mut gap = 0;
     let mut shift = 0;
     if nucleus_is_large_op {
         if as_over {
-            gap = shaper.math_constant(MathConstant::UpperLimitGapMin);
-            shift = shaper.math_constant(MathConstant::UpperLimitBaselineRiseMin)
+            gap = options.math_constant(MathConstant::UpperLimitGapMin);
+            shift = options.math_constant(MathConstant::UpperLimitBaselineRiseMin)
                 + nucleus.extents().ascent;
         } else {
-            gap = shaper.math_constant(MathConstant::LowerLimitGapMin);
-            shift = shaper.math_constant(MathConstant::LowerLimitBaselineDropMin)
+            gap = options.math_constant(MathConstant::LowerLimitGapMin);
+            shift = options.math_constant(MathConstant::LowerLimitBaselineDropMin)
                 + nucleus.extents().descent;
         }
     } else if nucleus_is_horizontally_stretchy {
         if as_over {
-            gap = shaper.math_constant(MathConstant::StretchStackGapBelowMin);
-            shift = shaper.math_constant(MathConstant::StretchStackTopShiftUp);
+            gap = options.math_constant(MathConstant::StretchStackGapBelowMin);
+            shift = options.math_constant(MathConstant::StretchStackTopShiftUp);
         } else {
-            gap = shaper.math_constant(MathConstant::StretchStackGapAboveMin);
-            shift = shaper.math_constant(MathConstant::StretchStackBottomShiftDown);
+            gap = options.math_constant(MathConstant::StretchStackGapAboveMin);
+            shift = options.math_constant(MathConstant::StretchStackBottomShiftDown);
         }
     } else if !as_accent {
         gap = if as_over {
-            shaper.math_constant(MathConstant::OverbarVerticalGap)
+            options.math_constant(MathConstant::OverbarVerticalGap)
         } else {
-            shaper.math_constant(MathConstant::UnderbarVerticalGap)
+            options.math_constant(MathConstant::UnderbarVerticalGap)
         };
         shift = gap;
     }
 
     let baseline_offset = if as_accent {
         if as_over {
-            let accent_base_height = shaper.math_constant(MathConstant::AccentBaseHeight);
+            let accent_base_height = options.math_constant(MathConstant::AccentBaseHeight);
             -max(nucleus.extents().ascent - accent_base_height, 0)
         } else {
             nucleus.extents().descent
@@ -507,53 +702,88 @@ impl MathLayout for GeneralizedFraction {
         if options.style.math_style == MathStyle::Display {
             numerator_options.style.math_style = MathStyle::Inline;
         } else {
-            numerator_options.style.script_level += 1;
+            numerator_options.style.script_level = options.style.script_level.saturating_add(1);
         }
         let denominator_options = LayoutOptions {
             style: numerator_options.style.cramped_style(),
+            stretch_size: None,
             ..options
         };
         let mut numerator = numerator.layout(numerator_options);
         let mut denominator = denominator.layout(denominator_options);
 
-        let shaper = &options.shaper;
-        let axis_height = shaper.math_constant(MathConstant::AxisHeight);
-        let default_thickness = shaper.math_constant(MathConstant::FractionRuleThickness);
+        let axis_height = options.math_constant(MathConstant::AxisHeight);
+        let default_thickness = options.math_constant(MathConstant::FractionRuleThickness);
+        let thickness = self
+            .thickness
+            .map(|thickness| max(0, thickness.to_font_units_of(default_thickness, options)))
+            .unwrap_or(default_thickness);
 
-        let (numerator_shift_up, denominator_shift_dn) =
-            if options.style.math_style == MathStyle::Inline {
+        if self.is_bevelled {
+            return layout_bevelled_fraction(numerator, denominator, thickness, options);
+        }
+
+        let (numerator_shift_up, denominator_shift_dn) = if thickness == 0 {
+            // without a fraction rule the fields are positioned as a stack
+            let (shift_up, shift_dn, gap_min) = if options.style.math_style == MathStyle::Inline {
                 (
-                    shaper.math_constant(MathConstant::FractionNumeratorShiftUp),
-                    shaper.math_constant(MathConstant::FractionDenominatorShiftDown),
+                    options.math_constant(MathConstant::StackTopShiftUp),
+                    options.math_constant(MathConstant::StackBottomShiftDown),
+                    options.math_constant(MathConstant::StackGapMin),
                 )
             } else {
                 (
-                    shaper.math_constant(MathConstant::FractionNumeratorDisplayStyleShiftUp),
-                    shaper.math_constant(MathConstant::FractionDenominatorDisplayStyleShiftDown),
+                    options.math_constant(MathConstant::StackTopDisplayStyleShiftUp),
+                    options.math_constant(MathConstant::StackBottomDisplayStyleShiftDown),
+                    options.math_constant(MathConstant::StackDisplayStyleGapMin),
                 )
             };
-
-        let (numerator_gap_min, denominator_gap_min) =
-            if options.style.math_style == MathStyle::Inline {
+            let gap = (shift_up - numerator.extents().descent)
+                + (shift_dn - denominator.extents().ascent);
+            let correction = max(0, gap_min - gap) / 2;
+            (
+                shift_up + correction - axis_height,
+                shift_dn + correction + axis_height,
+            )
+        } else {
+            let (numerator_shift_up, denominator_shift_dn) = if options.style.math_style
+                == MathStyle::Inline
+            {
                 (
-                    shaper.math_constant(MathConstant::FractionNumeratorGapMin),
-                    shaper.math_constant(MathConstant::FractionDenominatorGapMin),
+                    options.math_constant(MathConstant::FractionNumeratorShiftUp),
+                    options.math_constant(MathConstant::FractionDenominatorShiftDown),
                 )
             } else {
                 (
-                    shaper.math_constant(MathConstant::FractionNumDisplayStyleGapMin),
-                    shaper.math_constant(MathConstant::FractionDenomDisplayStyleGapMin),
+                    options.math_constant(MathConstant::FractionNumeratorDisplayStyleShiftUp),
+                    options.math_constant(MathConstant::FractionDenominatorDisplayStyleShiftDown),
                 )
             };
 
-        let numerator_shift_up = max(
-            numerator_shift_up - axis_height,
-            numerator_gap_min + default_thickness / 2 + numerator.extents().descent,
-        );
-        let denominator_shift_dn = max(
-            denominator_shift_dn + axis_height,
-            denominator_gap_min + default_thickness / 2 + denominator.extents().ascent,
-        );
+            let (numerator_gap_min, denominator_gap_min) =
+                if options.style.math_style == MathStyle::Inline {
+                    (
+                        options.math_constant(MathConstant::FractionNumeratorGapMin),
+                        options.math_constant(MathConstant::FractionDenominatorGapMin),
+                    )
+                } else {
+                    (
+                        options.math_constant(MathConstant::FractionNumDisplayStyleGapMin),
+                        options.math_constant(MathConstant::FractionDenomDisplayStyleGapMin),
+                    )
+                };
+
+            (
+                max(
+                    numerator_shift_up - axis_height,
+                    numerator_gap_min + thickness / 2 + numerator.extents().descent,
+                ),
+                max(
+                    denominator_shift_dn + axis_height,
+                    denominator_gap_min + thickness / 2 + denominator.extents().ascent,
+                ),
+            )
+        };
 
         numerator.origin.y -= axis_height;
         denominator.origin.y -= axis_height;
@@ -570,23 +800,28 @@ impl MathLayout for GeneralizedFraction {
             denominator.origin.x += center_difference;
         }
 
+        let left = min(
+            numerator.origin.x + numerator.extents().left_side_bearing,
+            denominator.origin.x + denominator.extents().left_side_bearing,
+        );
+        let right = max(
+            numerator.origin.x + numerator.extents().right_edge(),
+            denominator.origin.x + denominator.extents().right_edge(),
+        );
+        align_horizontally(&mut numerator, self.numerator_align, left, right);
+        align_horizontally(&mut denominator, self.denominator_align, left, right);
+
+        if thickness == 0 {
+            return MathBox::with_vec(vec![numerator, denominator], options.user_data);
+        }
+
         // the fraction rule
         let origin = Vector {
-            x: min(
-                numerator.origin.x + numerator.extents().left_side_bearing,
-                denominator.origin.x + denominator.extents().left_side_bearing,
-            ),
+            x: left,
             y: -axis_height,
         };
-        let target = Vector {
-            x: max(
-                numerator.origin.x + numerator.extents().right_edge(),
-                denominator.origin.x + denominator.extents().right_edge(),
-            ),
-            ..origin
-        };
-        let fraction_rule =
-            MathBox::with_line(origin, target, default_thickness as u32, options.user_data);
+        let target = Vector { x: right, ..origin };
+        let fraction_rule = MathBox::with_line(origin, target, thickness as u32, options.user_data);
 
         MathBox::with_vec(
             vec![numerator, fraction_rule, denominator],
@@ -601,6 +836,57 @@ impl MathLayout for GeneralizedFraction {
     }
 }
 
+/// Moves a (centered) fraction field to the left or right edge of the horizontal span `left..right`.
+fn align_horizontally(math_box: &mut MathBox, align: HorizontalAlign, left: i32, right: i32) {
+    let extents = math_box.extents();
+    match align {
+        HorizontalAlign::Left => math_box.origin.x = left - extents.left_side_bearing,
+        HorizontalAlign::Center => {}
+        HorizontalAlign::Right => math_box.origin.x = right - extents.right_edge(),
+    }
+}
+
+/// Lays out a skewed fraction with the numerator raised to the upper left and the denominator
+/// lowered to the lower right of a slanted line.
+fn layout_bevelled_fraction(
+    mut numerator: MathBox,
+    mut denominator: MathBox,
+    thickness: i32,
+    options: LayoutOptions,
+) -> MathBox {
+    let axis_height = options.math_constant(MathConstant::AxisHeight);
+    let horizontal_gap = options.math_constant(MathConstant::SkewedFractionHorizontalGap);
+    let vertical_gap = options.math_constant(MathConstant::SkewedFractionVerticalGap);
+
+    // the baselines of both fields are placed symmetrically around the math axis
+    numerator.origin.y -= axis_height + vertical_gap / 2;
+    denominator.origin.y -= axis_height - vertical_gap / 2;
+
+    let numerator_right = numerator.origin.x + numerator.advance_width();
+    denominator.origin.x += numerator_right + horizontal_gap;
+
+    // the slash spans the height of both fields and is slanted inside the horizontal gap
+    let top = min(
+        numerator.origin.y - numerator.extents().ascent,
+        denominator.origin.y - denominator.extents().ascent,
+    );
+    let bottom = max(
+        numerator.origin.y + numerator.extents().descent,
+        denominator.origin.y + denominator.extents().descent,
+    );
+    let from = Vector {
+        x: numerator_right + horizontal_gap / 4,
+        y: bottom,
+    };
+    let to = Vector {
+        x: numerator_right + horizontal_gap * 3 / 4,
+        y: top,
+    };
+    let slash = MathBox::with_line(from, to, thickness as u32, options.user_data);
+
+    MathBox::with_vec(vec![numerator, slash, denominator], options.user_data)
+}
+
 impl MathLayout for Root {
     fn layout(&self, options: LayoutOptions) -> MathBox {
         let radicand = match &self.radicand {
@@ -609,82 +895,27 @@ impl MathLayout for Root {
         };
 
         let shaper = options.shaper;
-        let line_thickness = shaper.math_constant(MathConstant::RadicalRuleThickness);
-        let vertical_gap = if options.style.math_style == MathStyle::Inline {
-            shaper.math_constant(MathConstant::RadicalVerticalGap)
-        } else {
-            shaper.math_constant(MathConstant::RadicalDisplayStyleVerticalGap)
-        };
-        let extra_ascender = shaper.math_constant(MathConstant::RadicalExtraAscender);
-
-        // calculate the needed surd height based on the height of the radicand
-        let mut radicand = radicand.layout(options);
-        let needed_surd_height = radicand.extents().height() + vertical_gap + line_thickness;
-
-        // draw a stretched version of the surd
-        // let surd_style = LayoutStyle {
-        //     stretch_constraints: Some(Vector {
-        //         x: 0,
-        //         y: needed_surd_height,
-        //     }),
-        //     ..options.style
-        // };
-        let surd = options.shaper.shape("√", options.style, options.user_data);
-        let mut surd = surd
-            .first_glyph()
-            .and_then(|(glyph, _scale)| {
-                if options.shaper.is_stretchable(glyph.glyph_code, false) {
-                    Some(options.shaper.stretch_glyph(
-                        glyph.glyph_code,
-                        false,
-                        needed_surd_height.abs() as u32,
-                        options.style,
-                        options.user_data,
-                    ))
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_default();
-
-        // raise the surd so that its ascent is at least the radicand's ascender plus the radical
-        // gap plus the line thickness of the radical rule
-        let surd_excess_height =
-            surd.extents().height() - (radicand.extents().height() + vertical_gap + line_thickness);
-
-        surd.origin.y =
-            (radicand.extents().descent - surd.extents().descent) + surd_excess_height / 2;
+        let extra_ascender = options.math_constant(MathConstant::RadicalExtraAscender);
 
-        // place the radicand after the surd
-        radicand.origin.x += surd.origin.x + surd.advance_width();
-
-        // the radical rule
-        let origin = Vector {
-            x: surd.origin.x + surd.advance_width(),
-            y: surd.origin.y - surd.extents().ascent + line_thickness / 2,
-        };
-        let target = Vector {
-            x: origin.x + radicand.extents().right_edge(),
-            ..origin
-        };
-        let mut radical_rule =
-            MathBox::with_line(origin, target, line_thickness as u32, options.user_data);
+        let radicand = radicand.layout(options);
+        let (mut surd, mut radical_rule, mut radicand) =
+            layout_radical_symbol("√", radicand, options);
 
         let mut boxes = vec![];
 
         // typeset the self degree
         if let &Some(ref degree) = &self.degree {
-            let degree_bottom_raise_percent = PercentValue::new(
-                shaper.math_constant(MathConstant::RadicalDegreeBottomRaisePercent) as u8,
+            let degree_bottom_raise_percent = ScaleFactor::new(
+                shaper.math_constant(MathConstant::RadicalDegreeBottomRaisePercent) as u32,
             );
-            let kern_before = shaper.math_constant(MathConstant::RadicalKernBeforeDegree);
-            let kern_after = shaper.math_constant(MathConstant::RadicalKernAfterDegree);
+            let kern_before = options.math_constant(MathConstant::RadicalKernBeforeDegree);
+            let kern_after = options.math_constant(MathConstant::RadicalKernAfterDegree);
             let surd_height = surd.extents().ascent + surd.extents().descent;
             let degree_bottom =
                 surd.origin.y + surd.extents().descent - surd_height * degree_bottom_raise_percent;
 
             let mut degree_options = options;
-            degree_options.style.script_level += 2;
+            degree_options.style.script_level = options.style.script_level.saturating_add(2);
             degree_options.style.math_style = MathStyle::Inline;
             let mut degree = degree.layout(degree_options);
             degree.origin.y += degree_bottom;
@@ -707,71 +938,775 @@ impl MathLayout for Root {
     }
 }
 
+/// Places a vertically stretched version of `symbol` in front of the already laid out `radicand`
+/// and draws a rule above the radicand, just like the surd of a radical.
+///
+/// Returns the symbol, the rule and the radicand.
+fn layout_radical_symbol(
+    symbol: &str,
+    mut radicand: MathBox,
+    options: LayoutOptions,
+) -> (MathBox, MathBox, MathBox) {
+    let shaper = options.shaper;
+    let line_thickness = options.math_constant(MathConstant::RadicalRuleThickness);
+    let vertical_gap = if options.style.math_style == MathStyle::Inline {
+        options.math_constant(MathConstant::RadicalVerticalGap)
+    } else {
+        options.math_constant(MathConstant::RadicalDisplayStyleVerticalGap)
+    };
+
+    // calculate the needed surd height based on the height of the radicand
+    let needed_surd_height = radicand.extents().height() + vertical_gap + line_thickness;
+
+    // draw a stretched version of the surd
+    let surd = shaper.shape(symbol, options.style, options.user_data);
+    let mut surd = surd
+        .first_glyph()
+        .and_then(|(glyph, _scale)| {
+            if shaper.is_stretchable(glyph.glyph_code, false) {
+                Some(shaper.stretch_glyph(
+                    glyph.glyph_code,
+                    false,
+                    needed_surd_height.unsigned_abs(),
+                    options.style,
+                    options.user_data,
+                ))
+            } else {
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    // raise the surd so that its ascent is at least the radicand's ascender plus the radical
+    // gap plus the line thickness of the radical rule
+    let surd_excess_height =
+        surd.extents().height() - (radicand.extents().height() + vertical_gap + line_thickness);
+
+    surd.origin.y = (radicand.extents().descent - surd.extents().descent) + surd_excess_height / 2;
+
+    // place the radicand after the surd
+    radicand.origin.x += surd.origin.x + surd.advance_width();
+
+    // the radical rule
+    let origin = Vector {
+        x: surd.origin.x + surd.advance_width(),
+        y: surd.origin.y - surd.extents().ascent + line_thickness / 2,
+    };
+    let target = Vector {
+        x: origin.x + radicand.extents().right_edge(),
+        ..origin
+    };
+    let radical_rule = MathBox::with_line(origin, target, line_thickness as u32, options.user_data);
+
+    (surd, radical_rule, radicand)
+}
+
+/// Returns the entry of a per-row or per-column attribute list. The last entry of the list is
+/// used for all indices past the end of the list.
+fn list_entry<T: Copy>(list: &[T], index: usize) -> Option<T> {
+    list.get(index).or_else(|| list.last()).cloned()
+}
+
+/// Returns the alignment of the group with the given index from a per-column list of group
+/// alignments.
+fn group_align_entry(
+    list: &[Vec<HorizontalAlign>],
+    column: usize,
+    index: usize,
+) -> Option<HorizontalAlign> {
+    list.get(column)
+        .or_else(|| list.last())
+        .and_then(|aligns| list_entry(aligns, index))
+}
+
+/// Returns the boxes needed to draw a line of the given style from `from` to `to`.
+fn styled_line(
+    from: Vector<i32>,
+    to: Vector<i32>,
+    style: LineStyle,
+    thickness: i32,
+    user_data: u64,
+) -> Vec<MathBox> {
+    match style {
+        LineStyle::None => vec![],
+        LineStyle::Solid => vec![MathBox::with_line(from, to, thickness as u32, user_data)],
+        LineStyle::Dashed => {
+            let delta = to - from;
+            let length = ((delta.x as f32).powi(2) + (delta.y as f32).powi(2)).sqrt();
+            let dash_length = 4 * max(thickness, 1);
+            let num_dashes = max(1, (length / (2 * dash_length) as f32).round() as i32);
+            // every dash is followed by a gap of the same length except for the last one
+            let step = delta / (2 * num_dashes - 1);
+            (0..num_dashes)
+                .map(|index| {
+                    let start = from + step * (2 * index);
+                    MathBox::with_line(start, start + step, thickness as u32, user_data)
+                })
+                .collect()
+        }
+    }
+}
+
+impl MathLayout for Table {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        let cell_options = LayoutOptions {
+            style: options.style.inline_style(),
+            stretch_size: None,
+            ..options
+        };
+
+        // cells with alignment groups or marks are aligned with the other such cells in their
+        // column, all other cells are laid out on their own
+        let aligned_cells: Vec<Vec<Option<Vec<AlignmentGroup>>>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.cells
+                    .iter()
+                    .enumerate()
+                    .map(|(column, cell)| {
+                        let mut groups = alignment_groups(cell.content.as_ref()?, cell_options)?;
+                        for (index, group) in groups.iter_mut().enumerate() {
+                            group.align = group
+                                .align
+                                .or_else(|| list_entry(&cell.group_align, index))
+                                .or_else(|| group_align_entry(&row.group_align, column, index))
+                                .or_else(|| group_align_entry(&self.group_align, column, index));
+                        }
+                        Some(groups)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut rows: Vec<Vec<MathBox>> = self
+            .rows
+            .iter()
+            .zip(&aligned_cells)
+            .map(|(row, aligned_row)| {
+                row.cells
+                    .iter()
+                    .zip(aligned_row)
+                    .map(|(cell, groups)| match *groups {
+                        Some(_) => MathBox::default(),
+                        None => cell.content.layout(cell_options),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let num_columns = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+        // the cells with alignment groups together with their row, sorted by column
+        let mut columns: Vec<Vec<_>> = (0..num_columns).map(|_| Vec::new()).collect();
+        for (row_index, (row, aligned_row)) in self.rows.iter().zip(aligned_cells).enumerate() {
+            for (column, (cell, groups)) in row.cells.iter().zip(aligned_row).enumerate() {
+                if let Some(groups) = groups {
+                    let user_data = cell
+                        .content
+                        .as_ref()
+                        .map_or(options.user_data, MathExpression::get_user_data);
+                    columns[column].push((row_index, (groups, user_data)));
+                }
+            }
+        }
+        for (column, cells) in columns.into_iter().enumerate() {
+            let (row_indices, cells): (Vec<_>, Vec<_>) = cells.into_iter().unzip();
+            for (row_index, math_box) in row_indices.into_iter().zip(align_groups(cells)) {
+                rows[row_index][column] = math_box;
+            }
+        }
+        let column_widths: Vec<i32> = (0..num_columns)
+            .map(|column| {
+                rows.iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|math_box| math_box.advance_width())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        // the ascent and descent of every row
+        let row_extents: Vec<(i32, i32)> = rows
+            .iter()
+            .map(|row| {
+                let ascent = row.iter().map(|math_box| math_box.extents().ascent).max();
+                let descent = row.iter().map(|math_box| math_box.extents().descent).max();
+                (ascent.unwrap_or_default(), descent.unwrap_or_default())
+            })
+            .collect();
+
+        // percentages are relative to the default spacings
+        let defaults = Table::default();
+        let spacing = |lengths: &[Length], default_lengths: &[Length], index: usize| {
+            let default = list_entry(default_lengths, index)
+                .unwrap_or_default()
+                .to_font_units(options);
+            list_entry(lengths, index)
+                .unwrap_or_default()
+                .to_font_units_of(default, options)
+        };
+        let column_spacing: Vec<i32> = (0..num_columns.saturating_sub(1))
+            .map(|index| spacing(&self.column_spacing, &defaults.column_spacing, index))
+            .collect();
+        let row_spacing: Vec<i32> = (0..rows.len().saturating_sub(1))
+            .map(|index| spacing(&self.row_spacing, &defaults.row_spacing, index))
+            .collect();
+        let (frame_spacing_h, frame_spacing_v) = if self.frame == LineStyle::None {
+            (0, 0)
+        } else {
+            let default_h = defaults.frame_spacing.0.to_font_units(options);
+            let default_v = defaults.frame_spacing.1.to_font_units(options);
+            (
+                self.frame_spacing.0.to_font_units_of(default_h, options),
+                self.frame_spacing.1.to_font_units_of(default_v, options),
+            )
+        };
+
+        let table_width = column_widths.iter().sum::<i32>()
+            + column_spacing.iter().sum::<i32>()
+            + 2 * frame_spacing_h;
+        let table_height = row_extents
+            .iter()
+            .map(|&(ascent, descent)| ascent + descent)
+            .sum::<i32>()
+            + row_spacing.iter().sum::<i32>()
+            + 2 * frame_spacing_v;
+
+        // the table is vertically centered on the math axis
+        let axis_height = options.math_constant(MathConstant::AxisHeight);
+        let table_top = -axis_height - table_height / 2;
+        let table_bottom = table_top + table_height;
+        let line_thickness = options.math_constant(MathConstant::FractionRuleThickness);
+
+        let mut boxes = Vec::new();
+        let mut labels = Vec::new();
+        let mut row_top = table_top + frame_spacing_v;
+        for (row_index, (row, cells)) in self.rows.iter().zip(rows.drain(..)).enumerate() {
+            let (row_ascent, row_descent) = row_extents[row_index];
+            let baseline = row_top + row_ascent;
+
+            let mut cell_left = frame_spacing_h;
+            for (column, (cell, mut math_box)) in row.cells.iter().zip(cells).enumerate() {
+                let column_align = cell
+                    .column_align
+                    .or_else(|| list_entry(&row.column_align, column))
+                    .or_else(|| list_entry(&self.column_align, column))
+                    .unwrap_or_default();
+                let row_align = cell
+                    .row_align
+                    .or(row.row_align)
+                    .or_else(|| list_entry(&self.row_align, row_index))
+                    .unwrap_or_default();
+
+                let free_space = column_widths[column] - math_box.advance_width();
+                math_box.origin.x += cell_left
+                    + match column_align {
+                        HorizontalAlign::Left => 0,
+                        HorizontalAlign::Center => free_space / 2,
+                        HorizontalAlign::Right => free_space,
+                    };
+
+                let extents = math_box.extents();
+                math_box.origin.y += match row_align {
+                    VerticalAlign::Baseline | VerticalAlign::Axis => baseline,
+                    VerticalAlign::Top => row_top + extents.ascent,
+                    VerticalAlign::Bottom => baseline + row_descent - extents.descent,
+                    VerticalAlign::Center => {
+                        row_top + (row_ascent + row_descent - extents.height()) / 2 + extents.ascent
+                    }
+                };
+                boxes.push(math_box);
+
+                cell_left += column_widths[column];
+                if let Some(&spacing) = column_spacing.get(column) {
+                    cell_left += spacing;
+                }
+            }
+
+            if let Some(ref label) = row.label {
+                let mut label = label.layout(cell_options);
+                label.origin.y += baseline;
+                labels.push(label);
+            }
+
+            row_top = baseline + row_descent;
+            if let Some(&spacing) = row_spacing.get(row_index) {
+                let line_style = list_entry(&self.row_lines, row_index).unwrap_or_default();
+                let y = row_top + spacing / 2;
+                boxes.append(&mut styled_line(
+                    Vector {
+                        x: frame_spacing_h,
+                        y,
+                    },
+                    Vector {
+                        x: table_width - frame_spacing_h,
+                        y,
+                    },
+                    line_style,
+                    line_thickness,
+                    options.user_data,
+                ));
+                row_top += spacing;
+            }
+        }
+
+        let mut column_left = frame_spacing_h;
+        for (column, spacing) in column_spacing.iter().enumerate() {
+            column_left += column_widths[column];
+            let line_style = list_entry(&self.column_lines, column).unwrap_or_default();
+            let x = column_left + spacing / 2;
+            boxes.append(&mut styled_line(
+                Vector {
+                    x,
+                    y: table_top + frame_spacing_v,
+                },
+                Vector {
+                    x,
+                    y: table_bottom - frame_spacing_v,
+                },
+                line_style,
+                line_thickness,
+                options.user_data,
+            ));
+            column_left += spacing;
+        }
+
+        let corners = [
+            Vector { x: 0, y: table_top },
+            Vector {
+                x: table_width,
+                y: table_top,
+            },
+            Vector {
+                x: table_width,
+                y: table_bottom,
+            },
+            Vector {
+                x: 0,
+                y: table_bottom,
+            },
+        ];
+        for index in 0..corners.len() {
+            let (from, to) = (corners[index], corners[(index + 1) % corners.len()]);
+            // always draw from top to bottom and from left to right
+            let (from, to) = if from.x > to.x || from.y > to.y {
+                (to, from)
+            } else {
+                (from, to)
+            };
+            boxes.append(&mut styled_line(
+                from,
+                to,
+                self.frame,
+                line_thickness,
+                options.user_data,
+            ));
+        }
+
+        // labels are placed to the right of the table
+        let label_spacing = Length::em(0.8).to_font_units(options);
+        for mut label in labels {
+            label.origin.x += table_width + label_spacing;
+            boxes.push(label);
+        }
+
+        let mut math_box = MathBox::with_vec(boxes, options.user_data);
+        math_box.metrics.advance_width = max(math_box.advance_width(), table_width);
+        math_box.metrics.italic_correction = 0;
+        math_box
+    }
+}
+
+impl MathLayout for Enclosure {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        let has = |notation| self.notations.contains(&notation);
+
+        let mut content = self.content.layout(options);
+        if has(Notation::Radical) {
+            let (surd, rule, radicand) = layout_radical_symbol("√", content, options);
+            content = MathBox::with_vec(vec![surd, rule, radicand], options.user_data);
+        }
+        if has(Notation::LongDiv) {
+            let (bracket, rule, dividend) = layout_radical_symbol(")", content, options);
+            content = MathBox::with_vec(vec![bracket, rule, dividend], options.user_data);
+        }
+
+        let thickness = options.math_constant(MathConstant::OverbarRuleThickness);
+        let gap = options.math_constant(MathConstant::OverbarVerticalGap);
+        let bottom_thickness = options.math_constant(MathConstant::UnderbarRuleThickness);
+        let bottom_gap = options.math_constant(MathConstant::UnderbarVerticalGap);
+
+        let closed = has(Notation::Box) || has(Notation::RoundedBox);
+        let surrounded = closed || has(Notation::Circle);
+        let line_left = has(Notation::Left);
+        let line_right = has(Notation::Right) || has(Notation::Actuarial) || has(Notation::MAdruwb);
+        let line_top = has(Notation::Top) || has(Notation::Actuarial);
+        let line_bottom = has(Notation::Bottom) || has(Notation::MAdruwb);
+
+        // the frame is the area around the content that is separated from the content by a gap
+        // on every side with a line
+        let padding = |has_line: bool, gap: i32| if has_line || surrounded { gap } else { 0 };
+        let extents = content.extents();
+        let frame_left = -padding(line_left, gap);
+        let frame_right = content.advance_width() + padding(line_right, gap);
+        let frame_top = -extents.ascent - padding(line_top, gap);
+        let frame_bottom = extents.descent + padding(line_bottom, bottom_gap);
+
+        // lines are drawn outside of the frame
+        let outside = |has_line: bool, thickness: i32| {
+            if has_line || closed {
+                thickness
+            } else {
+                0
+            }
+        };
+        let mut outer_left = frame_left - outside(line_left, thickness);
+        let mut outer_right = frame_right + outside(line_right, thickness);
+        let outer_top = frame_top - outside(line_top, thickness);
+        let outer_bottom = frame_bottom + outside(line_bottom, bottom_thickness);
+
+        let user_data = options.user_data;
+        let mut boxes = vec![];
+        let mut line = |from: (i32, i32), to: (i32, i32), thickness: i32| {
+            let from = Vector {
+                x: from.0,
+                y: from.1,
+            };
+            let to = Vector { x: to.0, y: to.1 };
+            boxes.push(MathBox::with_line(from, to, thickness as u32, user_data));
+        };
+        if line_left {
+            let x = frame_left - thickness / 2;
+            line((x, outer_top), (x, outer_bottom), thickness);
+        }
+        if line_right {
+            let x = frame_right + thickness / 2;
+            line((x, outer_top), (x, outer_bottom), thickness);
+        }
+        if line_top {
+            let y = frame_top - thickness / 2;
+            line((outer_left, y), (outer_right, y), thickness);
+        }
+        if line_bottom {
+            let y = frame_bottom + bottom_thickness / 2;
+            line((outer_left, y), (outer_right, y), bottom_thickness);
+        }
+
+        // strikes and arrows go across the frame
+        let center_x = (frame_left + frame_right) / 2;
+        let center_y = (frame_top + frame_bottom) / 2;
+        let bottom_left = (frame_left, frame_bottom);
+        let top_right = (frame_right, frame_top);
+        let top_left = (frame_left, frame_top);
+        let bottom_right = (frame_right, frame_bottom);
+        if has(Notation::UpDiagonalStrike) {
+            line(bottom_left, top_right, thickness);
+        }
+        if has(Notation::DownDiagonalStrike) {
+            line(top_left, bottom_right, thickness);
+        }
+        if has(Notation::VerticalStrike) {
+            line((center_x, frame_top), (center_x, frame_bottom), thickness);
+        }
+        if has(Notation::HorizontalStrike) {
+            line((frame_left, center_y), (frame_right, center_y), thickness);
+        }
+
+        let arrows = [
+            (Notation::UpDiagonalArrow, bottom_left, top_right),
+            (Notation::DownDiagonalArrow, top_left, bottom_right),
+            (
+                Notation::UpArrow,
+                (center_x, frame_bottom),
+                (center_x, frame_top),
+            ),
+            (
+                Notation::DownArrow,
+                (center_x, frame_top),
+                (center_x, frame_bottom),
+            ),
+            (
+                Notation::LeftArrow,
+                (frame_right, center_y),
+                (frame_left, center_y),
+            ),
+            (
+                Notation::RightArrow,
+                (frame_left, center_y),
+                (frame_right, center_y),
+            ),
+        ];
+        for &(notation, from, to) in arrows.iter() {
+            if has(notation) {
+                boxes.push(MathBox::with_arrow(
+                    Vector {
+                        x: from.0,
+                        y: from.1,
+                    },
+                    Vector { x: to.0, y: to.1 },
+                    thickness as u32,
+                    5 * thickness as u32,
+                    user_data,
+                ));
+            }
+        }
+
+        let frame_size = Vector {
+            x: frame_right - frame_left,
+            y: frame_bottom - frame_top,
+        };
+        if closed {
+            let corner_radius = if has(Notation::RoundedBox) { gap } else { 0 };
+            boxes.push(MathBox::with_rect(
+                Vector {
+                    x: frame_left - thickness,
+                    y: frame_top - thickness,
+                },
+                frame_size + Vector { x: 1, y: 1 } * (2 * thickness),
+                corner_radius as u32,
+                thickness as u32,
+                user_data,
+            ));
+        }
+        if has(Notation::Circle) {
+            // the smallest ellipse with the same aspect ratio as the frame that contains the
+            // frame is larger than the frame by a factor of sqrt(2)
+            let ellipse_size = Vector {
+                x: (frame_size.x as f32 * ::std::f32::consts::SQRT_2) as i32 + 2 * thickness,
+                y: (frame_size.y as f32 * ::std::f32::consts::SQRT_2) as i32 + 2 * thickness,
+            };
+            let origin = Vector {
+                x: frame_left - (ellipse_size.x - frame_size.x) / 2,
+                y: frame_top - (ellipse_size.y - frame_size.y) / 2,
+            };
+            outer_left = min(outer_left, origin.x);
+            outer_right = max(outer_right, origin.x + ellipse_size.x);
+            boxes.push(MathBox::with_ellipse(
+                origin,
+                ellipse_size,
+                thickness as u32,
+                user_data,
+            ));
+        }
+
+        boxes.insert(0, content);
+        for math_box in &mut boxes {
+            math_box.origin.x -= outer_left;
+        }
+        let mut result = MathBox::with_vec(boxes, user_data);
+
+        // leave some space above a line on top and below a line on the bottom
+        if line_top || surrounded {
+            let extra_ascender = options.math_constant(MathConstant::OverbarExtraAscender);
+            result.metrics.extents.ascent += extra_ascender;
+        }
+        if line_bottom || surrounded {
+            let extra_descender = options.math_constant(MathConstant::UnderbarExtraDescender);
+            result.metrics.extents.descent += extra_descender;
+        }
+        result.metrics.advance_width = outer_right - outer_left;
+        result.metrics.italic_correction = 0;
+        result
+    }
+}
+
+impl MathLayout for Styled {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        let style = self.apply_to(options.style, options.shaper);
+        let mut content = self.content.layout(options.style(style));
+        if style.direction != options.style.direction {
+            content.mirror();
+        }
+        if let Some(color) = self.math_color {
+            content.set_default_color(color);
+        }
+        match self.math_background {
+            Some(background) => MathBox::with_background(content, background, options.user_data),
+            None => content,
+        }
+    }
+
+    fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        self.content
+            .operator_properties(options.style(self.apply_to(options.style, options.shaper)))
+    }
+}
+
+impl Styled {
+    fn apply_to(&self, mut style: LayoutStyle, shaper: &dyn MathShaper) -> LayoutStyle {
+        if let Some(math_style) = self.math_style {
+            style.math_style = math_style;
+        }
+        if let Some(direction) = self.direction {
+            style.direction = direction;
+        }
+        if let Some(script_level) = self.script_level {
+            style.script_level = script_level.apply(style.script_level);
+        }
+        if let Some(math_size) = self.math_size {
+            let size = match math_size.unit {
+                // a percentage is relative to the surrounding size
+                LengthUnit::Percent => math_size.value / 100.0,
+                _ => math_size.to_unscaled_font_units(shaper) as f32 / shaper.em_size() as f32,
+            };
+            style.scale = match math_size.unit {
+                // relative sizes scale the surrounding size
+                LengthUnit::Em | LengthUnit::Ex | LengthUnit::MathUnit | LengthUnit::Percent => {
+                    style.scale.combine(ScaleFactor::from_scale_mult(size))
+                }
+                // absolute sizes are reached regardless of the script level
+                _ => {
+                    let script_scale = shaper.scale_factor(style.with_scale(ScaleFactor::new(100)));
+                    ScaleFactor::from_scale_mult(size / script_scale.as_scale_mult())
+                }
+            };
+        }
+        style
+    }
+}
+
+impl MathLayout for Padded {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        let mut content = self.content.layout(options);
+        let width = content.advance_width();
+        let extents = content.extents();
+
+        let resolve = |length: Option<PaddedLength>, original: i32| -> i32 {
+            let length = match length {
+                Some(length) => length,
+                None => return original,
+            };
+            let value = match length.value {
+                PaddedValue::Length(length) => length.to_font_units_of(original, options),
+                PaddedValue::Dimension(factor, unit) => {
+                    let dimension = match unit {
+                        PseudoUnit::Width => width,
+                        PseudoUnit::Height => extents.ascent,
+                        PseudoUnit::Depth => extents.descent,
+                    };
+                    (dimension as f32 * factor) as i32
+                }
+            };
+            if length.is_increment {
+                original + value
+            } else {
+                value
+            }
+        };
+
+        let new_width = max(0, resolve(self.width, width));
+        let new_ascent = max(0, resolve(self.height, extents.ascent));
+        let new_descent = max(0, resolve(self.depth, extents.descent));
+        content.origin.x = resolve(self.lspace, 0);
+        content.origin.y = -resolve(self.voffset, 0);
+
+        let mut result = MathBox::with_vec(vec![content], options.user_data);
+        result.metrics.advance_width = new_width;
+        result.metrics.extents = Extents::new(0, new_width, new_ascent, new_descent);
+        result.metrics.italic_correction = 0;
+        result
+    }
+
+    fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        self.content.operator_properties(options)
+    }
+}
+
+impl MathLayout for Phantom {
+    fn layout(&self, options: LayoutOptions) -> MathBox {
+        let content = self.content.layout(options);
+        let mut phantom = MathBox::empty(content.extents(), options.user_data);
+        phantom.metrics = Metrics::from_metrics(&content);
+        phantom
+    }
+
+    fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        self.content.operator_properties(options)
+    }
+}
+
 impl Operator {
+    /// Returns the size of the operator before it is stretched and the direction in which it
+    /// grows. Operators that only have horizontal variants are stretched horizontally.
+    fn stretch_properties(&self, options: LayoutOptions) -> StretchProperties {
+        let unstretched = self.field.layout(options);
+        let shaper = options.shaper;
+        let horizontal = unstretched.first_glyph().map(|(glyph, _)| {
+            shaper.is_stretchable(glyph.glyph_code, true)
+                && !shaper.is_stretchable(glyph.glyph_code, false)
+        }) == Some(true);
+        let extents = unstretched.extents();
+        let intrinsic_size = if horizontal {
+            unstretched.advance_width()
+        } else {
+            extents.ascent + extents.descent
+        };
+        StretchProperties {
+            intrinsic_size: max(0, intrinsic_size) as u32,
+            horizontal,
+        }
+    }
+
     fn layout_stretchy(
         &self,
         needed_height: u32,
         needed_width: u32,
         options: LayoutOptions,
     ) -> MathBox {
-        match self.field {
+        let glyph_code = match self.field {
             Field::Unicode(ref string) => {
                 let shape_result = options.shaper.shape(
                     string,
                     options.style.no_flat_accent_style(),
                     options.user_data,
                 );
-                let first_glyph = match shape_result.first_glyph() {
-                    Some((glyph, _scale)) => glyph,
+                match shape_result.first_glyph() {
+                    Some((glyph, _scale)) => glyph.glyph_code,
                     None => return MathBox::empty(Extents::default(), options.user_data),
-                };
-
-                if needed_width > 0 && options.shaper.is_stretchable(first_glyph.glyph_code, true) {
-                    return options.shaper.stretch_glyph(
-                        first_glyph.glyph_code,
-                        true,
-                        needed_width,
-                        options.style,
-                        options.user_data,
-                    );
                 }
+            }
+            Field::Glyph(ref glyph) => glyph.glyph_code,
+            Field::Empty => return MathBox::empty(Extents::default(), options.user_data),
+        };
 
-                if needed_height > 0 && options.shaper.is_stretchable(first_glyph.glyph_code, false)
-                {
-                    let mut math_box = options.shaper.stretch_glyph(
-                        first_glyph.glyph_code,
-                        false,
-                        needed_height,
-                        options.style,
-                        options.user_data,
-                    );
-                    let stretch_constraints =
-                        self.stretch_constraints.unwrap_or(StretchConstraints {
-                            symmetric: true,
-                            ..Default::default()
-                        });
-                    if stretch_constraints.symmetric {
-                        let axis_height = options.shaper.math_constant(MathConstant::AxisHeight);
-                        let shift_up = (math_box.extents().descent - math_box.extents().ascent) / 2
-                            + axis_height;
-                        math_box.origin.y -= shift_up;
-                    } else {
-                        let stretch_size = options.stretch_size.unwrap_or_default();
-                        let excess_ascent = math_box.extents().ascent - stretch_size.ascent;
-                        let excess_descent = math_box.extents().descent - stretch_size.descent;
-                        math_box.origin.y += (excess_ascent - excess_descent) / 2;
-                    }
-
-                    return math_box;
-                }
+        if needed_width > 0 && options.shaper.is_stretchable(glyph_code, true) {
+            return options.shaper.stretch_glyph(
+                glyph_code,
+                true,
+                needed_width,
+                options.style,
+                options.user_data,
+            );
+        }
 
-                // fallback
-                options
-                    .shaper
-                    .shape(string, options.style, options.user_data)
+        if needed_height > 0 && options.shaper.is_stretchable(glyph_code, false) {
+            let mut math_box = options.shaper.stretch_glyph(
+                glyph_code,
+                false,
+                needed_height,
+                options.style,
+                options.user_data,
+            );
+            let stretch_constraints = self.stretch_constraints.unwrap_or(StretchConstraints {
+                symmetric: true,
+                ..Default::default()
+            });
+            if stretch_constraints.symmetric {
+                let axis_height = options.math_constant(MathConstant::AxisHeight);
+                let shift_up =
+                    (math_box.extents().descent - math_box.extents().ascent) / 2 + axis_height;
+                math_box.origin.y -= shift_up;
+            } else {
+                let stretch_size = options.stretch_size.unwrap_or_default();
+                let excess_ascent = math_box.extents().ascent - stretch_size.ascent;
+                let excess_descent = math_box.extents().descent - stretch_size.descent;
+                math_box.origin.y += (excess_ascent - excess_descent) / 2;
             }
-            _ => unimplemented!(),
+
+            return math_box;
         }
+
+        // fallback
+        self.field.layout(options)
     }
 }
 
@@ -779,14 +1714,17 @@ impl MathLayout for Operator {
     fn layout(&self, options: LayoutOptions) -> MathBox {
         match (options.stretch_size, self.stretch_constraints) {
             (Some(stretch_size), Some(stretch_constraints)) => {
-                let min_size = stretch_constraints
-                    .min_size
-                    .map(|size| size.to_font_units(options.shaper));
-                let max_size = stretch_constraints
-                    .max_size
-                    .map(|size| size.to_font_units(options.shaper));
+                let resolve = |size: Length| match size.unit {
+                    LengthUnit::NormalSize => {
+                        let normal_size = self.stretch_properties(options).intrinsic_size;
+                        (normal_size as f32 * size.value) as i32
+                    }
+                    _ => size.to_font_units(options),
+                };
+                let min_size = stretch_constraints.min_size.map(resolve);
+                let max_size = stretch_constraints.max_size.map(resolve);
                 let mut needed_height = if stretch_constraints.symmetric {
-                    let axis_height = options.shaper.math_constant(MathConstant::AxisHeight);
+                    let axis_height = options.math_constant(MathConstant::AxisHeight);
                     max(
                         stretch_size.ascent - axis_height,
                         axis_height + stretch_size.descent,
@@ -800,11 +1738,9 @@ impl MathLayout for Operator {
             }
             _ => {
                 if self.is_large_op && options.style.math_style == MathStyle::Display {
-                    let display_min_height = (options
-                        .shaper
-                        .math_constant(MathConstant::DisplayOperatorMinHeight)
-                        as f32
-                        * 1.42) as i32;
+                    let display_min_height =
+                        (options.math_constant(MathConstant::DisplayOperatorMinHeight) as f32
+                            * 1.42) as i32;
                     self.layout_stretchy(display_min_height as u32, 0, options)
                 } else {
                     self.field.layout(options)
@@ -814,14 +1750,19 @@ impl MathLayout for Operator {
     }
 
     fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
+        let leading_space = self.leading_space.to_font_units(options);
+        let trailing_space = self.trailing_space.to_font_units(options);
         Some(OperatorProperties {
             stretch_properties: self
                 .stretch_constraints
-                .as_ref()
-                .map(|_| Default::default()),
-            leading_space: self.leading_space.to_font_units(options.shaper),
-            trailing_space: self.trailing_space.to_font_units(options.shaper),
+                .map(|_| self.stretch_properties(options)),
+            leading_space,
+            trailing_space,
             is_large_op: self.is_large_op,
+            atom_class: self.atom_class,
+            line_break: self.line_break,
+            line_break_style: self.line_break_style,
+            indent_align: self.indent_align,
         })
     }
 }
@@ -830,9 +1771,9 @@ impl MathLayout for MathSpace {
     fn layout(&self, options: LayoutOptions) -> MathBox {
         let extents = Extents {
             left_side_bearing: 0,
-            width: self.width.to_font_units(options.shaper),
-            ascent: self.ascent.to_font_units(options.shaper),
-            descent: self.descent.to_font_units(options.shaper),
+            width: self.width.to_font_units(options),
+            ascent: self.ascent.to_font_units(options),
+            descent: self.descent.to_font_units(options),
         };
         MathBox::empty(extents, options.user_data)
     }
@@ -854,6 +1795,12 @@ impl MathLayout for Option<MathExpression> {
 
 impl MathLayout for MathItem {
     fn layout(&self, options: LayoutOptions) -> MathBox {
+        // only rows can be broken into lines, but a change of style does not keep the row inside
+        // of it from being the outermost one
+        let options = match *self {
+            MathItem::List(_) | MathItem::Styled(_) | MathItem::Semantics(_) => options,
+            _ => options.max_width(None),
+        };
         match *self {
             MathItem::Field(ref field) => field.layout(options),
             MathItem::Space(ref space) => space.layout(options),
@@ -863,6 +1810,16 @@ impl MathLayout for MathItem {
             MathItem::Root(ref root) => root.layout(options),
             MathItem::Operator(ref operator) => operator.layout(options),
             MathItem::List(ref list) => list.layout(options),
+            MathItem::Table(ref table) => table.layout(options),
+            MathItem::Enclosure(ref enclosure) => enclosure.layout(options),
+            MathItem::Styled(ref styled) => styled.layout(options),
+            MathItem::MultiScripts(ref multiscripts) => multiscripts.layout(options),
+            MathItem::Padded(ref padded) => padded.layout(options),
+            MathItem::Phantom(ref phantom) => phantom.layout(options),
+            MathItem::Semantics(ref semantics) => semantics.content.layout(options),
+            MathItem::AlignMark | MathItem::AlignGroup(_) => {
+                MathBox::empty(Extents::default(), options.user_data)
+            }
             MathItem::Other(ref other) => other.layout(options),
         }
     }
@@ -877,6 +1834,14 @@ impl MathLayout for MathItem {
             MathItem::List(ref list) => (&list[..]).operator_properties(options),
             MathItem::Root(ref root) => root.operator_properties(options),
             MathItem::Operator(ref operator) => operator.operator_properties(options),
+            MathItem::Table(ref table) => table.operator_properties(options),
+            MathItem::Enclosure(ref enclosure) => enclosure.operator_properties(options),
+            MathItem::Styled(ref styled) => styled.operator_properties(options),
+            MathItem::MultiScripts(ref multiscripts) => multiscripts.operator_properties(options),
+            MathItem::Padded(ref padded) => padded.operator_properties(options),
+            MathItem::Phantom(ref phantom) => phantom.operator_properties(options),
+            MathItem::Semantics(ref semantics) => semantics.content.operator_properties(options),
+            MathItem::AlignMark | MathItem::AlignGroup(_) => None,
             MathItem::Other(ref other) => other.operator_properties(options),
         }
     }
@@ -891,8 +1856,13 @@ impl MathLayout for MathExpression {
         let old_style = options.style;
         let new_style = (options.style_provider)(old_style, options.user_data);
 
-        self.item
-            .layout(options.style(new_style).user_data(self.get_user_data()))
+        let mut math_box = self
+            .item
+            .layout(options.style(new_style).user_data(self.get_user_data()));
+        if new_style.direction != old_style.direction {
+            math_box.mirror();
+        }
+        math_box
     }
 
     fn operator_properties(&self, options: LayoutOptions) -> Option<OperatorProperties> {
@@ -0,0 +1,360 @@
+//! Breaking of rows that are too wide into several lines.
+//!
+//! Lines can only be broken at operators. The break points are chosen such that the sum of their
+//! penalties and of the badness of the resulting lines is minimal. The badness of a line grows
+//! with the amount of unused space at its end, which keeps all lines similarly wide.
+
+use super::alignment::{mark_position, Alignment};
+use super::layout::{LayoutOptions, MathLayout, OperatorProperties};
+use super::math_box::{Extents, MathBox, MathBoxMetrics};
+use super::shaper::MathConstant;
+use super::spacing::list_spacing;
+use super::stretchy::{layout_list_element, layout_strechy_list};
+use crate::types::{
+    AtomClass, HorizontalAlign, LineBreak, LineBreakStyle, MathExpression, MathItem,
+};
+
+// As in TeX a break at a relation is preferred to a break at a binary operator.
+const RELATION_PENALTY: i64 = 500;
+const BINARY_OPERATOR_PENALTY: i64 = 700;
+const GOOD_BREAK_PENALTY: i64 = 0;
+const BAD_BREAK_PENALTY: i64 = 10000;
+/// Added to the penalty of an operator for every row that it is nested in.
+const NESTING_PENALTY: i64 = 300;
+/// The badness of a line that has no space left at all.
+const MAX_BADNESS: f64 = 1000.0;
+/// Added to the badness of a line that is wider than the available width.
+const OVERFULL_PENALTY: i64 = 1_000_000;
+
+/// An element of a row together with the information needed to break the row.
+pub struct LineItem {
+    pub math_box: Option<MathBox>,
+    /// A second layout of the element if it is repeated at the start of the next line.
+    duplicate: Option<MathBox>,
+    /// The space in front of an operator, which is dropped at the start of a line.
+    pub leading_space: i32,
+    /// The space after an operator, which is dropped at the end of a line.
+    trailing_space: i32,
+    break_point: Option<BreakPoint>,
+    pub alignment: Option<Alignment>,
+}
+
+#[derive(Copy, Clone)]
+struct BreakPoint {
+    index: usize,
+    penalty: i64,
+    is_forced: bool,
+    style: LineBreakStyle,
+    indent_align: Option<HorizontalAlign>,
+}
+
+impl BreakPoint {
+    /// The index of the first element that is not on the line ending at this break.
+    fn end(&self) -> usize {
+        match self.style {
+            LineBreakStyle::Before => self.index,
+            LineBreakStyle::After | LineBreakStyle::Duplicate => self.index + 1,
+        }
+    }
+
+    /// The index of the first element on the line starting at this break.
+    fn next_start(&self) -> usize {
+        match self.style {
+            LineBreakStyle::Before | LineBreakStyle::Duplicate => self.index,
+            LineBreakStyle::After => self.index + 1,
+        }
+    }
+}
+
+/// Returns true if an operator in `list` or in one of the rows nested in it forces a line break.
+pub fn has_forced_break(list: &[MathExpression], options: LayoutOptions) -> bool {
+    list.iter().any(|expr| match *expr.item {
+        MathItem::List(ref list) => has_forced_break(list, options),
+        _ => {
+            expr.operator_properties(options)
+                .map(|properties| properties.line_break)
+                == Some(LineBreak::NewLine)
+        }
+    })
+}
+
+/// Lays out `list` as several lines that are at most `max_width` wide if possible.
+///
+/// Rows nested inside of `list` are broken as well but breaking them is penalized. Returns `None`
+/// if the row cannot be broken.
+pub fn layout_lines(
+    list: &[MathExpression],
+    max_width: i32,
+    options: LayoutOptions,
+) -> Option<MathBox> {
+    let mut items = Vec::new();
+    collect_items(list, options, 0, &mut items);
+    let break_points = items
+        .iter()
+        .filter_map(|item| item.break_point)
+        // a line must not be empty
+        .filter(|point| point.end() > 0 && point.next_start() < items.len())
+        .collect::<Vec<_>>();
+    let breaks = find_breaks(&items, &break_points, max_width)?;
+    if breaks.is_empty() {
+        return None;
+    }
+
+    let leading = options.math_constant(MathConstant::MathLeading);
+    let mut lines = Vec::with_capacity(breaks.len() + 1);
+    let mut start = 0;
+    let mut start_point: Option<BreakPoint> = None;
+    for end_point in breaks.iter().map(Some).chain(Some(None)) {
+        let end = end_point.map_or(items.len(), BreakPoint::end);
+        let trim_start = start_point.is_some();
+        let trim_end = end_point.is_some();
+        let (positions, width) = line_positions(&items[start..end], trim_start, trim_end);
+        let mark = mark_position(&items[start..end], &positions);
+
+        let mut boxes = Vec::with_capacity(end - start);
+        for (index, x) in (start..end).zip(positions) {
+            let item = &mut items[index];
+            let is_duplicate = index == start
+                && start_point.map(|point| point.style) == Some(LineBreakStyle::Duplicate);
+            let math_box = if is_duplicate {
+                item.duplicate.take()
+            } else {
+                item.math_box.take()
+            };
+            let mut math_box = math_box.expect("element is placed twice");
+            math_box.origin.x += x;
+            boxes.push(math_box);
+        }
+        let mut line = MathBox::with_vec(boxes, options.user_data);
+        line.metrics.advance_width = width;
+        let align = start_point.and_then(|point| point.indent_align);
+        lines.push((line, align, mark));
+
+        start = end_point.map_or(end, BreakPoint::next_start);
+        start_point = end_point.copied();
+    }
+
+    // lines with alignment marks are shifted such that their marks are placed below each other
+    let max_mark = lines.iter().filter_map(|&(_, _, mark)| mark).max();
+    let mark_offset = |mark: Option<i32>| Some(max_mark? - mark?);
+    let total_width = lines
+        .iter()
+        .map(|&(ref line, _, mark)| mark_offset(mark).unwrap_or(0) + line.advance_width())
+        .max()
+        .unwrap_or_default();
+    let mut baseline = 0;
+    let mut previous_descent = None;
+    let mut boxes = Vec::with_capacity(lines.len());
+    for (mut line, align, mark) in lines {
+        let extents = line.extents();
+        if let Some(previous_descent) = previous_descent {
+            baseline += previous_descent + leading + extents.ascent;
+        }
+        previous_descent = Some(extents.descent);
+        line.origin.y = baseline;
+        line.origin.x = match (mark_offset(mark), align) {
+            (Some(offset), _) => offset,
+            (None, None) | (None, Some(HorizontalAlign::Left)) => 0,
+            (None, Some(HorizontalAlign::Center)) => (total_width - line.advance_width()) / 2,
+            (None, Some(HorizontalAlign::Right)) => total_width - line.advance_width(),
+        };
+        boxes.push(line);
+    }
+    Some(MathBox::with_vec(boxes, options.user_data))
+}
+
+/// Lays out the elements of `list` and of all rows nested in it.
+pub fn collect_items(
+    list: &[MathExpression],
+    options: LayoutOptions,
+    depth: i64,
+    items: &mut Vec<LineItem>,
+) {
+    // the row is laid out as a whole first, so that stretchy operators get the same size as
+    // without line breaks
+    let boxes = layout_strechy_list(list, options);
+    let spacing = list_spacing(list, options);
+    for ((expr, math_box), spacing) in list.iter().zip(boxes).zip(spacing) {
+        let (leading_space, trailing_space) = spacing.unwrap_or_default();
+        if let MathItem::List(ref nested) = *expr.item {
+            let style = (options.style_provider)(options.style, expr.get_user_data());
+            let nested_options = options.style(style).user_data(expr.get_user_data());
+            push_space(items, leading_space, expr.get_user_data());
+            collect_items(nested, nested_options, depth + 1, items);
+            push_space(items, trailing_space, expr.get_user_data());
+            continue;
+        }
+        let properties = expr.operator_properties(options);
+        let break_point = properties.and_then(|properties| {
+            let penalty = penalty(&properties)?;
+            // alignment marks in front of the operator move to the new line together with it
+            let marks = match properties.line_break_style {
+                LineBreakStyle::Before => items
+                    .iter()
+                    .rev()
+                    .take_while(|item| item.alignment == Some(Alignment::Mark))
+                    .count(),
+                LineBreakStyle::After | LineBreakStyle::Duplicate => 0,
+            };
+            Some(BreakPoint {
+                index: items.len() - marks,
+                penalty: penalty + depth * NESTING_PENALTY,
+                is_forced: properties.line_break == LineBreak::NewLine,
+                style: properties.line_break_style,
+                indent_align: properties.indent_align,
+            })
+        });
+        let duplicate = match break_point {
+            Some(point) if point.style == LineBreakStyle::Duplicate => {
+                Some(layout_list_element(expr, spacing, options))
+            }
+            _ => None,
+        };
+        let alignment = match *expr.item {
+            MathItem::AlignMark => Some(Alignment::Mark),
+            MathItem::AlignGroup(group) => Some(Alignment::Group(group.group_align)),
+            _ => None,
+        };
+        items.push(LineItem {
+            math_box: Some(math_box),
+            duplicate,
+            leading_space,
+            trailing_space,
+            break_point,
+            alignment,
+        });
+    }
+}
+
+/// Adds the space around a nested row as an element of its own.
+fn push_space(items: &mut Vec<LineItem>, width: i32, user_data: u64) {
+    if width == 0 {
+        return;
+    }
+    items.push(LineItem {
+        math_box: Some(MathBox::empty(Extents::new(0, width, 0, 0), user_data)),
+        duplicate: None,
+        leading_space: 0,
+        trailing_space: 0,
+        break_point: None,
+        alignment: None,
+    });
+}
+
+/// Returns the penalty for breaking at an operator or `None` if the line must not be broken
+/// there.
+fn penalty(properties: &OperatorProperties) -> Option<i64> {
+    let automatic = match properties.atom_class {
+        AtomClass::Rel => Some(RELATION_PENALTY),
+        AtomClass::Bin => Some(BINARY_OPERATOR_PENALTY),
+        _ => None,
+    };
+    match properties.line_break {
+        LineBreak::Auto => automatic,
+        LineBreak::NewLine => Some(0),
+        LineBreak::NoBreak => None,
+        LineBreak::GoodBreak => Some(GOOD_BREAK_PENALTY),
+        LineBreak::BadBreak => Some(BAD_BREAK_PENALTY),
+    }
+}
+
+/// Returns the horizontal positions of `items` on a line and the width of the line.
+pub fn line_positions(items: &[LineItem], trim_start: bool, trim_end: bool) -> (Vec<i32>, i32) {
+    let mut cursor = 0;
+    let mut trim_start = trim_start;
+    let mut positions = Vec::with_capacity(items.len());
+    let mut previous_italic_correction = 0;
+    for item in items {
+        // alignment marks may precede the operator whose space is dropped
+        if trim_start && item.alignment.is_none() {
+            cursor -= item.leading_space;
+            trim_start = false;
+        }
+        let math_box = item
+            .math_box
+            .as_ref()
+            .or(item.duplicate.as_ref())
+            .expect("element has been placed already");
+        // apply italic correction if current glyph is upright
+        if math_box.italic_correction() == 0 {
+            cursor += previous_italic_correction;
+        }
+        positions.push(cursor);
+        cursor += math_box.advance_width();
+        previous_italic_correction = math_box.italic_correction();
+    }
+    match items.last() {
+        Some(item) if trim_end => cursor -= item.trailing_space,
+        _ => {}
+    }
+    (positions, cursor)
+}
+
+/// Chooses the break points with the smallest total cost.
+///
+/// Returns `None` if there is no way to break the row that honours all forced breaks.
+fn find_breaks(
+    items: &[LineItem],
+    break_points: &[BreakPoint],
+    max_width: i32,
+) -> Option<Vec<BreakPoint>> {
+    let max_width = std::cmp::max(max_width, 1);
+    let badness = |width: i32, is_last: bool| -> i64 {
+        if width > max_width {
+            OVERFULL_PENALTY + i64::from(width - max_width)
+        } else if is_last {
+            0
+        } else {
+            let slack = f64::from(max_width - width) / f64::from(max_width);
+            (MAX_BADNESS * slack * slack) as i64
+        }
+    };
+
+    // `costs[node]` is the smallest cost of breaking the row up to the start of a line. The
+    // first node is the start of the row, the others follow the break points.
+    let mut costs: Vec<Option<(i64, usize)>> = vec![None; break_points.len() + 1];
+    costs[0] = Some((0, 0));
+    let mut best_end: Option<(i64, usize)> = None;
+    for node in 0..costs.len() {
+        let cost = match costs[node] {
+            Some((cost, _)) => cost,
+            None => continue,
+        };
+        let (start, trim_start) = match node {
+            0 => (0, false),
+            _ => (break_points[node - 1].next_start(), true),
+        };
+        for (index, point) in break_points.iter().enumerate().skip(node) {
+            if point.end() > start && point.next_start() > start {
+                let (_, width) = line_positions(&items[start..point.end()], trim_start, true);
+                let total = cost + badness(width, false) + point.penalty;
+                match costs[index + 1] {
+                    Some((best, _)) if best <= total => {}
+                    _ => costs[index + 1] = Some((total, node)),
+                }
+            }
+            // a line cannot extend beyond a forced break
+            if point.is_forced {
+                break;
+            }
+        }
+        let is_forced_later = break_points[node..].iter().any(|point| point.is_forced);
+        if !is_forced_later {
+            let (_, width) = line_positions(&items[start..], trim_start, false);
+            let total = cost + badness(width, true);
+            match best_end {
+                Some((best, _)) if best <= total => {}
+                _ => best_end = Some((total, node)),
+            }
+        }
+    }
+
+    let (_, mut node) = best_end?;
+    let mut breaks = Vec::new();
+    while node > 0 {
+        breaks.push(break_points[node - 1]);
+        node = costs[node].expect("no path to break point").1;
+    }
+    breaks.reverse();
+    Some(breaks)
+}
@@ -1,4 +1,4 @@
-use crate::types::PercentValue;
+use crate::types::{Color, ScaleFactor};
 use std::cmp::{max, min};
 use std::default::Default;
 use std::ops::{Add, Div, Mul, Sub};
@@ -51,9 +51,9 @@ impl Div<i32> for Vector<i32> {
         }
     }
 }
-impl Mul<PercentValue> for Vector<i32> {
+impl Mul<ScaleFactor> for Vector<i32> {
     type Output = Vector<i32>;
-    fn mul(self, _rhs: PercentValue) -> Vector<i32> {
+    fn mul(self, _rhs: ScaleFactor) -> Vector<i32> {
         Vector {
             x: self.x * _rhs,
             y: self.y * _rhs,
@@ -118,9 +118,9 @@ impl Div<i32> for Extents<i32> {
         }
     }
 }
-impl Mul<PercentValue> for Extents<i32> {
+impl Mul<ScaleFactor> for Extents<i32> {
     type Output = Extents<i32>;
-    fn mul(self, _rhs: PercentValue) -> Extents<i32> {
+    fn mul(self, _rhs: ScaleFactor) -> Extents<i32> {
         Extents {
             left_side_bearing: self.left_side_bearing * _rhs,
             width: self.width * _rhs,
@@ -205,9 +205,9 @@ impl Div<i32> for Bounds {
     }
 }
 
-impl Mul<PercentValue> for Bounds {
+impl Mul<ScaleFactor> for Bounds {
     type Output = Bounds;
-    fn mul(self, _rhs: PercentValue) -> Bounds {
+    fn mul(self, _rhs: ScaleFactor) -> Bounds {
         Bounds {
             origin: self.origin * _rhs,
             extents: self.extents * _rhs,
@@ -270,12 +270,41 @@ pub enum Drawable {
         /// The size at which these glyphs should be rendered relative to their normal size.
         ///
         /// This is used to render subscripts and superscripts in a smaller size.
-        scale: PercentValue,
+        scale: ScaleFactor,
     },
     Line {
         vector: Vector<i32>,
         thickness: u32,
     },
+    /// A line with an arrowhead at its end.
+    Arrow {
+        vector: Vector<i32>,
+        thickness: u32,
+        /// The length of the arrowhead along the line. The arrowhead is as wide as it is long.
+        head_size: u32,
+    },
+    /// The outline of a rectangle whose top left corner is at the origin of the box.
+    ///
+    /// The outline is drawn inside of the rectangle.
+    Rect {
+        size: Vector<i32>,
+        /// If this is non-zero the corners of the rectangle are rounded with this radius.
+        corner_radius: u32,
+        thickness: u32,
+    },
+    /// A rectangle whose top left corner is at the origin of the box and which is filled with
+    /// the colour of the box, e.g. the background of a styled expression.
+    FilledRect {
+        size: Vector<i32>,
+    },
+    /// The outline of an ellipse inscribed in a rectangle whose top left corner is at the origin
+    /// of the box.
+    ///
+    /// The outline is drawn inside of the rectangle.
+    Ellipse {
+        size: Vector<i32>,
+        thickness: u32,
+    },
 }
 
 impl MathBoxMetrics for Drawable {
@@ -284,7 +313,12 @@ impl MathBoxMetrics for Drawable {
             Drawable::Glyphs { glyphs, scale } => {
                 glyphs.iter().map(|g| g.advance_width).sum::<i32>() * *scale
             }
-            Drawable::Line { ref vector, .. } => vector.x,
+            Drawable::Line { ref vector, .. } | Drawable::Arrow { ref vector, .. } => {
+                max(0, vector.x)
+            }
+            Drawable::Rect { ref size, .. }
+            | Drawable::FilledRect { ref size }
+            | Drawable::Ellipse { ref size, .. } => size.x,
         }
     }
     fn extents(&self) -> Extents<i32> {
@@ -326,12 +360,20 @@ impl MathBoxMetrics for Drawable {
                     descent: max_descent,
                 }
             }
-            Drawable::Line { ref vector, .. } => Extents {
-                left_side_bearing: 0,
-                width: vector.x,
+            Drawable::Line { ref vector, .. } | Drawable::Arrow { ref vector, .. } => Extents {
+                left_side_bearing: min(0, vector.x),
+                width: vector.x.abs(),
                 ascent: max(0, -vector.y),
                 descent: max(0, vector.y),
             },
+            Drawable::Rect { ref size, .. }
+            | Drawable::FilledRect { ref size }
+            | Drawable::Ellipse { ref size, .. } => Extents {
+                left_side_bearing: 0,
+                width: size.x,
+                ascent: 0,
+                descent: size.y,
+            },
         }
     }
 
@@ -341,7 +383,7 @@ impl MathBoxMetrics for Drawable {
                 .last()
                 .map(|g| g.italic_correction * *scale)
                 .unwrap_or_default(),
-            Drawable::Line { .. } => 0,
+            _ => 0,
         }
     }
 
@@ -376,6 +418,9 @@ pub struct MathBox {
     pub origin: Vector<i32>,
     pub(crate) metrics: Metrics,
     pub content: MathBoxContent,
+    /// The colour in which the drawable content is painted. Renderers choose the colour of boxes
+    /// without one, which is usually black.
+    color: Option<Color>,
     user_data: u64,
 }
 
@@ -475,10 +520,81 @@ impl MathBox {
             content: content,
             metrics,
             origin: Vector::default(),
+            color: None,
             user_data,
         }
     }
 
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    /// Paints all drawable content inside of this box that has no colour yet in `color`.
+    pub fn set_default_color(&mut self, color: Color) {
+        match self.content {
+            MathBoxContent::Boxes(ref mut boxes) => {
+                for math_box in boxes {
+                    math_box.set_default_color(color);
+                }
+            }
+            MathBoxContent::Drawable(_) => {
+                self.color.get_or_insert(color);
+            }
+            MathBoxContent::Empty(_) => {}
+        }
+    }
+
+    /// Puts a rectangle of the colour `background` behind `content`. The rectangle covers the
+    /// logical extents of the content and the metrics of the result are those of the content.
+    pub fn with_background(content: MathBox, background: Color, user_data: u64) -> Self {
+        let metrics = Metrics::from_metrics(&content);
+        let extents = content.extents();
+        let size = Vector {
+            x: content.advance_width(),
+            y: extents.ascent + extents.descent,
+        };
+        let mut rect = MathBox::with_content(
+            MathBoxContent::Drawable(Drawable::FilledRect { size }),
+            user_data,
+        );
+        rect.origin = content.origin
+            + Vector {
+                x: 0,
+                y: -extents.ascent,
+            };
+        rect.color = Some(background);
+        let mut math_box = MathBox::with_vec(vec![rect, content], user_data);
+        math_box.metrics = metrics;
+        math_box
+    }
+
+    /// Mirrors the content of this box horizontally inside of its advance width, which turns a
+    /// left-to-right layout into a right-to-left layout. Glyph runs keep their order since the
+    /// shaper already orders them for the direction of the text.
+    pub(crate) fn mirror(&mut self) {
+        let width = self.advance_width();
+        if let MathBoxContent::Boxes(ref mut boxes) = self.content {
+            for math_box in boxes {
+                match math_box.content {
+                    MathBoxContent::Drawable(Drawable::Line { ref mut vector, .. })
+                    | MathBoxContent::Drawable(Drawable::Arrow { ref mut vector, .. }) => {
+                        // lines are drawn from their origin, which becomes the other end
+                        vector.x = -vector.x;
+                        math_box.origin.x = width - math_box.origin.x;
+                        math_box.metrics = Metrics::from_metrics(&math_box.content);
+                    }
+                    _ => {
+                        math_box.origin.x = width - math_box.origin.x - math_box.advance_width();
+                        math_box.mirror();
+                    }
+                }
+            }
+        }
+        let extents = &mut self.metrics.extents;
+        extents.left_side_bearing = width - extents.left_side_bearing - extents.width;
+        self.metrics.top_accent_attachment = width - self.metrics.top_accent_attachment;
+    }
+
     pub fn empty(extents: Extents<i32>, user_data: u64) -> Self {
         MathBox::with_content(MathBoxContent::Empty(extents), user_data)
     }
@@ -495,7 +611,59 @@ impl MathBox {
         math_box
     }
 
-    pub fn with_glyphs(glyphs: Vec<MathGlyph>, scale: PercentValue, user_data: u64) -> Self {
+    pub fn with_arrow(
+        from: Vector<i32>,
+        to: Vector<i32>,
+        thickness: u32,
+        head_size: u32,
+        user_data: u64,
+    ) -> Self {
+        let mut math_box = MathBox::with_content(
+            MathBoxContent::Drawable(Drawable::Arrow {
+                vector: to - from,
+                thickness,
+                head_size,
+            }),
+            user_data,
+        );
+        math_box.origin = from;
+        math_box
+    }
+
+    pub fn with_rect(
+        origin: Vector<i32>,
+        size: Vector<i32>,
+        corner_radius: u32,
+        thickness: u32,
+        user_data: u64,
+    ) -> Self {
+        let mut math_box = MathBox::with_content(
+            MathBoxContent::Drawable(Drawable::Rect {
+                size,
+                corner_radius,
+                thickness,
+            }),
+            user_data,
+        );
+        math_box.origin = origin;
+        math_box
+    }
+
+    pub fn with_ellipse(
+        origin: Vector<i32>,
+        size: Vector<i32>,
+        thickness: u32,
+        user_data: u64,
+    ) -> Self {
+        let mut math_box = MathBox::with_content(
+            MathBoxContent::Drawable(Drawable::Ellipse { size, thickness }),
+            user_data,
+        );
+        math_box.origin = origin;
+        math_box
+    }
+
+    pub fn with_glyphs(glyphs: Vec<MathGlyph>, scale: ScaleFactor, user_data: u64) -> Self {
         MathBox::with_content(
             MathBoxContent::Drawable(Drawable::Glyphs { glyphs, scale }),
             user_data,
@@ -518,7 +686,7 @@ impl MathBox {
     }
 
     /// recursive search for a glyph at the leftmost position
-    pub fn first_glyph(&self) -> Option<(MathGlyph, PercentValue)> {
+    pub fn first_glyph(&self) -> Option<(MathGlyph, ScaleFactor)> {
         match self.content() {
             MathBoxContent::Drawable(Drawable::Glyphs { glyphs, scale }) => {
                 glyphs.first().map(|&g| (g, *scale))
@@ -528,7 +696,7 @@ impl MathBox {
         }
     }
 
-    pub fn last_glyph(&self) -> Option<(MathGlyph, PercentValue)> {
+    pub fn last_glyph(&self) -> Option<(MathGlyph, ScaleFactor)> {
         match self.content() {
             MathBoxContent::Drawable(Drawable::Glyphs { glyphs, scale }) => {
                 glyphs.last().map(|g| (*g, *scale))
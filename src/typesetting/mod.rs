@@ -1,7 +1,10 @@
+mod alignment;
 mod layout;
+mod linebreak;
 pub mod math_box;
 mod multiscripts;
 pub mod shaper;
+mod spacing;
 mod stretchy;
 pub mod unicode_math;
 
@@ -20,16 +23,39 @@ pub fn layout_with_style<'a>(
     expression: &'a MathExpression,
     shaper: &'a impl MathShaper,
     style: impl Fn(LayoutStyle, u64) -> LayoutStyle,
+) -> MathBox {
+    // without a width limit only forced line breaks break the outermost row
+    layout_with_options(expression, shaper, style, Some(i32::MAX))
+}
+
+/// Lays out `expression` and breaks it into several lines if it is wider than `max_width` (given
+/// in font units).
+pub fn layout_with_width<'a>(
+    expression: &'a MathExpression,
+    shaper: &'a impl MathShaper,
+    max_width: i32,
+) -> MathBox {
+    layout_with_options(expression, shaper, |old, _| old, Some(max_width))
+}
+
+fn layout_with_options<'a>(
+    expression: &'a MathExpression,
+    shaper: &'a impl MathShaper,
+    style: impl Fn(LayoutStyle, u64) -> LayoutStyle,
+    max_width: Option<i32>,
 ) -> MathBox {
     let user_data = expression.get_user_data();
 
     let default_style = LayoutStyle {
         math_style: MathStyle::Display,
         script_level: 0,
+        scale: ScaleFactor::new(100),
         is_cramped: false,
         flat_accent: false,
         stretch_constraints: None,
         as_accent: false,
+        spacing: SpacingMode::OperatorDictionary,
+        direction: TextDirection::Ltr,
     };
 
     let new_style = style(default_style, user_data);
@@ -40,7 +66,12 @@ pub fn layout_with_style<'a>(
         style: new_style,
         stretch_size: None,
         user_data: expression.get_user_data(),
+        max_width,
     };
 
-    layout::layout_expression(expression, options)
+    let mut math_box = layout::layout_expression(expression, options);
+    if new_style.direction != default_style.direction {
+        math_box.mirror();
+    }
+    math_box
 }
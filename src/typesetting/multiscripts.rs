@@ -3,26 +3,25 @@ use std::cmp::max;
 use super::layout::LayoutOptions;
 use super::math_box::{MathBox, MathBoxMetrics};
 use super::shaper::{MathConstant, Position};
-use crate::types::CornerPosition;
+use crate::types::{CornerPosition, TextDirection};
 
 pub fn get_superscript_shift_up(
     superscript: &MathBox,
     nucleus: &MathBox,
     options: LayoutOptions,
 ) -> Position {
-    let shaper = options.shaper;
     let style = options.style;
-    let std_shift_up = shaper.math_constant(if style.is_cramped {
+    let std_shift_up = options.math_constant(if style.is_cramped {
         MathConstant::SuperscriptShiftUpCramped
     } else {
         MathConstant::SuperscriptShiftUp
     });
 
     let min_shift_up =
-        superscript.extents().descent + shaper.math_constant(MathConstant::SuperscriptBottomMin);
+        superscript.extents().descent + options.math_constant(MathConstant::SuperscriptBottomMin);
 
     let min_shift_from_baseline_drop =
-        nucleus.extents().ascent - shaper.math_constant(MathConstant::SuperscriptBaselineDropMax);
+        nucleus.extents().ascent - options.math_constant(MathConstant::SuperscriptBaselineDropMax);
 
     max(
         min_shift_from_baseline_drop,
@@ -35,13 +34,12 @@ pub fn get_subscript_shift_dn(
     nucleus: &MathBox,
     options: LayoutOptions,
 ) -> Position {
-    let shaper = options.shaper;
     let min_shift_dn_from_baseline_drop =
-        nucleus.extents().descent + shaper.math_constant(MathConstant::SubscriptBaselineDropMin);
+        nucleus.extents().descent + options.math_constant(MathConstant::SubscriptBaselineDropMin);
 
-    let std_shift_dn = shaper.math_constant(MathConstant::SubscriptShiftDown);
+    let std_shift_dn = options.math_constant(MathConstant::SubscriptShiftDown);
     let min_shift_dn =
-        subscript.extents().ascent - shaper.math_constant(MathConstant::SubscriptTopMax);
+        subscript.extents().ascent - options.math_constant(MathConstant::SubscriptTopMax);
 
     max(
         min_shift_dn_from_baseline_drop,
@@ -55,12 +53,11 @@ pub fn get_subsup_shifts(
     nucleus: &MathBox,
     options: LayoutOptions,
 ) -> (Position, Position) {
-    let (shaper, _style) = (options.shaper, options.style);
     let mut super_shift = get_superscript_shift_up(superscript, nucleus, options);
     let mut sub_shift = get_subscript_shift_dn(subscript, nucleus, options);
 
-    let subsup_gap_min = shaper.math_constant(MathConstant::SubSuperscriptGapMin);
-    let super_bottom_max = shaper.math_constant(MathConstant::SuperscriptBottomMaxWithSubscript);
+    let subsup_gap_min = options.math_constant(MathConstant::SubSuperscriptGapMin);
+    let super_bottom_max = options.math_constant(MathConstant::SuperscriptBottomMaxWithSubscript);
 
     let super_bottom = super_shift - superscript.extents().descent;
     let sub_top = -sub_shift + subscript.extents().ascent;
@@ -91,10 +88,11 @@ pub fn get_attachment_kern(
     let shaper = options.shaper;
     let mut kerning = 0;
 
+    // the glyphs of the nucleus and the attachment that are next to each other
     let nucleus_glyph = if attachment_position.is_left() {
-        nucleus.last_glyph()
-    } else {
         nucleus.first_glyph()
+    } else {
+        nucleus.last_glyph()
     };
 
     if let Some((nucleus_glyph, scale)) = nucleus_glyph {
@@ -117,12 +115,16 @@ pub fn get_attachment_kern(
                     attachment_shift - nucleus.extents().descent * scale;
                 (base_correction_height, attachment_correction_height)
             };
-            kerning += shaper.math_kerning(&nucleus_glyph, attachment_position, bch) * scale;
-            kerning += shaper.math_kerning(
-                &attachment_glyph,
-                attachment_position.diagonal_mirror(),
-                ach,
-            ) * attachment_scale;
+            // a right-to-left layout is mirrored after it has been laid out, so the corners of
+            // the (mirrored) glyphs are on the other side
+            let kerning_position = match options.style.direction {
+                TextDirection::Ltr => attachment_position,
+                TextDirection::Rtl => attachment_position.horizontal_mirror(),
+            };
+            kerning += shaper.math_kerning(&nucleus_glyph, kerning_position, bch) * scale;
+            kerning +=
+                shaper.math_kerning(&attachment_glyph, kerning_position.diagonal_mirror(), ach)
+                    * attachment_scale;
         }
     };
     kerning
@@ -141,14 +143,15 @@ pub fn position_attachment(
     let kern = get_attachment_kern(nucleus, attachment, attachment_position, shift, options);
 
     let italic_correction = match (nucleus_is_largeop, attachment_position.is_top()) {
+        _ if attachment_position.is_left() => 0,
         (true, false) => -nucleus.italic_correction(),
         (false, true) => nucleus.italic_correction(),
         _ => 0,
     };
 
     if attachment_position.is_left() {
+        attachment.origin.x = nucleus.origin.x - attachment.advance_width();
         attachment.origin.x -= kern;
-        unimplemented!();
     } else {
         attachment.origin.x = nucleus.origin.x + nucleus.advance_width() + italic_correction;
         attachment.origin.x += kern;
@@ -7,12 +7,12 @@ use std::cmp::min;
 
 pub use self::harfbuzz_rs::Position;
 use self::harfbuzz_rs::{
-    shape, Blob, Feature, Font, GlyphBuffer, GlyphInfo, GlyphPosition, HarfbuzzObject, Shared, Tag,
-    UnicodeBuffer,
+    shape, Blob, Direction, Feature, Font, GlyphBuffer, GlyphInfo, GlyphPosition, HarfbuzzObject,
+    Shared, Tag, UnicodeBuffer,
 };
 use self::harfbuzz_rs::{FontFuncs, Glyph};
 use super::math_box::{Drawable, Extents, MathBox, MathBoxContent, MathBoxMetrics, Vector};
-use crate::types::{CornerPosition, LayoutStyle, PercentValue};
+use crate::types::{CornerPosition, LayoutStyle, ScaleFactor, TextDirection};
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
@@ -118,6 +118,16 @@ pub trait MathShaper {
 
     fn shape(&self, string: &str, style: LayoutStyle, user_data: u64) -> MathBox;
 
+    /// Lays out a single glyph given by its font-specific glyph code. The glyph is scaled by
+    /// `scale` in addition to the scaling of the current script level.
+    fn shape_glyph(
+        &self,
+        glyph: u32,
+        scale: ScaleFactor,
+        style: LayoutStyle,
+        user_data: u64,
+    ) -> MathBox;
+
     /// Returns a pointer to an OpenType-Math table.
     fn get_math_table(&self) -> &[u8];
 
@@ -127,6 +137,26 @@ pub trait MathShaper {
         (self.em_size(), self.em_size())
     }
 
+    /// Returns the x-height of the font, which is taken from the height of the letter x.
+    fn x_height(&self) -> Position {
+        self.shape("x", LayoutStyle::new(), 0).extents().ascent
+    }
+
+    /// Returns the factor by which glyphs are scaled in the given style, which combines the
+    /// scaling of the script level with the scale of the style.
+    fn scale_factor(&self, style: LayoutStyle) -> ScaleFactor {
+        let percent = if style.script_level >= 1 {
+            if style.script_level >= 2 {
+                self.math_constant(MathConstant::ScriptScriptPercentScaleDown)
+            } else {
+                self.math_constant(MathConstant::ScriptPercentScaleDown)
+            }
+        } else {
+            100
+        };
+        ScaleFactor::new(percent as u32).combine(style.scale)
+    }
+
     fn is_stretchable(&self, glyph: u32, horizontal: bool) -> bool;
 
     fn stretch_glyph(
@@ -266,26 +296,21 @@ impl<'a> HarfbuzzShaper<'a> {
         }
     }
 
-    // Return the font's scale factor for a given script level.
-    fn scale_factor(&self, style: LayoutStyle) -> PercentValue {
-        let percent = if style.script_level >= 1 {
-            if style.script_level >= 2 {
-                self.math_constant(MathConstant::ScriptScriptPercentScaleDown)
-            } else {
-                self.math_constant(MathConstant::ScriptPercentScaleDown)
-            }
-        } else {
-            100
-        };
-        PercentValue::new(percent as u8)
-    }
-
     fn shape_with_style(&self, string: &str, style: LayoutStyle, user_data: u64) -> MathBox {
         let mut buffer = self.buffer.borrow_mut().take().unwrap();
 
         buffer = buffer.add_str(string);
         *self.buffer.borrow_mut() = Some(buffer);
-        self.do_shape(&self.font, style, user_data)
+        // numbers and words keep their order in right-to-left math, only the letters of
+        // right-to-left scripts like Arabic are shaped from right to left
+        let direction = if style.direction == TextDirection::Rtl
+            && string.chars().any(is_right_to_left_letter)
+        {
+            Direction::Rtl
+        } else {
+            Direction::Ltr
+        };
+        self.do_shape(&self.font, direction, style, user_data)
     }
 
     fn glyph_from_index(
@@ -297,15 +322,21 @@ impl<'a> HarfbuzzShaper<'a> {
         let buffer = self.buffer.borrow_mut().take().unwrap();
         let buffer = buffer.add(glyph_index, 0);
         *self.buffer.borrow_mut() = Some(buffer);
-        let math_box = self.do_shape(&self.no_cmap_font, style, user_data);
+        let math_box = self.do_shape(&self.no_cmap_font, Direction::Ltr, style, user_data);
         match math_box.content {
             MathBoxContent::Drawable(Drawable::Glyphs { glyphs, .. }) => glyphs,
             _ => unreachable!(),
         }
     }
 
-    fn do_shape(&self, font: &Font, style: LayoutStyle, user_data: u64) -> MathBox {
-        let mut features = Vec::with_capacity(2);
+    fn do_shape(
+        &self,
+        font: &Font,
+        direction: Direction,
+        style: LayoutStyle,
+        user_data: u64,
+    ) -> MathBox {
+        let mut features = Vec::with_capacity(3);
         if style.script_level >= 1 {
             let math_variants_tag = Tag::new('s', 's', 't', 'y');
             let variant_num = style.script_level as u32;
@@ -315,13 +346,20 @@ impl<'a> HarfbuzzShaper<'a> {
         if style.flat_accent {
             features.push(Feature::new(Tag::from(b"flac"), 1, ..));
         }
+        // right-to-left math uses the mirrored forms of e.g. radicals and integrals
+        if style.direction == TextDirection::Rtl {
+            features.push(Feature::new(Tag::from(b"rtlm"), 1, ..));
+        }
 
         let buffer = self
             .buffer
             .borrow_mut()
             .take()
             .expect("Buffer not available");
-        let glyph_buffer = shape(font, buffer.set_script(Tag::from(b"Math")), &features);
+        let buffer = buffer
+            .set_script(Tag::from(b"Math"))
+            .set_direction(direction);
+        let glyph_buffer = shape(font, buffer, &features);
         let math_box = {
             let shaped_glyphs = self.layout_boxes(&glyph_buffer, style);
             MathBox::with_glyphs(shaped_glyphs.collect(), self.scale_factor(style), user_data)
@@ -345,6 +383,15 @@ impl<'a> HarfbuzzShaper<'a> {
     }
 }
 
+// Returns true for the letters of Hebrew, Arabic and the Arabic mathematical alphabets.
+fn is_right_to_left_letter(chr: char) -> bool {
+    matches!(
+        chr,
+        '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}'
+            | '\u{1EE00}'..='\u{1EEFF}'
+    )
+}
+
 fn point_with_offset(offset: i32, horizontal: bool) -> Vector<i32> {
     if horizontal {
         Vector { x: offset, y: 0 }
@@ -366,6 +413,20 @@ impl<'a> MathShaper for HarfbuzzShaper<'a> {
         self.shape_with_style(string, style, user_data)
     }
 
+    fn shape_glyph(
+        &self,
+        glyph: u32,
+        scale: ScaleFactor,
+        style: LayoutStyle,
+        user_data: u64,
+    ) -> MathBox {
+        MathBox::with_glyphs(
+            self.glyph_from_index(glyph, style, user_data),
+            self.scale_factor(style).combine(scale),
+            user_data,
+        )
+    }
+
     fn is_stretchable(&self, glyph: u32, horizontal: bool) -> bool {
         let direction = if horizontal {
             hb::HB_DIRECTION_LTR
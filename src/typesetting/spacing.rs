@@ -0,0 +1,182 @@
+//! The space between the elements of a row.
+//!
+//! By default the space around an operator is taken from the operator itself, i.e. from its
+//! `lspace` and `rspace` which usually come from the operator dictionary. With
+//! `SpacingMode::Tex` the elements of a row are instead classified like the atoms of TeX and the
+//! space between two neighbouring elements depends on both of their classes, as described in
+//! chapter 18 and appendix G of the TeXbook.
+
+use super::layout::{LayoutOptions, MathLayout};
+use crate::types::{AtomClass, MathExpression, MathItem, SpacingMode};
+
+impl AtomClass {
+    fn index(self) -> usize {
+        match self {
+            AtomClass::Ord => 0,
+            AtomClass::Op => 1,
+            AtomClass::Bin => 2,
+            AtomClass::Rel => 3,
+            AtomClass::Open => 4,
+            AtomClass::Close => 5,
+            AtomClass::Punct => 6,
+            AtomClass::Inner => 7,
+        }
+    }
+}
+
+/// The table of `math_spacing` from `tex.web`. The row is given by the class of the left atom
+/// and the column by the class of the right one. `0` means no space, `1` a thin space that is
+/// left out in scripts, `2` a thin space, `3` a medium space and `4` a thick space, both of
+/// which are also left out in scripts. Binary operators never border on the atoms marked with
+/// `*`, since they are turned into ordinary atoms there.
+const SPACING_TABLE: [&[u8; 8]; 8] = [
+    b"02340001",
+    b"22*40001",
+    b"33**3**3",
+    b"44*04004",
+    b"00*00000",
+    b"02340001",
+    b"11*11111",
+    b"12341011",
+];
+
+/// Returns the space in front of and after `item` if it is an (embellished) operator.
+///
+/// The spaces belong to the outermost element of an embellished operator, e.g. to a `msub` and
+/// not to the `mo` inside of it, and the style of that element decides whether they are applied.
+/// As in MathML Core they are dropped in scripts.
+pub fn operator_spacing(item: &MathExpression, options: LayoutOptions) -> Option<(i32, i32)> {
+    let style = (options.style_provider)(options.style, item.get_user_data());
+    if style.script_level > 0 {
+        return None;
+    }
+    item.operator_properties(options)
+        .map(|properties| (properties.leading_space, properties.trailing_space))
+}
+
+/// Returns the space in front of and after every element of `list`.
+///
+/// A row that is an embellished operator itself is spaced as a whole, so its elements are not.
+pub fn list_spacing(list: &[MathExpression], options: LayoutOptions) -> Vec<Option<(i32, i32)>> {
+    if list.operator_properties(options).is_some() {
+        return vec![None; list.len()];
+    }
+    match options.style.spacing {
+        SpacingMode::OperatorDictionary => list
+            .iter()
+            .map(|item| operator_spacing(item, options))
+            .collect(),
+        SpacingMode::Tex => tex_spacing(list, options),
+    }
+}
+
+/// Returns the class of an element of a row or `None` if the element is space-like and does not
+/// take part in the spacing.
+fn atom_class(item: &MathExpression, options: LayoutOptions) -> Option<AtomClass> {
+    if item.is_space_like() {
+        return None;
+    }
+    if let Some(properties) = item.operator_properties(options) {
+        return Some(properties.atom_class);
+    }
+    let class = match *item.item {
+        MathItem::GeneralizedFraction(_) => AtomClass::Inner,
+        // a row between fences is spaced like the result of `\left` and `\right`
+        MathItem::List(ref list) => {
+            let mut classes = list.iter().filter_map(|item| atom_class(item, options));
+            match (classes.next(), classes.next_back()) {
+                (Some(AtomClass::Open), Some(AtomClass::Close)) => AtomClass::Inner,
+                _ => AtomClass::Ord,
+            }
+        }
+        _ => AtomClass::Ord,
+    };
+    Some(class)
+}
+
+/// Turns binary operators into ordinary atoms where they have no operand on one of their sides
+/// (rules 5 and 6 of appendix G of the TeXbook).
+fn demote_binary_operators(classes: &mut [AtomClass]) {
+    for index in 0..classes.len() {
+        if classes[index] == AtomClass::Bin {
+            let previous = index.checked_sub(1).map(|previous| classes[previous]);
+            match previous {
+                None
+                | Some(AtomClass::Bin)
+                | Some(AtomClass::Op)
+                | Some(AtomClass::Rel)
+                | Some(AtomClass::Open)
+                | Some(AtomClass::Punct) => classes[index] = AtomClass::Ord,
+                _ => {}
+            }
+        }
+        let follows_binary_operator = index > 0 && classes[index - 1] == AtomClass::Bin;
+        match classes[index] {
+            AtomClass::Rel | AtomClass::Close | AtomClass::Punct if follows_binary_operator => {
+                classes[index - 1] = AtomClass::Ord
+            }
+            _ => {}
+        }
+    }
+    if let Some(last) = classes.last_mut() {
+        if *last == AtomClass::Bin {
+            *last = AtomClass::Ord;
+        }
+    }
+}
+
+fn tex_spacing(list: &[MathExpression], options: LayoutOptions) -> Vec<Option<(i32, i32)>> {
+    let atoms = list
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| atom_class(item, options).map(|class| (index, class)))
+        .collect::<Vec<_>>();
+    let mut classes = atoms.iter().map(|&(_, class)| class).collect::<Vec<_>>();
+    demote_binary_operators(&mut classes);
+
+    let em = options.shaper.em_size() * options.shaper.scale_factor(options.style);
+    let is_script = options.style.script_level > 0;
+    let mut spacing = vec![None; list.len()];
+    for (pair, classes) in atoms.windows(2).zip(classes.windows(2)) {
+        let (left, right) = (classes[0], classes[1]);
+        let mu = match SPACING_TABLE[left.index()][right.index()] {
+            b'2' => 3,
+            b'1' if !is_script => 3,
+            b'3' if !is_script => 4,
+            b'4' if !is_script => 5,
+            _ => continue,
+        };
+        let space = em * mu / 18;
+        // the space next to an operator is dropped together with it at a line break
+        let (left_index, right_index) = (pair[0].0, pair[1].0);
+        match (left, right) {
+            (_, AtomClass::Bin) | (_, AtomClass::Rel) => {
+                spacing[right_index].get_or_insert((0, 0)).0 += space
+            }
+            (AtomClass::Bin, _) | (AtomClass::Rel, _) | (AtomClass::Punct, _) => {
+                spacing[left_index].get_or_insert((0, 0)).1 += space
+            }
+            _ => spacing[right_index].get_or_insert((0, 0)).0 += space,
+        }
+    }
+    spacing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AtomClass::*;
+    use super::*;
+
+    #[test]
+    fn test_demote_binary_operators() {
+        // a binary operator at the start of a row or after a relation is a sign
+        let mut classes = [Bin, Ord, Rel, Bin, Ord, Bin, Ord];
+        demote_binary_operators(&mut classes);
+        assert_eq!(classes, [Ord, Ord, Rel, Ord, Ord, Bin, Ord]);
+
+        // nor can it be followed by a closing fence or by punctuation
+        let mut classes = [Open, Ord, Bin, Close, Ord, Bin, Punct, Ord, Bin];
+        demote_binary_operators(&mut classes);
+        assert_eq!(classes, [Open, Ord, Ord, Close, Ord, Ord, Punct, Ord, Ord]);
+    }
+}
@@ -1,88 +1,120 @@
 use super::*;
 
-use super::layout::{MathLayout, OperatorProperties};
+use super::layout::MathLayout;
+use super::shaper::MathConstant;
+use super::spacing::list_spacing;
 use crate::math_box::{Extents, MathBoxMetrics};
 use crate::types::MathExpression;
 
+/// Returns the indices of the elements that grow to the height of the row. Operators that
+/// stretch horizontally keep their size in a row and count as non-stretchy elements.
 fn indices_of_stretchy_elements(list: &[MathExpression], options: LayoutOptions) -> Vec<usize> {
     list.iter()
         .enumerate()
-        .filter(|&(_, ref expr)| expr.can_stretch(options))
+        .filter(|(_, expr)| {
+            expr.operator_properties(options)
+                .and_then(|properties| properties.stretch_properties)
+                .map(|stretch_properties| stretch_properties.horizontal)
+                == Some(false)
+        })
         .map(|(index, _)| index)
         .collect()
 }
 
-pub fn layout_list_element(item: &MathExpression, options: LayoutOptions) -> MathBox {
-    if let Some(OperatorProperties {
-        leading_space,
-        trailing_space,
-        ..
-    }) = item.operator_properties(options)
-    {
-        if options.style.math_style == MathStyle::Display {
-            let left_space =
-                MathBox::empty(Extents::new(0, leading_space, 0, 0), item.get_user_data());
-            let mut elem = item.layout(options);
-            elem.origin.x += leading_space;
-            let mut right_space =
-                MathBox::empty(Extents::new(0, trailing_space, 0, 0), item.get_user_data());
-            right_space.origin.x += leading_space + elem.advance_width();
+/// Lays out an element of a row together with the space in front of and after it.
+pub fn layout_list_element(
+    item: &MathExpression,
+    spacing: Option<(i32, i32)>,
+    options: LayoutOptions,
+) -> MathBox {
+    if let Some((leading_space, trailing_space)) = spacing {
+        let left_space = MathBox::empty(Extents::new(0, leading_space, 0, 0), item.get_user_data());
+        let mut elem = item.layout(options);
+        elem.origin.x += leading_space;
+        let mut right_space =
+            MathBox::empty(Extents::new(0, trailing_space, 0, 0), item.get_user_data());
+        right_space.origin.x += leading_space + elem.advance_width();
 
-            return MathBox::with_vec(vec![left_space, elem, right_space], item.get_user_data());
-        }
+        return MathBox::with_vec(vec![left_space, elem, right_space], item.get_user_data());
     }
     item.layout(options)
 }
 
+/// Lays out the elements of a row such that the vertically stretchy operators among them (which
+/// may be embellished) are as high as the other elements.
+///
+/// If the row contains nothing but stretchy operators, they are as high as the highest of them
+/// would be without stretching. If the row is an embellished operator that is stretched itself,
+/// they grow to the size given by `options.stretch_size` instead.
 pub fn layout_strechy_list(list: &[MathExpression], options: LayoutOptions) -> Vec<MathBox> {
     let stretchy_indices = indices_of_stretchy_elements(list, options);
+    let spacing = list_spacing(list, options);
+    let stretch_size = options.stretch_size;
+    let options = LayoutOptions {
+        stretch_size: None,
+        ..options
+    };
 
     if stretchy_indices.is_empty() {
         return list
             .iter()
-            .map(move |item| layout_list_element(item, options))
+            .zip(spacing)
+            .map(move |(item, spacing)| layout_list_element(item, spacing, options))
             .collect();
     }
 
     let mut items = Vec::with_capacity(list.len());
-
     let mut max_intrinsic_size = 0;
-    for ref item in list {
-        if let Some(OperatorProperties {
-            stretch_properties: Some(stretch_props),
-            ..
-        }) = item.operator_properties(options)
-        {
-            max_intrinsic_size = ::std::cmp::max(max_intrinsic_size, stretch_props.intrinsic_size);
+    for (index, (item, &spacing)) in list.iter().zip(&spacing).enumerate() {
+        if stretchy_indices.contains(&index) {
+            let intrinsic_size = item
+                .operator_properties(options)
+                .and_then(|properties| properties.stretch_properties)
+                .map_or(0, |stretch_properties| stretch_properties.intrinsic_size);
+            max_intrinsic_size = ::std::cmp::max(max_intrinsic_size, intrinsic_size as i32);
         } else {
-            let math_box = layout_list_element(*item, options);
+            let math_box = layout_list_element(item, spacing, options);
             items.push(math_box);
         }
     }
 
-    let max_ascent = items.iter().map(|math_box| math_box.extents().ascent).max();
-    let max_descent = items
-        .iter()
-        .map(|math_box| math_box.extents().descent)
-        .max();
-
-    let options = LayoutOptions {
-        stretch_size: Some(Extents {
+    let stretch_size = match stretch_size {
+        Some(stretch_size) if list.operator_properties(options).is_some() => stretch_size,
+        _ if items.is_empty() => {
+            // center the operators around the math axis
+            let axis_height = options.math_constant(MathConstant::AxisHeight);
+            Extents {
+                left_side_bearing: 0,
+                width: 0,
+                ascent: max_intrinsic_size / 2 + axis_height,
+                descent: max_intrinsic_size - max_intrinsic_size / 2 - axis_height,
+            }
+        }
+        _ => Extents {
             left_side_bearing: 0,
             width: 0,
-            ascent: max_ascent.unwrap_or_default(),
-            descent: max_descent.unwrap_or_default(),
-        }),
+            ascent: items
+                .iter()
+                .map(|math_box| math_box.extents().ascent)
+                .max()
+                .unwrap_or_default(),
+            descent: items
+                .iter()
+                .map(|math_box| math_box.extents().descent)
+                .max()
+                .unwrap_or_default(),
+        },
+    };
+    let options = LayoutOptions {
+        stretch_size: Some(stretch_size),
         ..options
     };
 
     for &stretchy_index in stretchy_indices.iter() {
         let stretchy_item = &list[stretchy_index];
-        let math_box = layout_list_element(stretchy_item, options);
+        let math_box = layout_list_element(stretchy_item, spacing[stretchy_index], options);
         items.insert(stretchy_index, math_box);
     }
 
     items
 }
-
-// TODO: Tests
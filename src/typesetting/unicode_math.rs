@@ -1,7 +1,7 @@
 use std::char;
 
 /// Mathematical font families available from the unicode character range.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 #[derive(Debug)]
 pub enum Family {
@@ -19,9 +19,17 @@ pub enum Family {
     SansSerifItalics = 11,
     SansSerifBoldItalics = 12,
     Monospace = 13,
+    /// The initial forms of the Arabic mathematical alphabetic symbols.
+    Initial = 14,
+    /// The tailed forms of the Arabic mathematical alphabetic symbols.
+    Tailed = 15,
+    /// The looped forms of the Arabic mathematical alphabetic symbols.
+    Looped = 16,
+    /// The stretched forms of the Arabic mathematical alphabetic symbols.
+    Stretched = 17,
 }
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ASCII_CHARACTERS: &'static [u32] = &[
     // small latin
     0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
@@ -33,7 +41,7 @@ const ASCII_CHARACTERS: &'static [u32] = &[
     0x55, 0x56, 0x57, 0x58, 0x59, 0x5a,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ASCII_WITH_GREEK_CHARACTERS: &'static [u32] = &[
     // small latin
     0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
@@ -57,7 +65,7 @@ const ASCII_WITH_GREEK_CHARACTERS: &'static [u32] = &[
     0x3a3, 0x3a4, 0x3a5, 0x3a6, 0x3a7, 0x3a8, 0x3a9, 0x2207, //nabla
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ASCII_WITH_GREEK_AND_DOTLESS_CHARACTERS: &'static [u32] = &[
     // small latin
     0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
@@ -84,7 +92,7 @@ const ASCII_WITH_GREEK_AND_DOTLESS_CHARACTERS: &'static [u32] = &[
     0x131, 0x237,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ASCII_WITH_NUMERALS_CHARACTERS: &'static [u32] = &[
     // small latin
     0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
@@ -99,7 +107,7 @@ const ASCII_WITH_NUMERALS_CHARACTERS: &'static [u32] = &[
     0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ASCII_WITH_NUMERALS_AND_GREEK_CHARACTERS: &'static [u32] = &[
     // small latin
     0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a,
@@ -138,7 +146,7 @@ const ASCII_WITH_NUMERALS_AND_GREEK_CHARACTERS: &'static [u32] = &[
  ", ".join("0x{:x}".format(num) for num in alphabet_from_point(<start_point>))
  */
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const ITALICS: &'static [u32] = &[
     // small latin
     0x1d44e, 0x1d44f, 0x1d450, 0x1d451, 0x1d452, 0x1d453, 0x1d454, // a-g
@@ -169,7 +177,7 @@ const ITALICS: &'static [u32] = &[
     0x1d6a4, 0x1d6a5
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const BOLD: &'static [u32] = &[
     // small latin
     0x1d41a, 0x1d41b, 0x1d41c, 0x1d41d, 0x1d41e, 0x1d41f, 0x1d420,
@@ -201,7 +209,7 @@ const BOLD: &'static [u32] = &[
     0x1d7d5, 0x1d7d6, 0x1d7d7
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const BOLD_ITALICS: &'static [u32] = &[
     // small latin
     0x1d482, 0x1d483, 0x1d484, 0x1d485, 0x1d486, 0x1d487, 0x1d488,
@@ -229,7 +237,7 @@ const BOLD_ITALICS: &'static [u32] = &[
     0x1d731, 0x1d732, 0x1d733, 0x1d734, 0x1d735,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const SCRIPT: &'static [u32] = &[
     // small latin
     0x1d4b6, 0x1d4b7, 0x1d4b8, 0x1d4b9, 0x212f,  0x1d4bb, 0x210a,
@@ -244,7 +252,7 @@ const SCRIPT: &'static [u32] = &[
     0x1d4b1, 0x1d4b2, 0x1d4b3, 0x1d4b4, 0x1d4b5,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const BOLD_SCRIPT: &'static [u32] = &[
     // small latin
     0x1d4ea, 0x1d4eb, 0x1d4ec, 0x1d4ed, 0x1d4ee, 0x1d4ef, 0x1d4f0,
@@ -259,7 +267,7 @@ const BOLD_SCRIPT: &'static [u32] = &[
     0x1d4e5, 0x1d4e6, 0x1d4e7, 0x1d4e8, 0x1d4e9,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const FRAKTUR: &'static [u32] = &[
     // small latin
     0x1d51e, 0x1d51f, 0x1d520, 0x1d521, 0x1d522, 0x1d523, 0x1d524,
@@ -274,7 +282,7 @@ const FRAKTUR: &'static [u32] = &[
     0x1d519, 0x1d51a, 0x1d51b, 0x1d51c, 0x2128,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const DOUBLE_STRUCK: &'static [u32] = &[
     // small latin
     0x1d552, 0x1d553, 0x1d554, 0x1d555, 0x1d556, 0x1d557, 0x1d558,
@@ -293,7 +301,7 @@ const DOUBLE_STRUCK: &'static [u32] = &[
     0x1d7df, 0x1d7e0, 0x1d7e1,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const BOLD_FRAKTUR: &'static [u32] = &[
     // small latin
     0x1d586, 0x1d587, 0x1d588, 0x1d589, 0x1d58a, 0x1d58b, 0x1d58c,
@@ -308,7 +316,7 @@ const BOLD_FRAKTUR: &'static [u32] = &[
     0x1d581, 0x1d582, 0x1d583, 0x1d584, 0x1d585,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const SANS_SERIF: &'static [u32] = &[
     // small latin
     0x1d5ba, 0x1d5bb, 0x1d5bc, 0x1d5bd, 0x1d5be, 0x1d5bf, 0x1d5c0,
@@ -327,7 +335,7 @@ const SANS_SERIF: &'static [u32] = &[
     0x1d7e9, 0x1d7ea, 0x1d7eb,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const SANS_SERIF_BOLD: &'static [u32] = &[
     // small latin
     0x1d5ee, 0x1d5ef, 0x1d5f0, 0x1d5f1, 0x1d5f2, 0x1d5f3, 0x1d5f4,
@@ -359,7 +367,7 @@ const SANS_SERIF_BOLD: &'static [u32] = &[
     0x1d7f3, 0x1d7f4, 0x1d7f5,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const SANS_SERIF_ITALICS: &'static [u32] = &[
     // small latin
     0x1d622, 0x1d623, 0x1d624, 0x1d625, 0x1d626, 0x1d627, 0x1d628,
@@ -374,7 +382,7 @@ const SANS_SERIF_ITALICS: &'static [u32] = &[
     0x1d61d, 0x1d61e, 0x1d61f, 0x1d620, 0x1d621,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const SANS_SERIF_BOLD_ITALICS: &'static [u32] = &[
     // small latin
     0x1d656, 0x1d657, 0x1d658, 0x1d659, 0x1d65a, 0x1d65b, 0x1d65c,
@@ -402,7 +410,7 @@ const SANS_SERIF_BOLD_ITALICS: &'static [u32] = &[
     0x1d7a5, 0x1d7a6, 0x1d7a7, 0x1d7a8, 0x1d7a9,
 ];
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 const MONOSPACE: &'static [u32] = &[
     // small latin
     0x1d68a, 0x1d68b, 0x1d68c, 0x1d68d, 0x1d68e, 0x1d68f, 0x1d690,
@@ -421,6 +429,49 @@ const MONOSPACE: &'static [u32] = &[
     0x1d7fd, 0x1d7fe, 0x1d7ff,
 ];
 
+/// The Arabic letters in the order of the Arabic mathematical alphabetic symbols. Every alphabet
+/// of the block starts at a multiple of 0x20 and the position of a letter inside of an alphabet
+/// is its index here. Position 4 is only used by heh, which has no plain mathematical form.
+#[rustfmt::skip]
+const ARABIC_CHARACTERS: &[u32] = &[
+    // alef, beh, jeem, dal, heh, waw, zain, hah
+    0x627, 0x628, 0x62c, 0x62f, 0x647, 0x648, 0x632, 0x62d,
+    // tah, yeh, kaf, lam, meem, noon, seen, ain
+    0x637, 0x64a, 0x643, 0x644, 0x645, 0x646, 0x633, 0x639,
+    // feh, sad, qaf, reh, sheen, teh, theh, khah
+    0x641, 0x635, 0x642, 0x631, 0x634, 0x62a, 0x62b, 0x62e,
+    // thal, dad, zah, ghain, dotless beh, dotless noon, dotless feh, dotless qaf
+    0x630, 0x636, 0x638, 0x63a, 0x66e, 0x6ba, 0x6a1, 0x66f,
+];
+
+// the positions of the letters that exist in each of the Arabic alphabets
+
+#[rustfmt::skip]
+const ARABIC_INITIAL: &[u32] = &[
+    1, 2, 4, 7, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 20, 21, 22, 23, 25, 27,
+];
+
+#[rustfmt::skip]
+const ARABIC_TAILED: &[u32] = &[
+    2, 7, 9, 11, 13, 14, 15, 17, 18, 20, 23, 25, 27, 29, 31,
+];
+
+#[rustfmt::skip]
+const ARABIC_STRETCHED: &[u32] = &[
+    1, 2, 4, 7, 8, 9, 10, 12, 13, 14, 15, 16, 17, 18, 20, 21, 22, 23, 25, 26, 27, 28, 30,
+];
+
+#[rustfmt::skip]
+const ARABIC_LOOPED: &[u32] = &[
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+    26, 27,
+];
+
+#[rustfmt::skip]
+const ARABIC_DOUBLE_STRUCK: &[u32] = &[
+    1, 2, 3, 5, 6, 7, 8, 9, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+];
+
 static FAMILY_TABLES: &'static [&'static [u32]] = &[ITALICS,
                                                     BOLD,
                                                     BOLD_ITALICS,
@@ -450,8 +501,16 @@ static CHARACTER_TABLES: &'static [&'static [u32]] = &[ASCII_WITH_GREEK_AND_DOTL
                                                        ASCII_WITH_NUMERALS_CHARACTERS];
 
 pub fn convert_character_to_family(c: char, family: Family) -> char {
-    if let Family::Normal = family {
-        return c;
+    if let Some(arabic) = convert_arabic_character(c, family) {
+        return arabic;
+    }
+    match family {
+        Family::Normal
+        | Family::Initial
+        | Family::Tailed
+        | Family::Looped
+        | Family::Stretched => return c,
+        _ => {}
     }
     let family_index = family as usize - 1;
     let table_to_search = CHARACTER_TABLES[family_index];
@@ -463,6 +522,27 @@ pub fn convert_character_to_family(c: char, family: Family) -> char {
     c
 }
 
+/// Returns the Arabic mathematical alphabetic symbol for an Arabic letter if the alphabet of
+/// `family` contains it.
+fn convert_arabic_character(c: char, family: Family) -> Option<char> {
+    let (start, positions) = match family {
+        Family::Initial => (0x1ee20, ARABIC_INITIAL),
+        Family::Tailed => (0x1ee40, ARABIC_TAILED),
+        Family::Stretched => (0x1ee60, ARABIC_STRETCHED),
+        Family::Looped => (0x1ee80, ARABIC_LOOPED),
+        Family::DoubleStruck => (0x1eea0, ARABIC_DOUBLE_STRUCK),
+        _ => return None,
+    };
+    let position = ARABIC_CHARACTERS
+        .iter()
+        .position(|&code| code == c as u32)? as u32;
+    if positions.contains(&position) {
+        char::from_u32(start + position)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,4 +558,17 @@ mod tests {
         assert_eq!(mathematical_dotless_i,
                    convert_character_to_family(latin_dotless_i, Family::Italics));
     }
+
+    #[test]
+    fn arabic_test() {
+        let beh = '\u{628}';
+        assert_eq!('\u{1ee21}', convert_character_to_family(beh, Family::Initial));
+        assert_eq!('\u{1ee81}', convert_character_to_family(beh, Family::Looped));
+        assert_eq!('\u{1eea1}', convert_character_to_family(beh, Family::DoubleStruck));
+        // there is no tailed beh
+        assert_eq!(beh, convert_character_to_family(beh, Family::Tailed));
+        let heh = '\u{647}';
+        assert_eq!('\u{1ee64}', convert_character_to_family(heh, Family::Stretched));
+        assert_eq!('a', convert_character_to_family('a', Family::Initial));
+    }
 }
@@ -4,10 +4,14 @@ extern crate math_render;
 extern crate freetype;
 
 use math_render::mathmlparser;
-use math_render::math_box::{MathBox, MathBoxContent, MathBoxMetrics};
+use math_render::math_box::{Drawable, MathBox, MathBoxContent, MathBoxMetrics};
+use math_render::{
+    Color, Field, Glyph, LayoutStyle, MathExpression, MathItem, ScaleFactor, SpacingMode,
+    TextDirection,
+};
 
 mod util;
-use crate::util::TEST_FONT;
+use crate::util::{layout, TEST_FONT};
 
 #[test]
 fn mathml_test() {
@@ -81,3 +85,747 @@ fn fraction_centering_test() {
         assert!((left_space - right_space).abs() <= 2);
     })
 }
+
+#[test]
+fn matrix_fences_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mo>(</mo>\
+                   <mtable>\
+                   <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+                   <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+                   </mtable>\
+                   <mo>)</mo>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        println!("{:?}", result);
+        let boxes = assume_boxes(result.content());
+        let table = &boxes[1];
+
+        // the cells of the first row are placed next to each other and the second row is placed
+        // below the first row
+        let cells = assume_boxes(table.content());
+        assert!(cells[1].origin.x > cells[0].origin.x + cells[0].extents().width);
+        assert!(cells[2].origin.y > cells[0].origin.y);
+
+        // both fences stretch to cover the matrix with a somewhat big error margin
+        let min_height = (table.extents().height() as f32 * 0.9) as i32;
+        assert!(boxes[0].extents().height() >= min_height);
+        assert!(boxes[2].extents().height() >= min_height);
+    })
+}
+
+#[test]
+fn stretchy_fences_test() {
+    TEST_FONT.with(|font| {
+        let fraction = "<mfrac><mfrac><mi>a</mi><mi>b</mi></mfrac><mi>c</mi></mfrac>";
+        let fraction_height = layout(fraction, font).extents().height();
+        let unstretched_height = layout("<mo stretchy=\"false\">(</mo>", font).extents().height();
+        let fence_height = |xml: &str, index: usize| {
+            let result = layout(xml, font);
+            assume_boxes(result.content())[index].extents().height()
+        };
+        let min_height = (fraction_height as f32 * 0.9) as i32;
+
+        // fences that are embellished or nested in a row with spaces grow as well
+        let plain = format!("<mo>(</mo>{}<mo>)</mo>", fraction);
+        assert!(fence_height(&plain, 0) >= min_height);
+        let scripted = format!("<msub><mo>(</mo><mi>i</mi></msub>{}", fraction);
+        assert!(fence_height(&scripted, 0) >= min_height);
+        let nested = format!("<mrow><mo>(</mo><mspace width=\"1em\"/></mrow>{}", fraction);
+        assert!(fence_height(&nested, 0) >= min_height);
+
+        // the scripts of an embellished fence are not stretched
+        let stretchy_script = format!("<msub><mo>(</mo><mo>|</mo></msub>{}", fraction);
+        let result = layout(&stretchy_script, font);
+        // the embellished operator is surrounded by its spaces
+        let embellished = &assume_boxes(assume_boxes(result.content())[0].content())[1];
+        let script = &assume_boxes(embellished.content())[1];
+        assert!(script.extents().height() < unstretched_height * 2);
+
+        // fences without anything to cover do not grow
+        assert!(fence_height("<mo>(</mo><mo>)</mo>", 0) < fence_height(&plain, 0));
+
+        // the size is limited by minsize and maxsize
+        let limited = format!("<mo maxsize=\"1em\">(</mo>{}", fraction);
+        assert!(fence_height(&limited, 0) < fence_height(&plain, 0));
+        assert!(fence_height("<mo minsize=\"3em\">(</mo><mi>x</mi>", 0) > unstretched_height);
+    })
+}
+
+#[test]
+fn clamped_parentheses_test() {
+    TEST_FONT.with(|font| {
+        // the heights of the first and the last element
+        let heights = |xml: &str| {
+            let result = layout(xml, font);
+            let boxes = assume_boxes(result.content());
+            let last = boxes.len() - 1;
+            (boxes[0].extents().height(), boxes[last].extents().height())
+        };
+        let fraction = "<mfrac><mfrac><mi>a</mi><mi>b</mi></mfrac>\
+                        <mfrac><mi>c</mi><mi>d</mi></mfrac></mfrac>";
+        let (stretched, _) = heights(&format!("<mo>(</mo>{}<mo>)</mo>", fraction));
+        let normal = layout("<mo stretchy=\"false\">(</mo>", font).extents().height();
+
+        // the maximal size can be given as a length or as a multiple of the normal size
+        let clamped = format!(
+            "<mo maxsize=\"1.2em\">(</mo>{}<mo maxsize=\"120%\">)</mo>",
+            fraction
+        );
+        let (open, close) = heights(&clamped);
+        assert!(open < stretched && close < stretched);
+        let (open, close) = heights(&format!("<mo maxsize=\"1\">(</mo>{}<mo>)</mo>", fraction));
+        assert!(open < stretched);
+        assert!(close >= stretched);
+
+        // parentheses around small content grow to their minimal size
+        let (open, close) =
+            heights("<mo minsize=\"2\">(</mo><mi>x</mi><mo minsize=\"200%\">)</mo>");
+        assert!(open >= 2 * normal);
+        assert_eq!(open, close);
+    })
+}
+
+#[test]
+fn enclosure_box_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<menclose notation=\"box\"><mi>x</mi></menclose>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        println!("{:?}", result);
+        let boxes = assume_boxes(result.content());
+        let (content, frame) = (&boxes[0], &boxes[1]);
+
+        // the box has to be drawn around the ink of the content
+        let ink = content.extents();
+        assert!(frame.origin.x < content.origin.x + ink.left_side_bearing);
+        assert!(frame.origin.x + frame.extents().width > content.origin.x + ink.right_edge());
+        assert!(frame.origin.y < content.origin.y - ink.ascent);
+        assert!(frame.origin.y + frame.extents().descent > content.origin.y + ink.descent);
+
+        // the enclosure is wide enough for the box
+        assert!(result.advance_width() >= frame.origin.x + frame.extents().width);
+    })
+}
+
+#[test]
+fn style_script_level_test() {
+    TEST_FONT.with(|font| {
+        let normal = layout("<mi>x</mi>", font);
+        let scripted = layout("<mstyle scriptlevel=\"+1\"><mi>x</mi></mstyle>", font);
+        assert!(scripted.advance_width() < normal.advance_width());
+
+        // the script level can also be reset to zero
+        let reset = layout(
+            "<msup><mi>x</mi><mstyle scriptlevel=\"0\"><mi>x</mi></mstyle></msup>",
+            font,
+        );
+        let boxes = assume_boxes(reset.content());
+        assert_eq!(boxes[1].advance_width(), normal.advance_width());
+
+        // the script level of scripts stops at the largest level
+        let largest = layout("<mstyle scriptlevel=\"254\"><mi>x</mi></mstyle>", font);
+        for xml in &[
+            "<mstyle scriptlevel=\"255\"><msup><mi>x</mi><mn>2</mn></msup></mstyle>",
+            "<mstyle scriptlevel=\"254\"><mroot><mi>x</mi><mn>3</mn></mroot></mstyle>",
+            "<mstyle scriptlevel=\"255\" displaystyle=\"false\">\
+             <mfrac><mi>x</mi><mn>2</mn></mfrac></mstyle>",
+        ] {
+            let result = layout(xml, font);
+            assert!(result.advance_width() >= largest.advance_width());
+        }
+    })
+}
+
+#[test]
+fn prescripts_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mmultiscripts><mi>C</mi><mprescripts/><mn>6</mn><mn>14</mn></mmultiscripts>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        println!("{:?}", result);
+        let boxes = assume_boxes(result.content());
+        let (nucleus, subscript, superscript) = (&boxes[0], &boxes[1], &boxes[2]);
+
+        // both prescripts are in front of the nucleus and right aligned
+        let right_edge = |math_box: &MathBox| math_box.origin.x + math_box.advance_width();
+        assert!(right_edge(subscript) <= nucleus.origin.x);
+        assert!(right_edge(superscript) <= nucleus.origin.x);
+        assert!(superscript.origin.x < subscript.origin.x);
+        assert!(subscript.origin.x >= 0 && superscript.origin.x >= 0);
+        assert!(subscript.origin.y > 0 && superscript.origin.y < 0);
+    })
+}
+
+#[test]
+fn multiple_postscripts_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mmultiscripts><mi>R</mi><mi>i</mi><none/><none/><mi>j</mi></mmultiscripts>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        println!("{:?}", result);
+        let boxes = assume_boxes(result.content());
+        let (subscript, superscript) = (&boxes[1], &boxes[2]);
+
+        // the superscript goes into the column after the subscript
+        assert!(superscript.origin.x >= subscript.origin.x + subscript.advance_width());
+        assert!(subscript.origin.y > 0 && superscript.origin.y < 0);
+    })
+}
+
+#[test]
+fn phantom_test() {
+    TEST_FONT.with(|font| {
+        let visible = layout("<mi>x</mi>", font);
+        let phantom = layout("<mphantom><mi>x</mi></mphantom>", font);
+        assert_eq!(phantom.advance_width(), visible.advance_width());
+        assert_eq!(phantom.extents(), visible.extents());
+        match *phantom.content() {
+            MathBoxContent::Empty(_) => {}
+            ref other => panic!("Expected an empty box. Found {:?}.", other),
+        }
+    })
+}
+
+#[test]
+fn padded_test() {
+    TEST_FONT.with(|font| {
+        let content = layout("<mi>x</mi>", font);
+        let padded = layout(
+            "<mpadded width=\"+100%\" height=\"2 height\" depth=\"0\" lspace=\"50% width\" \
+             voffset=\"1 depth\"><mi>x</mi></mpadded>",
+            font,
+        );
+        assert_eq!(padded.advance_width(), 2 * content.advance_width());
+        assert_eq!(padded.extents().ascent, 2 * content.extents().ascent);
+        assert_eq!(padded.extents().descent, 0);
+
+        let boxes = assume_boxes(padded.content());
+        assert_eq!(boxes[0].origin.x, content.advance_width() / 2);
+        assert_eq!(boxes[0].origin.y, -content.extents().descent);
+    })
+}
+
+#[test]
+fn fraction_attributes_test() {
+    TEST_FONT.with(|font| {
+        // a binomial coefficient has no fraction rule
+        let stack = layout("<mfrac linethickness=\"0\"><mi>n</mi><mi>k</mi></mfrac>", font);
+        assert_eq!(assume_boxes(stack.content()).len(), 2);
+
+        // the narrow numerator is moved to the left edge of the denominator
+        let aligned = layout("<mfrac numalign=\"left\"><mi>x</mi><mi>abc</mi></mfrac>", font);
+        let boxes = assume_boxes(aligned.content());
+        let (numerator, denominator) = (&boxes[0], &boxes[2]);
+        assert_eq!(
+            numerator.origin.x + numerator.extents().left_side_bearing,
+            denominator.origin.x + denominator.extents().left_side_bearing
+        );
+
+        // the fields of a bevelled fraction are placed next to each other
+        let bevelled = layout("<mfrac bevelled=\"true\"><mi>a</mi><mi>b</mi></mfrac>", font);
+        let boxes = assume_boxes(bevelled.content());
+        let (numerator, slash, denominator) = (&boxes[0], &boxes[1], &boxes[2]);
+        assert!(numerator.origin.x + numerator.advance_width() < slash.origin.x);
+        assert!(slash.origin.x < denominator.origin.x);
+        assert!(numerator.origin.y < denominator.origin.y);
+    })
+}
+
+#[test]
+fn glyph_field_test() {
+    TEST_FONT.with(|font| {
+        let text = mathmlparser::parse("<mi>x</mi>".as_bytes()).unwrap();
+        let text = math_render::layout(&text, font);
+        let (glyph, _) = text.first_glyph().unwrap();
+
+        let xml = format!("<mi><mglyph index=\"{}\"/></mi>", glyph.glyph_code);
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        assert_eq!(result.first_glyph().unwrap().0.glyph_code, glyph.glyph_code);
+        assert_eq!(result.advance_width(), text.advance_width());
+
+        let half_size = MathExpression::new(
+            MathItem::Field(Field::Glyph(Glyph {
+                glyph_code: glyph.glyph_code,
+                scale: ScaleFactor::new(50),
+            })),
+            0,
+        );
+        let result = math_render::layout(&half_size, font);
+        assert_eq!(result.advance_width(), text.advance_width() / 2);
+    })
+}
+
+#[test]
+fn stretchy_glyph_test() {
+    TEST_FONT.with(|font| {
+        let glyph_code = |xml: &str| layout(xml, font).first_glyph().unwrap().0.glyph_code;
+        let height = |xml: &str| layout(xml, font).extents().height();
+
+        // a glyph stretches like the character it shows
+        let parenthesis = glyph_code("<mo stretchy=\"false\">(</mo>");
+        let fraction = "<mfrac><mfrac><mi>a</mi><mi>b</mi></mfrac><mi>c</mi></mfrac>";
+        let stretched = |content: &str| {
+            let xml = format!(
+                "<mrow><mo stretchy=\"true\" symmetric=\"true\">{}</mo>{}</mrow>",
+                content, fraction
+            );
+            let result = layout(&xml, font);
+            assume_boxes(result.content())[0].extents().height()
+        };
+        let glyph = format!("<mglyph index=\"{}\"/>", parenthesis);
+        assert_eq!(stretched(&glyph), stretched("("));
+        assert!(stretched(&glyph) > height("<mo stretchy=\"false\">(</mo>"));
+
+        let sum = glyph_code("<mo>∑</mo>");
+        let large = |content: &str| {
+            let xml = format!(
+                "<mstyle displaystyle=\"true\"><mo largeop=\"true\">{}</mo></mstyle>",
+                content
+            );
+            height(&xml)
+        };
+        let glyph = format!("<mglyph index=\"{}\"/>", sum);
+        assert_eq!(large(&glyph), large("∑"));
+
+        // a glyph without variants keeps its size
+        let letter = glyph_code("<mi>x</mi>");
+        let glyph = format!("<mglyph index=\"{}\"/>", letter);
+        assert_eq!(large(&glyph), height("<mi>x</mi>"));
+    })
+}
+
+#[test]
+fn math_size_test() {
+    TEST_FONT.with(|font| {
+        let normal = layout("<mi>x</mi>", font).advance_width();
+        assert_eq!(
+            layout("<mi mathsize=\"200%\">x</mi>", font).advance_width(),
+            2 * normal
+        );
+        let nested = layout(
+            "<mstyle mathsize=\"2em\"><mi mathsize=\"50%\">x</mi></mstyle>",
+            font,
+        );
+        assert_eq!(nested.advance_width(), normal);
+        assert!(layout("<mi mathsize=\"small\">x</mi>", font).advance_width() < normal);
+
+        // lengths relative to the font size and the constants of the font grow with the glyphs
+        let large = |xml: &str| {
+            layout(&format!("<mstyle mathsize=\"200%\">{}</mstyle>", xml), font)
+        };
+        let space = "<mspace width=\"1em\"/>";
+        assert_eq!(
+            large(space).advance_width(),
+            2 * layout(space, font).advance_width()
+        );
+        let space = "<mspace width=\"10pt\"/>";
+        assert_eq!(
+            large(space).advance_width(),
+            layout(space, font).advance_width()
+        );
+        let fraction = "<mfrac><mi>x</mi><mn>2</mn></mfrac>";
+        let height = layout(fraction, font).extents().height();
+        assert!((large(fraction).extents().height() - 2 * height).abs() <= 2);
+    })
+}
+
+#[test]
+fn percent_length_test() {
+    TEST_FONT.with(|font| {
+        // percentages are relative to the value that the attribute replaces
+        let width = |xml: &str| layout(xml, font).advance_width();
+        let row =
+            |attributes: &str| width(&format!("<mi>a</mi><mo{}>+</mo><mi>b</mi>", attributes));
+        assert_eq!(row(" lspace=\"100%\" rspace=\"100%\""), row(""));
+        assert_eq!(
+            row(" lspace=\"0%\" rspace=\"0%\""),
+            row(" lspace=\"0\" rspace=\"0\"")
+        );
+        assert!(row(" lspace=\"200%\"") > row(""));
+
+        let table = |attributes: &str| {
+            let xml = format!(
+                "<mtable{}><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr></mtable>",
+                attributes
+            );
+            width(&xml)
+        };
+        assert_eq!(table(" columnspacing=\"100%\""), table(""));
+        assert_eq!(
+            table(" columnspacing=\"0%\""),
+            table(" columnspacing=\"0\"")
+        );
+
+        // a space has no width to refer to
+        assert_eq!(width("<mspace width=\"50%\"/>"), 0);
+    })
+}
+
+#[test]
+fn math_color_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mi>a</mi><mstyle mathcolor=\"red\"><mi>b</mi><mi mathcolor=\"blue\">c</mi>\
+                   </mstyle><mstyle mathbackground=\"yellow\"><mi>d</mi></mstyle>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout(&list, font);
+        let boxes = assume_boxes(result.content());
+        assert_eq!(boxes[0].color(), None);
+
+        // the colour of an inner element is kept
+        let colored = assume_boxes(boxes[1].content());
+        assert_eq!(colored[0].color(), Some(Color::rgb(255, 0, 0)));
+        assert_eq!(colored[1].color(), Some(Color::rgb(0, 0, 255)));
+
+        // the background is drawn first and covers the logical extents of the content
+        let background = assume_boxes(boxes[2].content());
+        match *background[0].content() {
+            MathBoxContent::Drawable(Drawable::FilledRect { size }) => {
+                let content = &background[1];
+                assert_eq!(size.x, content.advance_width());
+                assert_eq!(size.y, content.extents().height());
+            }
+            ref other => panic!("Expected a filled rectangle. Found {:?}", other),
+        }
+        assert_eq!(background[0].color(), Some(Color::rgb(255, 255, 0)));
+        assert_eq!(background[1].color(), None);
+        assert_eq!(boxes[2].advance_width(), background[1].advance_width());
+    })
+}
+
+#[test]
+fn right_to_left_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mi>a</mi><mo>+</mo><msup><mi>b</mi><mn>2</mn></msup>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let ltr = math_render::layout(&list, font);
+        let rtl = math_render::layout_with_style(&list, font, |style, _| LayoutStyle {
+            direction: TextDirection::Rtl,
+            ..style
+        });
+        assert_eq!(ltr.advance_width(), rtl.advance_width());
+
+        let xml = format!("<mrow dir=\"rtl\">{}</mrow>", xml);
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let styled = math_render::layout(&list, font);
+        assert_eq!(styled.advance_width(), rtl.advance_width());
+
+        for result in &[rtl, styled] {
+            let width = result.advance_width();
+            let boxes = assume_boxes(result.content());
+
+            // the row starts on the right
+            assert_eq!(boxes[0].origin.x + boxes[0].advance_width(), width);
+            assert!(boxes[1].origin.x < boxes[0].origin.x);
+            assert!(boxes[2].origin.x < boxes[1].origin.x);
+
+            // the superscript is on the left of its base
+            let scripts = assume_boxes(boxes[2].content());
+            assert!(scripts[1].origin.x < scripts[0].origin.x);
+        }
+
+        // numbers and words are not reversed
+        fn glyph_codes(math_box: &MathBox) -> Vec<u32> {
+            match *math_box.content() {
+                MathBoxContent::Drawable(Drawable::Glyphs { ref glyphs, .. }) => {
+                    glyphs.iter().map(|glyph| glyph.glyph_code).collect()
+                }
+                MathBoxContent::Boxes(ref boxes) => boxes.iter().flat_map(glyph_codes).collect(),
+                _ => Vec::new(),
+            }
+        }
+        for &(xml, length) in &[("<mn>12</mn>", 2), ("<mi>sin</mi>", 3)] {
+            let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+            let ltr = math_render::layout(&list, font);
+            let rtl = math_render::layout_with_style(&list, font, |style, _| LayoutStyle {
+                direction: TextDirection::Rtl,
+                ..style
+            });
+            assert_eq!(glyph_codes(&rtl).len(), length);
+            assert_eq!(glyph_codes(&rtl), glyph_codes(&ltr));
+        }
+    })
+}
+
+#[test]
+fn source_map_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mi>a</mi><mo>+</mo><mfrac><mi>b</mi><mn>2</mn></mfrac>";
+        let mut context = mathmlparser::ParseContext::default();
+        let list = mathmlparser::parse_with_context(xml.as_bytes(), &mut context).unwrap();
+        let result = math_render::layout(&list, font);
+
+        // every glyph can be traced back to the token element that contains it
+        let mut sources = Vec::new();
+        let mut stack = vec![&result];
+        while let Some(math_box) = stack.pop() {
+            match *math_box.content() {
+                MathBoxContent::Boxes(ref boxes) => stack.extend(boxes),
+                MathBoxContent::Drawable(Drawable::Glyphs { .. }) => {
+                    let span = context.span(math_box.user_data()).unwrap();
+                    sources.push(&xml[span]);
+                }
+                _ => {}
+            }
+        }
+        sources.sort();
+        assert_eq!(
+            sources,
+            ["<mi>a</mi>", "<mi>b</mi>", "<mn>2</mn>", "<mo>+</mo>"]
+        );
+    })
+}
+
+#[test]
+fn semantics_test() {
+    TEST_FONT.with(|font| {
+        let content = "<mi>a</mi><mo>+</mo><msup><mi>b</mi><mn>2</mn></msup>";
+        let xml = format!(
+            "<semantics><mrow>{}</mrow><annotation encoding=\"application/x-tex\">a+b^2\
+             </annotation></semantics>",
+            content
+        );
+        let expr = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let annotation = expr.annotation("application/x-tex").unwrap();
+        assert_eq!(annotation.content, "a+b^2");
+
+        // the annotations are not laid out
+        let with_semantics = math_render::layout(&expr, font);
+        let without = math_render::layout(&mathmlparser::parse(content.as_bytes()).unwrap(), font);
+        assert_eq!(with_semantics.extents(), without.extents());
+        assert_eq!(with_semantics.advance_width(), without.advance_width());
+    })
+}
+
+#[test]
+fn line_break_test() {
+    TEST_FONT.with(|font| {
+        let xml = "<mi>x</mi><mo>=</mo><mi>a</mi><mo>+</mo><mi>b</mi><mo>+</mo><mi>c</mi>\
+                   <mo>+</mo><mi>d</mi><mo>+</mo><mi>e</mi><mo>+</mo><mi>f</mi>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let single_line = math_render::layout(&list, font);
+
+        // a formula that fits is not broken
+        let result = math_render::layout_with_width(&list, font, single_line.advance_width());
+        assert_eq!(result.extents(), single_line.extents());
+
+        let max_width = single_line.advance_width() / 2;
+        let result = math_render::layout_with_width(&list, font, max_width);
+        let lines = assume_boxes(result.content());
+        assert!(lines.len() > 1);
+        for pair in lines.windows(2) {
+            assert!(pair[0].origin.y < pair[1].origin.y);
+        }
+        for line in lines {
+            assert!(line.advance_width() <= max_width);
+        }
+
+        // a forced break is honoured even if the formula fits
+        let xml = "<mi>x</mi><mo linebreak=\"newline\">=</mo><mi>y</mi>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout_with_width(&list, font, i32::MAX);
+        assert_eq!(assume_boxes(result.content()).len(), 2);
+        // also without a width limit
+        let result = math_render::layout(&list, font);
+        assert_eq!(assume_boxes(result.content()).len(), 2);
+    })
+}
+
+#[test]
+fn alignment_test() {
+    TEST_FONT.with(|font| {
+        // the equals signs of both rows are placed below each other
+        let xml = "<mtable>\
+                   <mtr><mtd><mi>x</mi><malignmark/><mo>=</mo><mi>a</mi><mo>+</mo><mi>b</mi>\
+                   </mtd></mtr>\
+                   <mtr><mtd><mi>abc</mi><malignmark/><mo>=</mo><mi>c</mi></mtd></mtr>\
+                   </mtable>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let table = math_render::layout(&list, font);
+        let cells = assume_boxes(table.content());
+        let equals_position = |cell: &MathBox, index: usize| {
+            cell.origin.x + assume_boxes(cell.content())[index].origin.x
+        };
+        assert_eq!(equals_position(&cells[0], 2), equals_position(&cells[1], 2));
+
+        // the second group of each row is aligned to the right
+        let xml = "<mtable groupalign=\"{left right}\">\
+                   <mtr><mtd><maligngroup/><mi>x</mi><maligngroup/><mi>a</mi></mtd></mtr>\
+                   <mtr><mtd><maligngroup/><mi>xyz</mi><maligngroup/><mi>abc</mi></mtd></mtr>\
+                   </mtable>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let table = math_render::layout(&list, font);
+        let cells = assume_boxes(table.content());
+        let right_edge = |cell: &MathBox| {
+            let last = assume_boxes(cell.content()).last().unwrap();
+            cell.origin.x + last.origin.x + last.advance_width()
+        };
+        assert_eq!(right_edge(&cells[0]), right_edge(&cells[1]));
+
+        // the lines of a broken row are aligned at their marks
+        let xml = "<mi>x</mi><malignmark/><mo>=</mo><mi>a</mi><mo>+</mo><mi>b</mi>\
+                   <malignmark/><mo linebreak=\"newline\">=</mo><mi>c</mi>";
+        let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+        let result = math_render::layout_with_width(&list, font, i32::MAX);
+        let lines = assume_boxes(result.content());
+        assert_eq!(lines.len(), 2);
+        // the space in front of an operator is part of its box
+        let glyph_position = |line: &MathBox, index: usize| {
+            let operator = &assume_boxes(line.content())[index];
+            line.origin.x + operator.origin.x + assume_boxes(operator.content())[1].origin.x
+        };
+        assert_eq!(glyph_position(&lines[0], 2), glyph_position(&lines[1], 1));
+    })
+}
+
+#[test]
+fn operator_spacing_test() {
+    TEST_FONT.with(|font| {
+        let width = |xml: &str| layout(xml, font).advance_width();
+        let spaced = "<mi>a</mi><mo>=</mo><mi>b</mi>";
+        let unspaced = "<mi>a</mi><mo lspace=\"0\" rspace=\"0\">=</mo><mi>b</mi>";
+        let space = width(spaced) - width(unspaced);
+        assert!(space > 0);
+
+        // operators are spaced in inline formulas as well
+        let inline = |xml: &str| format!("<mstyle displaystyle=\"false\">{}</mstyle>", xml);
+        assert_eq!(width(&inline(spaced)) - width(&inline(unspaced)), space);
+
+        // but not in scripts
+        let scripted = |xml: &str| format!("<mstyle scriptlevel=\"1\">{}</mstyle>", xml);
+        assert_eq!(width(&scripted(spaced)), width(&scripted(unspaced)));
+
+        // the space of an embellished operator is placed around the whole element
+        let embellished = "<mi>a</mi><msub><mo>=</mo><mi>i</mi></msub><mi>b</mi>";
+        let unspaced_embellished =
+            "<mi>a</mi><msub><mo lspace=\"0\" rspace=\"0\">=</mo><mi>i</mi></msub><mi>b</mi>";
+        assert_eq!(width(embellished) - width(unspaced_embellished), space);
+    })
+}
+
+#[test]
+fn tex_spacing_test() {
+    TEST_FONT.with(|font| {
+        let width = |xml: &str, spacing: SpacingMode| {
+            let list = mathmlparser::parse(xml.as_bytes()).unwrap();
+            math_render::layout_with_style(&list, font, |style, _| style.with_spacing(spacing))
+                .advance_width()
+        };
+        let tex_space = |xml: &str| {
+            let unspaced = xml.replace("<mo>", "<mo lspace=\"0\" rspace=\"0\">");
+            width(xml, SpacingMode::Tex) - width(&unspaced, SpacingMode::OperatorDictionary)
+        };
+        // the thick spaces on both sides of a relation
+        let relation_space = width("<mi>a</mi><mo>=</mo><mi>b</mi>", SpacingMode::Tex)
+            - width("<mi>a</mi><mi>b</mi>", SpacingMode::Tex)
+            - width("<mo>=</mo>", SpacingMode::Tex);
+        assert!(relation_space > 0);
+
+        // the class of an operator comes from the operator dictionary and not from its spaces
+        let unspaced = "<mi>a</mi><mo lspace=\"0\" rspace=\"0\">=</mo><mi>b</mi>";
+        assert_eq!(
+            width(unspaced, SpacingMode::Tex),
+            width("<mi>a</mi><mo>=</mo><mi>b</mi>", SpacingMode::Tex)
+        );
+
+        // binary operators and relations are spaced as with the operator dictionary
+        let sum = "<mi>a</mi><mo>+</mo><mi>b</mi><mo>=</mo><mi>c</mi>";
+        assert_eq!(
+            width(sum, SpacingMode::Tex),
+            width(sum, SpacingMode::OperatorDictionary)
+        );
+
+        // but a binary operator after a relation is a sign, which is not spaced
+        let sign = "<mi>a</mi><mo>=</mo><mo>-</mo><mi>b</mi>";
+        assert_eq!(tex_space(sign), relation_space);
+        let unspaced_sign = sign.replace("<mo>", "<mo lspace=\"0\" rspace=\"0\">");
+        let dictionary_space = width(sign, SpacingMode::OperatorDictionary)
+            - width(&unspaced_sign, SpacingMode::OperatorDictionary);
+        assert!(tex_space(sign) < dictionary_space);
+
+        // relations are not spaced in scripts
+        let scripted = format!("<mstyle scriptlevel=\"1\">{}</mstyle>", sign);
+        assert_eq!(tex_space(&scripted), 0);
+    })
+}
+
+#[test]
+fn mathml_writer_round_trip_test() {
+    use math_render::mathmlwriter;
+
+    TEST_FONT.with(|font| {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testfiles");
+        let mut paths = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension() == Some("xml".as_ref()))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let bytes = std::fs::read(&path).unwrap();
+            let original = mathmlparser::parse(&bytes[..]).expect("invalid parse");
+            let written = mathmlwriter::to_string(&original);
+            let reparsed = mathmlparser::parse(written.as_bytes())
+                .unwrap_or_else(|err| panic!("{:?}: {:?} in {}", path, err, written));
+
+            // writing the parsed output again does not change it any further
+            assert_eq!(mathmlwriter::to_string(&reparsed), written, "{:?}", path);
+
+            let original = math_render::layout(&original, font);
+            let reparsed = math_render::layout(&reparsed, font);
+            assert_eq!(original.extents(), reparsed.extents(), "{:?}", path);
+            assert_eq!(
+                original.advance_width(),
+                reparsed.advance_width(),
+                "{:?}",
+                path
+            );
+        }
+    })
+}
+
+#[cfg(feature = "tex_parser")]
+#[test]
+fn tex_layout_test() {
+    use math_render::texparser;
+
+    TEST_FONT.with(|font| {
+        let tex = r"\sum_{i=1}^n \frac{1}{\sqrt{x_i}} \left( a + b \right)";
+        let tex = texparser::parse(tex).unwrap();
+        let xml = "<munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi>\
+                   </munderover>\
+                   <mfrac><mn>1</mn><msqrt><msub><mi>x</mi><mi>i</mi></msub></msqrt></mfrac>\
+                   <mrow><mo>(</mo><mi>a</mi><mo>+</mo><mi>b</mi><mo>)</mo></mrow>";
+        let mathml = mathmlparser::parse(xml.as_bytes()).unwrap();
+
+        // both front ends produce the same layout for equivalent input
+        let tex = math_render::layout(&tex, font);
+        let mathml = math_render::layout(&mathml, font);
+        assert_eq!(tex.extents(), mathml.extents());
+        assert_eq!(tex.advance_width(), mathml.advance_width());
+    })
+}
+
+#[cfg(feature = "asciimath_parser")]
+#[test]
+fn asciimath_layout_test() {
+    use math_render::asciimathparser;
+
+    TEST_FONT.with(|font| {
+        let asciimath = asciimathparser::parse("sum_(i=1)^n i^3=((n(n+1))/2)^2").unwrap();
+        let xml = "<munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi>\
+                   </munderover><msup><mi>i</mi><mn>3</mn></msup><mo>=</mo>\
+                   <msup><mrow><mo>(</mo><mfrac><mrow><mi>n</mi><mrow><mo>(</mo><mi>n</mi>\
+                   <mo>+</mo><mn>1</mn><mo>)</mo></mrow></mrow><mn>2</mn></mfrac><mo>)</mo></mrow>\
+                   <mn>2</mn></msup>";
+        let mathml = mathmlparser::parse(xml.as_bytes()).unwrap();
+
+        let asciimath = math_render::layout(&asciimath, font);
+        let mathml = math_render::layout(&mathml, font);
+        assert_eq!(asciimath.extents(), mathml.extents());
+        assert_eq!(asciimath.advance_width(), mathml.advance_width());
+    })
+}
@@ -4,6 +4,8 @@ extern crate math_render;
 
 use self::harfbuzz_rs::{Face, Font};
 use math_render::shaper::HarfbuzzShaper;
+#[cfg(feature = "mathml_parser")]
+use math_render::{math_box::MathBox, mathmlparser};
 
 pub fn get_bytes() -> &'static [u8] {
     include_bytes!("testfiles/latinmodern-math.otf")
@@ -16,3 +18,11 @@ thread_local! {
         HarfbuzzShaper::new(font.into())
     };
 }
+
+/// Parses `xml` as MathML and lays it out with `font`.
+#[cfg(feature = "mathml_parser")]
+#[allow(dead_code)]
+pub fn layout(xml: &str, font: &HarfbuzzShaper) -> MathBox {
+    let expr = mathmlparser::parse(xml.as_bytes()).unwrap();
+    math_render::layout(&expr, font)
+}